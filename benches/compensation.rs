@@ -0,0 +1,34 @@
+//! The compensation formulas of the datasheet, which run for every reading.
+
+mod harness;
+
+use bmp280::vectors::DATASHEET_CALIBRATION;
+use bmp280::{RawMeasurement, STANDARD_SEA_LEVEL_PA};
+use std::hint::black_box;
+
+fn main() {
+    let mut runner = harness::Runner::from_args();
+    let calibration = DATASHEET_CALIBRATION;
+    let raw = RawMeasurement {
+        adc_t: 519888,
+        adc_p: 415148,
+    };
+    let (_, fine) = calibration.compensate_temperature(raw.adc_t);
+
+    runner.bench("compensation/temperature", || {
+        black_box(&calibration).compensate_temperature(black_box(raw.adc_t))
+    });
+    runner.bench("compensation/pressure", || {
+        black_box(&calibration).compensate_pressure(black_box(raw.adc_p), black_box(fine))
+    });
+    runner.bench("compensation/measurement", || {
+        black_box(&calibration).compensate(black_box(raw), STANDARD_SEA_LEVEL_PA)
+    });
+
+    let compensation = calibration.compensation();
+    runner.bench("compensation/measurement/precomputed", || {
+        black_box(&compensation).compensate(black_box(raw), STANDARD_SEA_LEVEL_PA)
+    });
+
+    runner.finish();
+}
@@ -0,0 +1,33 @@
+//! The CPU cost of decoding the data registers, and of a whole `read_all_fast()` against the
+//! emulator, with and without SMBus block reads, which return their data in a `Vec`.
+
+mod harness;
+
+use bmp280::emulator::Emulator;
+use bmp280::{Bmp280Builder, RawMeasurement};
+use std::hint::black_box;
+
+fn main() {
+    let mut runner = harness::Runner::from_args();
+
+    let data = [0x65, 0x5A, 0xC0, 0x7E, 0xED, 0x00];
+    runner.bench("decode/from_data_registers", || {
+        RawMeasurement::from_data_registers(black_box(&data))
+    });
+
+    for &block_reads in &[true, false] {
+        let mut sensor = Bmp280Builder::new()
+            .block_reads(block_reads)
+            .build_with(Emulator::new())
+            .expect("Could not build device");
+
+        let name = if block_reads {
+            "decode/read_all_fast/block_reads"
+        } else {
+            "decode/read_all_fast/write_then_read"
+        };
+        runner.bench(name, || sensor.read_all_fast().unwrap());
+    }
+
+    runner.finish();
+}
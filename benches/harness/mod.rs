@@ -0,0 +1,169 @@
+//! A minimal benchmark runner, reporting the median time per iteration of a few samples.
+//!
+//! Run with `cargo bench`, or `cargo bench -- NAME` for the benchmarks whose name contains NAME.
+//!
+//! To guard against regressions, save the results of a known good build with
+//! `cargo bench -- --save-baseline FILE`, then compare later runs against it with
+//! `cargo bench -- --baseline FILE`. A benchmark that got more than 25% slower, or the percentage
+//! given with `--threshold PCT`, fails the run. Only compare results taken on the same machine.
+
+use std::collections::HashMap;
+use std::fs;
+use std::process;
+use std::time::{Duration, Instant};
+
+/// How long each sample runs for.
+const SAMPLE_TIME: Duration = Duration::from_millis(200);
+
+const SAMPLES: usize = 5;
+
+const DEFAULT_THRESHOLD_PERCENT: f64 = 25.;
+
+pub struct Runner {
+    filter: Option<String>,
+    save_baseline: Option<String>,
+    baseline: Option<String>,
+    threshold_percent: f64,
+    /// The median nanoseconds per iteration of each benchmark that ran
+    results: Vec<(String, f64)>,
+}
+
+fn usage(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(2);
+}
+
+fn read_baseline(path: &str) -> HashMap<String, f64> {
+    let contents = fs::read_to_string(path)
+        .unwrap_or_else(|e| usage(&format!("could not read '{}': {}", path, e)));
+
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (name, nanos) = line.rsplit_once(' ')?;
+            Some((name.trim().to_owned(), nanos.parse().ok()?))
+        })
+        .collect()
+}
+
+impl Runner {
+    pub fn from_args() -> Self {
+        let mut runner = Runner {
+            filter: None,
+            save_baseline: None,
+            baseline: None,
+            threshold_percent: DEFAULT_THRESHOLD_PERCENT,
+            results: Vec::new(),
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = |flag: &str| {
+                args.next()
+                    .unwrap_or_else(|| usage(&format!("{} requires a value", flag)))
+            };
+
+            match arg.as_str() {
+                "--save-baseline" => runner.save_baseline = Some(value(&arg)),
+                "--baseline" => runner.baseline = Some(value(&arg)),
+                "--threshold" => {
+                    runner.threshold_percent = value(&arg)
+                        .parse()
+                        .unwrap_or_else(|_| usage("invalid value for --threshold"))
+                }
+                // Cargo passes `--bench`.
+                _ if arg.starts_with("--") => {}
+                _ => runner.filter = Some(arg),
+            }
+        }
+
+        runner
+    }
+
+    /// Time `f`, printing the median time per iteration.
+    pub fn bench<T>(&mut self, name: &str, mut f: impl FnMut() -> T) {
+        if let Some(filter) = &self.filter {
+            if !name.contains(filter.as_str()) {
+                return;
+            }
+        }
+
+        // Warm up, and find how many iterations fill a sample.
+        let mut iterations = 1u64;
+        loop {
+            let started = Instant::now();
+            for _ in 0..iterations {
+                std::hint::black_box(f());
+            }
+            if started.elapsed() >= SAMPLE_TIME / 10 {
+                iterations *= 10;
+                break;
+            }
+            iterations *= 2;
+        }
+
+        let mut samples: Vec<f64> = (0..SAMPLES)
+            .map(|_| {
+                let started = Instant::now();
+                for _ in 0..iterations {
+                    std::hint::black_box(f());
+                }
+                started.elapsed().as_nanos() as f64 / iterations as f64
+            })
+            .collect();
+        samples.sort_by(|a, b| a.total_cmp(b));
+
+        let median = samples[SAMPLES / 2];
+        println!("{:<48} {:>12.1} ns/iter", name, median);
+        self.results.push((name.to_owned(), median));
+    }
+
+    /// Save or compare against the baseline, exiting with an error if anything regressed.
+    pub fn finish(self) {
+        if let Some(path) = &self.save_baseline {
+            // Each bench target saves its own results into the file, so keep those of the others
+            // and of benchmarks filtered out of this run.
+            let mut baseline = if fs::metadata(path).is_ok() {
+                read_baseline(path)
+            } else {
+                HashMap::new()
+            };
+            baseline.extend(self.results.iter().cloned());
+
+            let mut lines: Vec<String> = baseline
+                .iter()
+                .map(|(name, nanos)| format!("{} {:.1}\n", name, nanos))
+                .collect();
+            lines.sort();
+            fs::write(path, lines.concat())
+                .unwrap_or_else(|e| usage(&format!("could not write '{}': {}", path, e)));
+        }
+
+        let path = match &self.baseline {
+            Some(path) => path,
+            None => return,
+        };
+        let baseline = read_baseline(path);
+
+        let mut regressions = 0;
+        for (name, nanos) in &self.results {
+            let before = match baseline.get(name) {
+                Some(&before) if before > 0. => before,
+                _ => continue,
+            };
+
+            let change_percent = (nanos / before - 1.) * 100.;
+            if change_percent > self.threshold_percent {
+                println!(
+                    "REGRESSION {}: {:.1} ns/iter, was {:.1} ({:+.0}%)",
+                    name, nanos, before, change_percent
+                );
+                regressions += 1;
+            }
+        }
+
+        if regressions > 0 {
+            process::exit(1);
+        }
+    }
+}
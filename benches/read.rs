@@ -0,0 +1,34 @@
+//! The whole read path of `measure()` against the emulator, to see the cost of the features
+//! layered on top of the transactions.
+
+mod harness;
+
+use bmp280::emulator::Emulator;
+use bmp280::validate::Validation;
+use bmp280::{Bmp280Builder, ReferencePressure};
+
+fn main() {
+    let mut runner = harness::Runner::from_args();
+
+    let mut sensor = Bmp280Builder::new()
+        .build_with(Emulator::new())
+        .expect("Could not build device");
+    runner.bench("read/measure", || sensor.measure().unwrap());
+
+    let mut sensor = Bmp280Builder::new()
+        .ground_pressure(ReferencePressure::STANDARD_SEA_LEVEL)
+        .validation(Validation::default())
+        .consistency_check(16)
+        .build_with(Emulator::new())
+        .expect("Could not build device");
+    runner.bench("read/measure/validated_double_read", || {
+        sensor.measure().unwrap()
+    });
+
+    let mut sensor = Bmp280Builder::new()
+        .build_with(Emulator::new())
+        .expect("Could not build device");
+    runner.bench("read/pressure_kpa", || sensor.pressure_kpa().unwrap());
+
+    runner.finish();
+}
@@ -0,0 +1,44 @@
+//! Serializing readings for the outputs: MAVLink frames and CSV logs.
+
+mod harness;
+
+use bmp280::csv;
+use bmp280::mavlink::{Altitude, MavlinkEncoder, ScaledPressure};
+use bmp280::vectors::DATASHEET_CALIBRATION;
+use std::hint::black_box;
+
+fn main() {
+    let mut runner = harness::Runner::from_args();
+    let mut encoder = MavlinkEncoder::new(1, 1);
+
+    let pressure = ScaledPressure {
+        time_boot_ms: 123_456,
+        press_abs: 1006.53,
+        press_diff: 0.,
+        temperature: 2508,
+    };
+    runner.bench("sinks/mavlink/scaled_pressure", || {
+        encoder.scaled_pressure(black_box(&pressure))
+    });
+
+    let altitude = Altitude {
+        time_usec: 123_456_000,
+        altitude_monotonic: 56.2,
+        altitude_amsl: 56.2,
+        altitude_local: 0.,
+        altitude_relative: 0.,
+        altitude_terrain: f32::NAN,
+        bottom_clearance: f32::NAN,
+    };
+    runner.bench("sinks/mavlink/altitude", || {
+        encoder.altitude(black_box(&altitude))
+    });
+
+    let mut line = Vec::with_capacity(256);
+    runner.bench("sinks/csv/calibration", || {
+        line.clear();
+        csv::write_calibration(&mut line, "outdoor", black_box(&DATASHEET_CALIBRATION)).unwrap();
+    });
+
+    runner.finish();
+}
@@ -0,0 +1,31 @@
+//! Measures the latency of `Bmp280::read_all_fast()` on the bus the sensor is on.
+
+use bmp280::latency::Latency;
+use bmp280::Bmp280Builder;
+use std::time::Instant;
+
+const READS: u32 = 1000;
+
+fn main() {
+    let mut dev = Bmp280Builder::new()
+        .path("/dev/i2c-1")
+        .address(0x77)
+        .build()
+        .expect("Failed to build device");
+
+    let mut latency = Latency::default();
+    for _ in 0..READS {
+        let started = Instant::now();
+        dev.read_all_fast().expect("failed to read");
+        latency.record(started.elapsed());
+    }
+
+    println!(
+        "{} reads: min {:?}, mean {:?}, p99 {:?}, max {:?}",
+        latency.count(),
+        latency.min().unwrap(),
+        latency.mean().unwrap(),
+        latency.p99().unwrap(),
+        latency.max().unwrap()
+    );
+}
@@ -0,0 +1,21 @@
+use bmp280::mavlink::MavlinkOutput;
+use bmp280::Bmp280Builder;
+
+fn main() {
+    let mut dev = Bmp280Builder::new()
+        .path("/dev/i2c-1")
+        .address(0x77)
+        .build()
+        .expect("Failed to build device");
+
+    dev.zero().expect("failed to zero");
+
+    let mut output = MavlinkOutput::udp("127.0.0.1:14550").expect("Failed to open UDP socket");
+
+    loop {
+        output
+            .send_sensor(&mut dev)
+            .expect("Failed to send MAVLink messages");
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+}
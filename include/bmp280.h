@@ -0,0 +1,76 @@
+/* Generated by cbindgen from src/ffi.rs, see cbindgen.toml. Do not edit by hand.
+ * The library is built with `cargo rustc --lib --release --crate-type cdylib --features ffi`. */
+
+#ifndef BMP280_H
+#define BMP280_H
+
+#include <stdint.h>
+
+/**
+ * A sensor opened by `bmp280_open()`, only used through a pointer.
+ */
+typedef struct bmp280 bmp280;
+
+/**
+ * A reading of `bmp280_read()`.
+ */
+typedef struct bmp280_measurement {
+  float temperature_celsius;
+  float pressure_kpa;
+  /**
+   * Relative to the pressure at `bmp280_zero()`, NaN before it was called
+   */
+  float altitude_m;
+} bmp280_measurement;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Open and set up the sensor at `address` on the I2C device at `path`, like `Bmp280Builder`
+ * with its defaults otherwise.
+ *
+ * Returns null and sets `errno` if there is no sensor there or it can't be set up.
+ *
+ * # Safety
+ *
+ * `path` must be null or point to a NUL terminated string.
+ */
+bmp280 *bmp280_open(const char *path, uint16_t address);
+
+/**
+ * Take a reading of the sensor into `measurement`.
+ *
+ * # Safety
+ *
+ * `sensor` must be null or returned by `bmp280_open()` and not closed yet, and `measurement`
+ * null or valid for writes. Neither may be used by another thread during the call.
+ */
+int bmp280_read(bmp280 *sensor, bmp280_measurement *measurement);
+
+/**
+ * Take the current pressure as the reference of the altitudes of later readings.
+ *
+ * # Safety
+ *
+ * `sensor` must be null or returned by `bmp280_open()` and not closed yet, and not be used by
+ * another thread during the call.
+ */
+int bmp280_zero(bmp280 *sensor);
+
+/**
+ * Close the device of the sensor and free it. Does nothing for null.
+ *
+ * # Safety
+ *
+ * `sensor` must be null or returned by `bmp280_open()` and not closed yet. It can't be used
+ * afterwards.
+ */
+void bmp280_close(bmp280 *sensor);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* BMP280_H */
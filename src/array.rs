@@ -0,0 +1,189 @@
+//! Several labeled sensors that are read together.
+//!
+//! ```ignore
+//! use bmp280::{array::SensorArray, Bmp280Builder};
+//!
+//! let mut array = SensorArray::new();
+//! array.add("indoor", Bmp280Builder::new().address(0x76).build()?);
+//! array.add("outdoor", Bmp280Builder::new().address(0x77).build()?);
+//!
+//! for (label, measurement) in array.measure_all() {
+//!     println!("{}: {:?}", label, measurement);
+//! }
+//! ```
+
+use crate::sampler::Source;
+use crate::{Bmp280, Error, Measurement, Result};
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::LinuxI2CDevice;
+use std::thread;
+use std::time::Instant;
+
+pub struct SensorArray<D = LinuxI2CDevice> {
+    sensors: Vec<(String, Bmp280<D>)>,
+}
+
+impl<D> Default for SensorArray<D> {
+    fn default() -> Self {
+        SensorArray {
+            sensors: Vec::new(),
+        }
+    }
+}
+
+impl<D> SensorArray<D>
+where
+    D: I2CDevice,
+    Error: From<D::Error>,
+{
+    pub fn new() -> Self {
+        SensorArray::default()
+    }
+
+    /// Add a sensor, which is given the label unless it already has one.
+    pub fn add(&mut self, label: impl Into<String>, mut sensor: Bmp280<D>) -> &mut Self {
+        let label = label.into();
+        if sensor.label().is_none() && !label.is_empty() {
+            sensor.set_label(&label);
+        }
+
+        self.sensors.push((label, sensor));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.sensors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sensors.is_empty()
+    }
+
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        self.sensors.iter().map(|(label, _)| label.as_str())
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&str, &mut Bmp280<D>)> {
+        self.sensors
+            .iter_mut()
+            .map(|(label, sensor)| (label.as_str(), sensor))
+    }
+
+    /// Take a measurement from each sensor in turn, in the order they were added. A failing sensor
+    /// does not keep the others from being read.
+    pub fn measure_all(&mut self) -> Vec<(String, Result<Measurement>)> {
+        self.sensors
+            .iter_mut()
+            .map(|(label, sensor)| (label.clone(), sensor.measure()))
+            .collect()
+    }
+
+    /// Take a forced measurement from every sensor, with the conversions of all sensors running at
+    /// the same time: the conversions are started on each sensor in turn, then each is read once
+    /// it has finished. A scan of N sensors takes about as long as a single forced measurement,
+    /// instead of N times as long with `.forced_measurement()` on each. Sensors in normal mode are
+    /// put back into it afterwards, and a failing sensor does not keep the others from being read.
+    ///
+    /// ```
+    /// use bmp280::array::SensorArray;
+    /// use bmp280::emulator::Emulator;
+    /// use bmp280::Bmp280Builder;
+    ///
+    /// let mut array = SensorArray::new();
+    /// for label in &["indoor", "outdoor"] {
+    ///     let emulator = Emulator::new();
+    ///     emulator.set_conversion_reads(3);
+    ///     let sensor = Bmp280Builder::new()
+    ///         .build_with(emulator)
+    ///         .expect("Could not build device");
+    ///     array.add(*label, sensor);
+    /// }
+    ///
+    /// for (label, measurement) in array.read_all_sensors() {
+    ///     let measurement = measurement.unwrap();
+    ///     assert!(measurement.fresh);
+    ///     assert_eq!(measurement.label.as_deref(), Some(label.as_str()));
+    /// }
+    /// ```
+    pub fn read_all_sensors(&mut self) -> Vec<(String, Result<Measurement>)> {
+        struct Conversion {
+            control: u8,
+            finished: bool,
+        }
+
+        let mut conversions: Vec<Result<Conversion>> = self
+            .sensors
+            .iter_mut()
+            .map(|(_, sensor)| {
+                sensor.start_forced_conversion().map(|control| Conversion {
+                    control,
+                    finished: false,
+                })
+            })
+            .collect();
+
+        // The sensor with the fastest conversions sets the pace.
+        let interval = self
+            .sensors
+            .iter()
+            .map(|(_, sensor)| sensor.poll_interval())
+            .min()
+            .unwrap_or_default();
+
+        let started = Instant::now();
+        loop {
+            thread::sleep(interval);
+
+            let mut converting = false;
+            for ((_, sensor), conversion) in self.sensors.iter_mut().zip(&mut conversions) {
+                let pending = match conversion {
+                    Ok(pending) if !pending.finished => pending,
+                    _ => continue,
+                };
+                match sensor.conversion_finished(started) {
+                    Ok(finished) => {
+                        pending.finished = finished;
+                        converting |= !finished;
+                    }
+                    Err(e) => *conversion = Err(e),
+                }
+            }
+
+            if !converting {
+                break;
+            }
+        }
+
+        self.sensors
+            .iter_mut()
+            .zip(conversions)
+            .map(|((label, sensor), conversion)| {
+                let measurement = conversion
+                    .and_then(|conversion| sensor.finish_forced_measurement(conversion.control));
+                (label.clone(), measurement)
+            })
+            .collect()
+    }
+}
+
+impl<D> Source for SensorArray<D>
+where
+    D: I2CDevice,
+    Error: From<D::Error>,
+{
+    type Output = Vec<(String, Result<Measurement>)>;
+
+    fn sample(&mut self) -> Self::Output {
+        self.measure_all()
+    }
+
+    fn sample_numbered(&mut self, sequence: u64) -> Self::Output {
+        let mut readings = self.measure_all();
+        for (_, reading) in &mut readings {
+            if let Ok(measurement) = reading {
+                measurement.sequence = Some(sequence);
+            }
+        }
+        readings
+    }
+}
@@ -0,0 +1,198 @@
+//! Models of the atmosphere for converting pressures into altitudes.
+//!
+//! By default altitudes come from the barometric formula of the datasheet, which assumes the
+//! temperature falls by 6.5 K/km from 15 degrees celsius at the reference pressure, as in the
+//! troposphere of the International Standard Atmosphere. That is off where the air is much warmer
+//! or colder, and above 11 km, where the temperature stops falling. An `Atmosphere` sets the
+//! temperatures, and can have up to 4 layers of different lapse rates. Like those of the standard
+//! atmospheres, its altitudes are geopotential, which above 10 km fall short of the geometric ones
+//! by a few tens of meters:
+//!
+//! ```
+//! use bmp280::atmosphere::Atmosphere;
+//!
+//! assert!((Atmosphere::ISA.exponent() - 0.190263).abs() < 1e-5);
+//!
+//! let standard = Atmosphere::US_STANDARD_1976;
+//! assert!((standard.altitude_m(22_632., 101_325.) - 11_000.).abs() < 1.);
+//! assert!((standard.altitude_m(2_511.0, 101_325.) - 25_000.).abs() < 1.);
+//! assert!((standard.pressure_pa(36_000., 101_325.) - 484.32).abs() < 0.1);
+//!
+//! // A hot day at a site in the mountains, 25 degrees celsius at the reference pressure
+//! let hot = Atmosphere::troposphere(298.15, 0.0065);
+//! assert!(hot.altitude_m(80_000., 85_000.) > Atmosphere::ISA.altitude_m(80_000., 85_000.));
+//! ```
+//!
+//! Set one on a sensor with `Bmp280::set_atmosphere()` or `Bmp280Builder::atmosphere()`.
+
+/// g·M/R of dry air in K/m: the standard acceleration of gravity times the molar mass of air,
+/// over the universal gas constant.
+pub const HYDROSTATIC_CONSTANT: f32 = 0.034_163_2;
+
+/// The most layers an atmosphere can have.
+const MAX_LAYERS: usize = 4;
+
+/// A layer of the atmosphere in which the temperature changes linearly with the altitude.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layer {
+    /// The altitude of the bottom of the layer, relative to the reference pressure
+    pub base_altitude_m: f32,
+    /// The temperature at the bottom of the layer, in K
+    pub base_temperature_k: f32,
+    /// How much the temperature falls per meter of height, in K/m; negative where it rises
+    pub lapse_rate_k_per_m: f32,
+}
+
+const fn layer(base_altitude_m: f32, base_temperature_k: f32, lapse_rate_k_per_m: f32) -> Layer {
+    Layer {
+        base_altitude_m,
+        base_temperature_k,
+        lapse_rate_k_per_m,
+    }
+}
+
+/// The layers of an atmosphere, from the reference pressure up. The top layer extends upwards
+/// without limit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Atmosphere {
+    layers: [Layer; MAX_LAYERS],
+    len: usize,
+    /// g·M/R, `HYDROSTATIC_CONSTANT` unless tuned. The exponent of the barometric formula is the
+    /// lapse rate over this, 0.190263 in the standard troposphere.
+    pub hydrostatic_k_per_m: f32,
+}
+
+impl Atmosphere {
+    /// The troposphere of the International Standard Atmosphere, the model of the barometric
+    /// formula, for altitudes below 11 km.
+    pub const ISA: Atmosphere = Atmosphere::troposphere(288.15, 0.0065);
+
+    /// The US Standard Atmosphere 1976 up to 47 km: the troposphere of the ISA, an isothermal layer
+    /// from 11 to 20 km, and two layers in which the temperature rises again, for stratospheric
+    /// balloons.
+    pub const US_STANDARD_1976: Atmosphere = Atmosphere {
+        layers: [
+            layer(0., 288.15, 0.0065),
+            layer(11_000., 216.65, 0.),
+            layer(20_000., 216.65, -0.001),
+            layer(32_000., 228.65, -0.0028),
+        ],
+        len: 4,
+        hydrostatic_k_per_m: HYDROSTATIC_CONSTANT,
+    };
+
+    /// A single layer with the temperature in K at the reference pressure and its lapse rate.
+    pub const fn troposphere(base_temperature_k: f32, lapse_rate_k_per_m: f32) -> Self {
+        let layer = layer(0., base_temperature_k, lapse_rate_k_per_m);
+
+        Atmosphere {
+            layers: [layer; MAX_LAYERS],
+            len: 1,
+            hydrostatic_k_per_m: HYDROSTATIC_CONSTANT,
+        }
+    }
+
+    /// An atmosphere of the given layers, from the bottom up, the first one at altitude 0. `None`
+    /// if there are none, or more than 4, or they aren't in order of altitude.
+    pub fn new(layers: &[Layer]) -> Option<Self> {
+        let first = *layers.first()?;
+        if layers.len() > MAX_LAYERS || first.base_altitude_m != 0. {
+            return None;
+        }
+        if layers
+            .windows(2)
+            .any(|pair| pair[1].base_altitude_m <= pair[0].base_altitude_m)
+        {
+            return None;
+        }
+
+        let mut atmosphere = Atmosphere::troposphere(first.base_temperature_k, 0.);
+        atmosphere.layers[..layers.len()].copy_from_slice(layers);
+        atmosphere.len = layers.len();
+
+        Some(atmosphere)
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers[..self.len]
+    }
+
+    /// The exponent of the barometric formula in the bottom layer, its lapse rate over g·M/R.
+    pub fn exponent(&self) -> f32 {
+        self.layers[0].lapse_rate_k_per_m / self.hydrostatic_k_per_m
+    }
+
+    /// The altitude in meters of a pressure relative to a reference pressure, both in Pa.
+    pub fn altitude_m(&self, pressure_pa: f32, reference_pa: f32) -> f32 {
+        let mut base_pa = reference_pa as f64;
+        let mut layers = self.layers().iter().peekable();
+
+        while let Some(layer) = layers.next() {
+            let next = layers.peek();
+            let top_pa =
+                next.map(|next| self.pressure_in_layer(layer, base_pa, next.base_altitude_m));
+
+            match top_pa {
+                Some(top_pa) if (pressure_pa as f64) < top_pa => base_pa = top_pa,
+                _ => return self.altitude_in_layer(layer, base_pa, pressure_pa as f64) as f32,
+            }
+        }
+
+        unreachable!("an atmosphere has at least one layer")
+    }
+
+    /// The pressure in Pa at an altitude in meters, relative to a reference pressure in Pa.
+    ///
+    /// The inverse of `.altitude_m()`, e.g. for the sea level pressure given the pressure at a
+    /// known elevation: `pressure_pa(-elevation_m, pressure_pa)`.
+    pub fn pressure_pa(&self, altitude_m: f32, reference_pa: f32) -> f32 {
+        let mut base_pa = reference_pa as f64;
+        let mut layers = self.layers().iter().peekable();
+
+        while let Some(layer) = layers.next() {
+            match layers.peek() {
+                Some(next) if altitude_m >= next.base_altitude_m => {
+                    base_pa = self.pressure_in_layer(layer, base_pa, next.base_altitude_m)
+                }
+                _ => return self.pressure_in_layer(layer, base_pa, altitude_m) as f32,
+            }
+        }
+
+        unreachable!("an atmosphere has at least one layer")
+    }
+
+    /// The pressure at an altitude, in the layer whose base is at `base_pa`.
+    fn pressure_in_layer(&self, layer: &Layer, base_pa: f64, altitude_m: f32) -> f64 {
+        let height = (altitude_m - layer.base_altitude_m) as f64;
+        let temperature = layer.base_temperature_k as f64;
+        let lapse_rate = layer.lapse_rate_k_per_m as f64;
+        let hydrostatic = self.hydrostatic_k_per_m as f64;
+
+        if lapse_rate == 0. {
+            base_pa * (-hydrostatic * height / temperature).exp()
+        } else {
+            base_pa * (1. - lapse_rate * height / temperature).powf(hydrostatic / lapse_rate)
+        }
+    }
+
+    /// The altitude of a pressure, in the layer whose base is at `base_pa`.
+    fn altitude_in_layer(&self, layer: &Layer, base_pa: f64, pressure_pa: f64) -> f64 {
+        let temperature = layer.base_temperature_k as f64;
+        let lapse_rate = layer.lapse_rate_k_per_m as f64;
+        let hydrostatic = self.hydrostatic_k_per_m as f64;
+
+        let height = if lapse_rate == 0. {
+            temperature / hydrostatic * (base_pa / pressure_pa).ln()
+        } else {
+            temperature / lapse_rate * (1. - (pressure_pa / base_pa).powf(lapse_rate / hydrostatic))
+        };
+
+        layer.base_altitude_m as f64 + height
+    }
+}
+
+impl Default for Atmosphere {
+    fn default() -> Self {
+        Atmosphere::ISA
+    }
+}
@@ -0,0 +1,292 @@
+//! Sampling on a thread of its own, handing the samples over through a bounded queue.
+//!
+//! A consumer that is slower than the sampling, like a sink posting every reading over HTTP, can't
+//! keep up with the samples forever. The `Backpressure` policy decides what happens once the queue
+//! is full, so memory use stays bounded. Dropped samples show up as gaps in
+//! `Measurement::sequence`:
+//!
+//! ```
+//! use bmp280::background::{Background, Backpressure};
+//! use bmp280::emulator::Emulator;
+//! use bmp280::Bmp280Builder;
+//! use std::thread;
+//! use std::time::Duration;
+//!
+//! let sensor = Bmp280Builder::new()
+//!     .build_with(Emulator::new())
+//!     .expect("Could not build device");
+//!
+//! let background = Background {
+//!     interval: Duration::from_millis(1),
+//!     capacity: 4,
+//!     backpressure: Backpressure::DropOldest,
+//! };
+//! let mut sampler = background.spawn(sensor);
+//!
+//! // A slow consumer misses samples, but only ever has the latest 4 waiting for it.
+//! thread::sleep(Duration::from_millis(50));
+//! let first = sampler.recv().unwrap().unwrap();
+//! assert!(first.sequence.unwrap() > 0);
+//! assert!(sampler.dropped() > 0);
+//!
+//! // Gives the sensor back for use on this thread.
+//! let _sensor = sampler.stop();
+//! ```
+
+use crate::sampler::{Sampler, Source, DEFAULT_INTERVAL};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// What the sampling thread does with a new sample while the queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backpressure {
+    /// Wait for the consumer to take a sample, which delays the sampling
+    Block,
+    /// Drop the oldest sample in the queue to make room
+    DropOldest,
+    /// Drop the new sample
+    DropNewest,
+    /// Keep only the latest sample, whatever the capacity, for consumers that only care about the
+    /// current reading
+    CoalesceToLatest,
+}
+
+/// The settings of a background sampler.
+#[derive(Debug, Clone)]
+pub struct Background {
+    /// The time between the start of two samples
+    pub interval: Duration,
+    /// The most samples waiting for the consumer, at least 1
+    pub capacity: usize,
+    pub backpressure: Backpressure,
+}
+
+impl Default for Background {
+    /// Sampling every 250ms, blocking once 64 samples are waiting.
+    fn default() -> Self {
+        Background {
+            interval: DEFAULT_INTERVAL,
+            capacity: 64,
+            backpressure: Backpressure::Block,
+        }
+    }
+}
+
+impl Background {
+    /// Start sampling `source` on a new thread.
+    pub fn spawn<S>(&self, source: S) -> BackgroundSampler<S>
+    where
+        S: Source + Send + 'static,
+        S::Output: Send + 'static,
+    {
+        let capacity = match self.backpressure {
+            Backpressure::CoalesceToLatest => 1,
+            _ => self.capacity.max(1),
+        };
+        let queue = Arc::new(Queue {
+            state: Mutex::new(State {
+                samples: VecDeque::with_capacity(capacity),
+                dropped: 0,
+                finished: false,
+                stopped: false,
+            }),
+            changed: Condvar::new(),
+            capacity,
+            backpressure: self.backpressure,
+        });
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let queue = Arc::clone(&queue);
+            let stop = Arc::clone(&stop);
+            let interval = self.interval;
+
+            thread::spawn(move || {
+                let _finish = Finish(&queue);
+                let mut source = source;
+                {
+                    let mut sampler = Sampler::new(&mut source);
+                    sampler.interval(interval).stop_flag(&stop);
+                    for sample in sampler {
+                        if !queue.push(sample) {
+                            break;
+                        }
+                    }
+                }
+
+                source
+            })
+        };
+
+        BackgroundSampler {
+            queue,
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+struct State<T> {
+    samples: VecDeque<T>,
+    /// Samples dropped by the backpressure policy
+    dropped: u64,
+    /// Whether the sampling thread is done, and won't add more samples
+    finished: bool,
+    /// Whether the consumer asked the sampling thread to stop
+    stopped: bool,
+}
+
+struct Queue<T> {
+    state: Mutex<State<T>>,
+    /// Notified when a sample is added or taken, or sampling ends
+    changed: Condvar,
+    capacity: usize,
+    backpressure: Backpressure,
+}
+
+impl<T> Queue<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        // A panic while holding the lock leaves the queue consistent, so the poison can be ignored.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Add a sample by the backpressure policy, returning false if sampling should end instead.
+    fn push(&self, sample: T) -> bool {
+        let mut state = self.lock();
+
+        while state.samples.len() >= self.capacity && !state.stopped {
+            match self.backpressure {
+                Backpressure::Block => {
+                    state = self.changed.wait(state).unwrap_or_else(|e| e.into_inner());
+                }
+                Backpressure::DropOldest | Backpressure::CoalesceToLatest => {
+                    state.samples.pop_front();
+                    state.dropped += 1;
+                }
+                Backpressure::DropNewest => {
+                    state.dropped += 1;
+                    return true;
+                }
+            }
+        }
+        if state.stopped {
+            return false;
+        }
+
+        state.samples.push_back(sample);
+        self.changed.notify_all();
+        true
+    }
+}
+
+/// Marks the queue finished when the sampling thread ends, even by a panic, so the consumer doesn't
+/// wait for it forever.
+struct Finish<'a, T>(&'a Queue<T>);
+
+impl<T> Drop for Finish<'_, T> {
+    fn drop(&mut self) {
+        self.0.lock().finished = true;
+        self.0.changed.notify_all();
+    }
+}
+
+/// A sampler running on its own thread, started by `Background::spawn()`. Dropping it stops the
+/// sampling.
+pub struct BackgroundSampler<S: Source> {
+    queue: Arc<Queue<S::Output>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<S>>,
+}
+
+impl<S: Source> BackgroundSampler<S> {
+    /// Wait for the next sample. `None` once sampling has ended and all samples are taken.
+    pub fn recv(&mut self) -> Option<S::Output> {
+        let mut state = self.queue.lock();
+        loop {
+            if let Some(sample) = self.pop(&mut state) {
+                return Some(sample);
+            }
+            if state.finished {
+                return None;
+            }
+            state = self
+                .queue
+                .changed
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Wait up to `timeout` for the next sample.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<S::Output> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.queue.lock();
+        loop {
+            if let Some(sample) = self.pop(&mut state) {
+                return Some(sample);
+            }
+            let now = Instant::now();
+            if state.finished || now >= deadline {
+                return None;
+            }
+            state = match self.queue.changed.wait_timeout(state, deadline - now) {
+                Ok((state, _)) => state,
+                Err(e) => e.into_inner().0,
+            };
+        }
+    }
+
+    /// The next sample if there is one waiting, without waiting for it.
+    pub fn try_recv(&mut self) -> Option<S::Output> {
+        let mut state = self.queue.lock();
+        self.pop(&mut state)
+    }
+
+    /// The number of samples waiting to be taken.
+    pub fn pending(&self) -> usize {
+        self.queue.lock().samples.len()
+    }
+
+    /// The number of samples dropped because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.queue.lock().dropped
+    }
+
+    /// Stop sampling and give back the source. Samples still waiting are discarded.
+    pub fn stop(mut self) -> S {
+        self.shut_down().expect("the sampling thread panicked")
+    }
+
+    fn pop(&self, state: &mut State<S::Output>) -> Option<S::Output> {
+        let sample = state.samples.pop_front()?;
+        self.queue.changed.notify_all();
+        Some(sample)
+    }
+
+    /// Stop the sampling thread and wait for it, returning the source unless it panicked or was
+    /// already shut down.
+    fn shut_down(&mut self) -> Option<S> {
+        self.stop.store(true, Ordering::SeqCst);
+        self.queue.lock().stopped = true;
+        self.queue.changed.notify_all();
+
+        self.thread.take()?.join().ok()
+    }
+}
+
+impl<S: Source> Iterator for BackgroundSampler<S> {
+    type Item = S::Output;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv()
+    }
+}
+
+impl<S: Source> Drop for BackgroundSampler<S> {
+    fn drop(&mut self) {
+        self.shut_down();
+    }
+}
@@ -0,0 +1,408 @@
+//! A small command line parser, supporting `--flag value` and `--flag=value`.
+
+use bmp280::array::SensorArray;
+use bmp280::config::Config;
+use bmp280::correction::{SelfHeating, ThermalCorrection};
+use bmp280::observe::{Level, Logger};
+use bmp280::retry::RetryPolicy;
+use bmp280::validate::Validation;
+use bmp280::{Bmp280, Bmp280Builder};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// An invalid command line.
+#[derive(Debug)]
+pub struct ArgError(pub String);
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ArgError {}
+
+pub type ArgResult<T> = std::result::Result<T, ArgError>;
+
+/// A single command line token.
+pub enum Arg {
+    /// `--name` or `--name=value`
+    Flag(String, Option<String>),
+    /// Anything that isn't a flag
+    Positional(String),
+}
+
+pub struct Parser {
+    args: std::vec::IntoIter<String>,
+}
+
+impl Parser {
+    pub fn new(args: Vec<String>) -> Self {
+        Parser {
+            args: args.into_iter(),
+        }
+    }
+
+    pub fn next_arg(&mut self) -> Option<Arg> {
+        let arg = self.args.next()?;
+
+        if let Some(flag) = arg.strip_prefix("--") {
+            match flag.find('=') {
+                Some(i) => Some(Arg::Flag(flag[..i].into(), Some(flag[i + 1..].into()))),
+                None => Some(Arg::Flag(flag.into(), None)),
+            }
+        } else {
+            Some(Arg::Positional(arg))
+        }
+    }
+
+    /// Take the value for a flag, either the inline `--flag=value` or the next token.
+    pub fn value(&mut self, flag: &str, inline: Option<String>) -> ArgResult<String> {
+        if let Some(value) = inline {
+            return Ok(value);
+        }
+
+        self.args
+            .next()
+            .ok_or_else(|| ArgError(format!("--{} requires a value", flag)))
+    }
+}
+
+pub const SENSOR_OPTIONS: &str =
+    "    --config FILE       Read the path, address, oversampling, filter, standby time, mode and
+                        reference pressure of the sensor from the TOML file FILE
+    --path PATH         I2C device path (default: /dev/i2c-1)
+    --address ADDR      I2C address (default: 0x77)
+    --any-address       Accept addresses other than 0x76 and 0x77
+    --retries N         Retry failed I2C transactions up to N times (default: 0)
+    --timeout TIME      Give up on a failing I2C transaction after TIME, including retries
+    --bus-timeout TIME  Set the timeout of the I2C adapter in the kernel, e.g. for peripherals
+                        that stretch the clock, rounded up to 10ms
+    --bus-retries N     Set how often the I2C adapter retries unacknowledged transactions
+    --recover N         Reset and set up the sensor again up to N times when a reading fails with
+                        a bus error (default: 0)
+    --verify-writes     Read back every register written to the sensor
+    --validate          Reject readings outside the operating range of the sensor
+    --double-read TOL   Read the data registers twice and only accept readings that agree within
+                        TOL ADC counts
+    --thermal-correction FILE
+                        Correct the pressure for the thermal bias of the board, with the
+                        polynomial in the TOML file FILE
+    --self-heating C    Subtract the warming of the die from the temperature, C degrees celsius
+                        when converting without pause and less with longer standby times
+    --log LEVEL         Write what the sensor is doing to stderr, LEVEL is info, debug or trace";
+
+pub const MULTI_SENSOR_OPTIONS: &str = "    --sensor SPEC       Read this sensor instead, may be repeated for several sensors. SPEC is a
+                        comma separated list of bus=N or path=PATH, addr=ADDR and label=LABEL,
+                        e.g. bus=1,addr=0x76,label=outdoor";
+
+/// One `--sensor` specification.
+struct SensorSpec {
+    path: Option<PathBuf>,
+    address: Option<u16>,
+    label: Option<String>,
+}
+
+impl SensorSpec {
+    fn parse(s: &str) -> ArgResult<Self> {
+        let mut spec = SensorSpec {
+            path: None,
+            address: None,
+            label: None,
+        };
+
+        for part in s.split(',') {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| ArgError(format!("invalid sensor specification '{}'", s)))?;
+
+            match key {
+                "bus" => {
+                    let bus: u32 = parse_number("sensor", value)?;
+                    spec.path = Some(format!("/dev/i2c-{}", bus).into());
+                }
+                "path" => spec.path = Some(value.into()),
+                "addr" | "address" => spec.address = Some(parse_address(value)?),
+                "label" => spec.label = Some(value.into()),
+                _ => return Err(ArgError(format!("unknown sensor option '{}'", key))),
+            }
+        }
+
+        Ok(spec)
+    }
+}
+
+/// Options selecting the sensor, shared by all commands.
+#[derive(Default)]
+pub struct SensorArgs {
+    config: Option<Config>,
+    path: Option<PathBuf>,
+    address: Option<u16>,
+    any_address: bool,
+    retries: u32,
+    timeout: Option<Duration>,
+    bus_timeout: Option<Duration>,
+    bus_retries: Option<u32>,
+    recover: u32,
+    verify_writes: bool,
+    validate: bool,
+    double_read: Option<u32>,
+    thermal_correction: Option<ThermalCorrection>,
+    self_heating: Option<f32>,
+    log: Option<Level>,
+    sensors: Vec<SensorSpec>,
+}
+
+impl SensorArgs {
+    /// Handle the flag if it is a sensor option, returning whether it was one.
+    pub fn parse(
+        &mut self,
+        parser: &mut Parser,
+        flag: &str,
+        inline: Option<String>,
+    ) -> ArgResult<bool> {
+        match flag {
+            "config" => {
+                let path = parser.value(flag, inline)?;
+                let config = Config::load(&path)
+                    .map_err(|e| ArgError(format!("could not read '{}': {}", path, e)))?;
+                self.config = Some(config);
+            }
+            "path" => self.path = Some(parser.value(flag, inline)?.into()),
+            "address" => self.address = Some(parse_address(&parser.value(flag, inline)?)?),
+            "any-address" => self.any_address = true,
+            "retries" => self.retries = parse_number(flag, &parser.value(flag, inline)?)?,
+            "timeout" => self.timeout = Some(parse_duration(&parser.value(flag, inline)?)?),
+            "bus-timeout" => self.bus_timeout = Some(parse_duration(&parser.value(flag, inline)?)?),
+            "bus-retries" => {
+                self.bus_retries = Some(parse_number(flag, &parser.value(flag, inline)?)?)
+            }
+            "recover" => self.recover = parse_number(flag, &parser.value(flag, inline)?)?,
+            "verify-writes" => self.verify_writes = true,
+            "log" => self.log = Some(parse_level(&parser.value(flag, inline)?)?),
+            "validate" => self.validate = true,
+            "double-read" => {
+                self.double_read = Some(parse_number(flag, &parser.value(flag, inline)?)?)
+            }
+            "thermal-correction" => {
+                let path = parser.value(flag, inline)?;
+                let correction = ThermalCorrection::load(&path)
+                    .map_err(|e| ArgError(format!("could not read '{}': {}", path, e)))?;
+                self.thermal_correction = Some(correction);
+            }
+            "self-heating" => {
+                self.self_heating = Some(parse_number(flag, &parser.value(flag, inline)?)?)
+            }
+            "sensor" => self
+                .sensors
+                .push(SensorSpec::parse(&parser.value(flag, inline)?)?),
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    pub fn build(&self) -> bmp280::Result<Bmp280> {
+        self.builder().build()
+    }
+
+    /// The builder for the sensor selected by `--path` and `--address`.
+    pub fn builder(&self) -> Bmp280Builder {
+        self.builder_for(self.path.as_ref(), self.address)
+    }
+
+    /// Build all sensors given with `--sensor`, or the one selected by `--path` and `--address`.
+    pub fn build_array(&self) -> bmp280::Result<SensorArray> {
+        let mut array = SensorArray::new();
+
+        if self.sensors.is_empty() {
+            array.add("", self.build()?);
+        }
+
+        for (i, spec) in self.sensors.iter().enumerate() {
+            let label = spec
+                .label
+                .clone()
+                .unwrap_or_else(|| format!("sensor{}", i + 1));
+            array.add(
+                label,
+                self.builder_for(spec.path.as_ref(), spec.address).build()?,
+            );
+        }
+
+        Ok(array)
+    }
+
+    fn builder_for(&self, path: Option<&PathBuf>, address: Option<u16>) -> Bmp280Builder {
+        let mut builder = match &self.config {
+            Some(config) => Bmp280Builder::from(config),
+            None => Bmp280Builder::new(),
+        };
+
+        if let Some(path) = path {
+            builder = builder.path(path);
+        }
+        if let Some(address) = address {
+            builder = builder.address(address);
+        }
+        if self.any_address {
+            builder = builder.allow_any_address();
+        }
+        builder = builder
+            .retry_policy(RetryPolicy::new(self.retries.saturating_add(1)))
+            .retry_division_by_zero(true)
+            .recovery(self.recover)
+            .verify_writes(self.verify_writes);
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.bus_timeout {
+            builder = builder.bus_timeout_ms(timeout.as_millis().min(u32::MAX as u128) as u32);
+        }
+        if let Some(retries) = self.bus_retries {
+            builder = builder.bus_retries(retries);
+        }
+        if self.validate {
+            builder = builder.validation(Validation::default());
+        }
+        if let Some(tolerance) = self.double_read {
+            builder = builder.consistency_check(tolerance);
+        }
+        if let Some(correction) = &self.thermal_correction {
+            builder = builder.thermal_correction(correction.clone());
+        }
+        if let Some(continuous_celsius) = self.self_heating {
+            builder = builder.self_heating(SelfHeating { continuous_celsius });
+        }
+        if let Some(level) = self.log {
+            builder = builder.observer(Arc::new(Logger::new(level)));
+        }
+
+        builder
+    }
+
+    /// Whether output rows need to be labeled with the sensor they come from.
+    pub fn labeled(&self) -> bool {
+        !self.sensors.is_empty()
+    }
+}
+
+/// Parse an I2C address in decimal or `0x` hexadecimal.
+pub fn parse_address(s: &str) -> ArgResult<u16> {
+    let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+
+    parsed.map_err(|_| ArgError(format!("invalid address '{}'", s)))
+}
+
+/// Parse a duration such as `250ms`, `10s`, `10m` or `2h`. A bare number is in seconds.
+pub fn parse_duration(s: &str) -> ArgResult<Duration> {
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| ArgError(format!("invalid duration '{}'", s)))?;
+
+    let seconds = match unit {
+        "ms" => number / 1000.,
+        "" | "s" => number,
+        "m" => number * 60.,
+        "h" => number * 3600.,
+        _ => return Err(ArgError(format!("invalid duration unit in '{}'", s))),
+    };
+
+    Duration::try_from_secs_f64(seconds)
+        .map_err(|_| ArgError(format!("duration '{}' is too long", s)))
+}
+
+fn parse_level(s: &str) -> ArgResult<Level> {
+    match s {
+        "info" => Ok(Level::Info),
+        "debug" => Ok(Level::Debug),
+        "trace" => Ok(Level::Trace),
+        _ => Err(ArgError(format!("unknown log level '{}'", s))),
+    }
+}
+
+pub fn parse_number<T: std::str::FromStr>(flag: &str, s: &str) -> ArgResult<T> {
+    s.parse()
+        .map_err(|_| ArgError(format!("invalid value '{}' for --{}", s, flag)))
+}
+
+pub fn unknown(arg: Arg) -> ArgError {
+    match arg {
+        Arg::Flag(flag, _) => ArgError(format!("unknown option --{}", flag)),
+        Arg::Positional(value) => ArgError(format!("unexpected argument '{}'", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_duration("10s").unwrap(), Duration::from_secs(10));
+        assert_eq!(parse_duration("1.5").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parse_duration("10m").unwrap(), Duration::from_secs(600));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+    }
+
+    #[test]
+    fn invalid_durations() {
+        for (duration, error) in [
+            ("", "invalid duration ''"),
+            ("ms", "invalid duration 'ms'"),
+            ("1.2.3s", "invalid duration '1.2.3s'"),
+            ("-1s", "invalid duration '-1s'"),
+            ("10d", "invalid duration unit in '10d'"),
+            ("10 s", "invalid duration unit in '10 s'"),
+            ("1e300h", "invalid duration unit in '1e300h'"),
+            (
+                "99999999999999999999h",
+                "duration '99999999999999999999h' is too long",
+            ),
+        ] {
+            assert_eq!(parse_duration(duration).unwrap_err().0, error);
+        }
+    }
+
+    #[test]
+    fn sensor_specs() {
+        let spec = SensorSpec::parse("bus=0,addr=0x76,label=outdoor").unwrap();
+        assert_eq!(spec.path, Some(PathBuf::from("/dev/i2c-0")));
+        assert_eq!(spec.address, Some(0x76));
+        assert_eq!(spec.label.as_deref(), Some("outdoor"));
+
+        let spec = SensorSpec::parse("path=/dev/i2c-3").unwrap();
+        assert_eq!(spec.path, Some(PathBuf::from("/dev/i2c-3")));
+        assert_eq!(spec.address, None);
+        assert_eq!(spec.label, None);
+
+        assert_eq!(
+            SensorSpec::parse("address=119").unwrap().address,
+            Some(0x77)
+        );
+    }
+
+    #[test]
+    fn invalid_sensor_specs() {
+        for (spec, error) in [
+            ("", "invalid sensor specification ''"),
+            ("bus=1,0x76", "invalid sensor specification 'bus=1,0x76'"),
+            ("bus=one", "invalid value 'one' for --sensor"),
+            ("addr=0x1g", "invalid address '0x1g'"),
+            ("port=1", "unknown sensor option 'port'"),
+        ] {
+            assert_eq!(SensorSpec::parse(spec).err().unwrap().0, error);
+        }
+    }
+}
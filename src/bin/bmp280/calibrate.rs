@@ -0,0 +1,199 @@
+//! `bmp280 calibrate`: compute and store the local sea level pressure.
+
+use crate::args::{self, Arg, ArgError, ArgResult, Parser, SensorArgs};
+use crate::reference::{self, Reference};
+use bmp280::correction::PressureCorrection;
+use std::path::PathBuf;
+
+const DEFAULT_COUNT: u32 = 8;
+
+const FEET_PER_METER: f32 = 3.28084;
+
+/// The elevation where the pressure of the barometric formula reaches zero, from which there is no
+/// sea level pressure.
+const MAX_ELEVATION_M: f32 = 44330.;
+
+pub fn usage() -> String {
+    format!(
+        "\
+Usage: bmp280 calibrate --elevation ELEVATION [OPTIONS]
+
+Compute the sea level pressure from the current pressure and the known elevation of the sensor, and
+store it in the reference file. `bmp280 read` then reports altitudes above sea level. The offsets
+in the reference file are applied to the readings, and kept unless changed with the options.
+
+Options:
+    --elevation ELEV    Elevation of the sensor, e.g. 214m or 702ft
+    --count N           Number of readings to average (default: {})
+    --temperature-offset C
+                        Add C degrees celsius to all temperature readings, e.g. -1.5 for a sensor
+                        warmed by its board
+    --pressure-offset PA
+                        Add PA Pa to all pressure readings, to match a reference instrument
+    --pressure-points REF:READ,REF:READ
+                        Correct pressure readings with a gain and an offset instead, so that
+                        readings of READ Pa become REF Pa. READ are pressures the sensor read
+                        without correction, REF those of a reference instrument at the same time
+{}
+{}",
+        DEFAULT_COUNT,
+        reference::OPTIONS,
+        args::SENSOR_OPTIONS
+    )
+}
+
+pub struct CalibrateArgs {
+    sensor: SensorArgs,
+    elevation_m: f32,
+    count: u32,
+    reference: PathBuf,
+    temperature_offset_c: Option<f32>,
+    pressure_offset_pa: Option<f32>,
+    pressure_points: Option<PressureCorrection>,
+}
+
+/// Parse two `REF:READ` pressure pairs, separated by a comma.
+fn parse_points(s: &str) -> ArgResult<PressureCorrection> {
+    let invalid = || ArgError(format!("invalid pressure points '{}'", s));
+    let pair = |p: &str| -> ArgResult<(f32, f32)> {
+        let (reference, read) = p.split_once(':').ok_or_else(invalid)?;
+        Ok((
+            reference.trim().parse().map_err(|_| invalid())?,
+            read.trim().parse().map_err(|_| invalid())?,
+        ))
+    };
+
+    let (first, second) = s.split_once(',').ok_or_else(invalid)?;
+    PressureCorrection::two_point(pair(first)?, pair(second)?).ok_or_else(|| {
+        ArgError(format!(
+            "the pressure points '{}' are too close together for a correction",
+            s
+        ))
+    })
+}
+
+/// Parse an elevation such as `214m` or `702ft`. A bare number is in meters.
+fn parse_elevation(s: &str) -> ArgResult<f32> {
+    let (number, factor) = if let Some(feet) = s.strip_suffix("ft") {
+        (feet, 1. / FEET_PER_METER)
+    } else {
+        (s.strip_suffix('m').unwrap_or(s), 1.)
+    };
+
+    let elevation: f32 = number
+        .parse()
+        .map_err(|_| ArgError(format!("invalid elevation '{}'", s)))?;
+
+    Ok(elevation * factor)
+}
+
+impl CalibrateArgs {
+    pub fn parse(mut parser: Parser) -> ArgResult<Self> {
+        let mut sensor = SensorArgs::default();
+        let mut elevation_m = None;
+        let mut count = DEFAULT_COUNT;
+        let mut reference = reference::default_path();
+        let mut temperature_offset_c = None;
+        let mut pressure_offset_pa = None;
+        let mut pressure_points = None;
+
+        while let Some(arg) = parser.next_arg() {
+            match arg {
+                Arg::Flag(flag, inline) => {
+                    if sensor.parse(&mut parser, &flag, inline.clone())? {
+                        continue;
+                    }
+
+                    match flag.as_str() {
+                        "elevation" => {
+                            elevation_m = Some(parse_elevation(&parser.value(&flag, inline)?)?)
+                        }
+                        "count" => {
+                            let value = parser.value(&flag, inline)?;
+                            count = args::parse_number(&flag, &value)?;
+                        }
+                        "reference" => reference = parser.value(&flag, inline)?.into(),
+                        "temperature-offset" => {
+                            let value = parser.value(&flag, inline)?;
+                            temperature_offset_c = Some(args::parse_number(&flag, &value)?);
+                        }
+                        "pressure-offset" => {
+                            let value = parser.value(&flag, inline)?;
+                            pressure_offset_pa = Some(args::parse_number(&flag, &value)?);
+                        }
+                        "pressure-points" => {
+                            pressure_points = Some(parse_points(&parser.value(&flag, inline)?)?)
+                        }
+                        _ => return Err(args::unknown(Arg::Flag(flag, inline))),
+                    }
+                }
+                arg => return Err(args::unknown(arg)),
+            }
+        }
+
+        let elevation_m = elevation_m.ok_or_else(|| ArgError("--elevation is required".into()))?;
+        if elevation_m.is_nan() || elevation_m >= MAX_ELEVATION_M {
+            return Err(ArgError(format!(
+                "--elevation must be below {} m",
+                MAX_ELEVATION_M
+            )));
+        }
+        if count == 0 {
+            return Err(ArgError("--count must be at least 1".into()));
+        }
+        if pressure_offset_pa.is_some() && pressure_points.is_some() {
+            return Err(ArgError(
+                "--pressure-offset and --pressure-points can't be used together".into(),
+            ));
+        }
+
+        Ok(CalibrateArgs {
+            sensor,
+            elevation_m,
+            count,
+            reference,
+            temperature_offset_c,
+            pressure_offset_pa,
+            pressure_points,
+        })
+    }
+}
+
+pub fn run(args: CalibrateArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut reference = Reference::load(&args.reference)?.unwrap_or_default();
+    if let Some(offset) = args.temperature_offset_c {
+        reference.temperature_offset_c = offset;
+    }
+    let correction = match (args.pressure_offset_pa, args.pressure_points) {
+        (Some(offset), _) => Some(PressureCorrection::offset(offset)),
+        (None, points) => points,
+    };
+    if let Some(correction) = correction {
+        reference.pressure_gain = correction.gain;
+        reference.pressure_offset_pa = correction.offset_pa;
+    }
+
+    let mut sensor = args.sensor.build()?;
+    reference.correct(&mut sensor);
+
+    // A forced measurement per reading, so every one is a new conversion rather than the one
+    // still in the data registers.
+    let mut total = 0.;
+    for _ in 0..args.count {
+        total += sensor.forced_measurement()?.pressure_kpa * 1000.;
+    }
+    let pressure_pa = total / args.count as f32;
+
+    reference.sea_level_pa = bmp280::sea_level_pressure(pressure_pa, args.elevation_m);
+    reference.elevation_m = args.elevation_m;
+    reference.save(&args.reference)?;
+
+    println!(
+        "Sea level pressure {:.1} Pa at {:.1} m elevation, saved to {}",
+        reference.sea_level_pa,
+        reference.elevation_m,
+        args.reference.display()
+    );
+
+    Ok(())
+}
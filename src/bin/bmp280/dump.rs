@@ -0,0 +1,48 @@
+//! `bmp280 dump`: print every register with its decoded meaning.
+
+use crate::args::{self, Arg, ArgResult, Parser, SensorArgs};
+
+pub fn usage() -> String {
+    format!(
+        "\
+Usage: bmp280 dump [OPTIONS]
+
+Print every register with its decoded meaning: mode, oversampling, filter, standby time, raw ADC
+values and calibration words. The sensor is not configured first, so this shows the settings left
+by whichever program used it last.
+
+Options:
+{}",
+        args::SENSOR_OPTIONS
+    )
+}
+
+#[derive(Default)]
+pub struct DumpArgs {
+    sensor: SensorArgs,
+}
+
+impl DumpArgs {
+    pub fn parse(mut parser: Parser) -> ArgResult<Self> {
+        let mut args = DumpArgs::default();
+
+        while let Some(arg) = parser.next_arg() {
+            match arg {
+                Arg::Flag(flag, inline) => {
+                    if !args.sensor.parse(&mut parser, &flag, inline.clone())? {
+                        return Err(args::unknown(Arg::Flag(flag, inline)));
+                    }
+                }
+                arg => return Err(args::unknown(arg)),
+            }
+        }
+
+        Ok(args)
+    }
+}
+
+pub fn run(args: DumpArgs) -> bmp280::Result<()> {
+    print!("{}", args.sensor.builder().dump()?);
+
+    Ok(())
+}
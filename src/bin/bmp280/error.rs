@@ -0,0 +1,97 @@
+//! Exit codes and error reporting.
+
+use crate::args::ArgError;
+use crate::selftest::SelftestFailed;
+use bmp280::info::json_string;
+
+/// The process exit codes, so supervisory scripts can react to the kind of failure.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExitCode {
+    Failure = 1,
+    InvalidArguments = 2,
+    DeviceNotFound = 3,
+    WrongChipId = 4,
+    BusError = 5,
+    SelftestFailed = 6,
+}
+
+pub const EXIT_CODES: &str = "\
+Exit codes:
+    0   Success
+    1   Other failure
+    2   Invalid arguments
+    3   Device not found (no device node, or no device answering at the address)
+    4   Wrong chip ID
+    5   Bus error
+    6   Self-test failed";
+
+impl ExitCode {
+    fn kind(self) -> &'static str {
+        match self {
+            ExitCode::Failure => "failure",
+            ExitCode::InvalidArguments => "invalid_arguments",
+            ExitCode::DeviceNotFound => "device_not_found",
+            ExitCode::WrongChipId => "wrong_chip_id",
+            ExitCode::BusError => "bus_error",
+            ExitCode::SelftestFailed => "selftest_failed",
+        }
+    }
+}
+
+pub fn exit_code(error: &(dyn std::error::Error + 'static)) -> ExitCode {
+    if error.is::<ArgError>() {
+        return ExitCode::InvalidArguments;
+    }
+    if error.is::<SelftestFailed>() {
+        return ExitCode::SelftestFailed;
+    }
+
+    match error.downcast_ref::<bmp280::Error>().map(|e| e.innermost()) {
+        Some(bmp280::Error::NotConnected { .. })
+        | Some(bmp280::Error::InvalidDevicePath { .. }) => ExitCode::DeviceNotFound,
+        Some(bmp280::Error::UnexpectedAddress { .. })
+        | Some(bmp280::Error::IncompatibleSettings { .. }) => ExitCode::InvalidArguments,
+        Some(bmp280::Error::ChipIdMismatch { .. }) => ExitCode::WrongChipId,
+        Some(bmp280::Error::Timeout { .. }) => ExitCode::BusError,
+        Some(e @ bmp280::Error::I2cError(_)) | Some(e @ bmp280::Error::IoError(_)) => {
+            match e.raw_os_error() {
+                // The device node doesn't exist, or nothing acknowledged the address.
+                Some(libc::ENOENT) | Some(libc::ENXIO) | Some(libc::EREMOTEIO) => {
+                    ExitCode::DeviceNotFound
+                }
+                _ => ExitCode::BusError,
+            }
+        }
+        _ => ExitCode::Failure,
+    }
+}
+
+/// The error message followed by the messages of all its sources, separated by colons.
+pub fn message(error: &(dyn std::error::Error + 'static)) -> String {
+    let mut message = error.to_string();
+
+    let mut source = error.source();
+    while let Some(e) = source {
+        message.push_str(": ");
+        message.push_str(&e.to_string());
+        source = e.source();
+    }
+
+    message
+}
+
+/// Print the error to stderr, or as a JSON object to stdout if `json` is set.
+pub fn report(error: &(dyn std::error::Error + 'static), code: ExitCode, json: bool) {
+    let message = message(error);
+
+    if json {
+        println!(
+            "{{\"error\":{},\"message\":{},\"exit_code\":{}}}",
+            json_string(code.kind()),
+            json_string(&message),
+            code as i32
+        );
+    } else {
+        eprintln!("bmp280: {}", message);
+    }
+}
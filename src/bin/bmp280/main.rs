@@ -0,0 +1,120 @@
+//! Command line interface for the BMP280.
+
+mod args;
+mod calibrate;
+mod dump;
+mod error;
+#[cfg(feature = "metar")]
+mod metar;
+mod monitor;
+mod output;
+mod read;
+mod reference;
+mod replay;
+mod selftest;
+mod serve;
+
+use args::{ArgError, Parser};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const USAGE: &str = "\
+Usage: bmp280 <COMMAND> [OPTIONS]
+
+Commands:
+    read        Print a single reading
+    monitor     Print readings continuously, then a summary
+    calibrate   Store the sea level pressure for a known elevation
+    selftest    Check the sensor and print a pass/fail report
+    dump        Print all registers with their decoded meaning
+    replay      Play back a trace recorded with `monitor --record`
+    serve       Stream readings as JSON lines over a Unix socket
+
+Run `bmp280 <COMMAND> --help` for the options of a command.
+";
+
+/// Set by SIGINT/SIGTERM so long running commands can exit cleanly.
+static STOP: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_signal(_signal: libc::c_int) {
+    STOP.store(true, Ordering::SeqCst);
+}
+
+fn install_signal_handlers() {
+    let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+
+    // The handler only stores to an atomic, which is async-signal-safe.
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+}
+
+fn run(mut args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    if args.is_empty() {
+        return Err(ArgError(USAGE.into()).into());
+    }
+
+    let command = args.remove(0);
+
+    if args.iter().any(|arg| arg == "--help") {
+        match command.as_str() {
+            "read" => println!("{}", read::usage()),
+            "monitor" => println!("{}", monitor::usage()),
+            "calibrate" => println!("{}", calibrate::usage()),
+            "selftest" => println!("{}", selftest::usage()),
+            "dump" => println!("{}", dump::usage()),
+            "replay" => println!("{}", replay::usage()),
+            "serve" => println!("{}", serve::usage()),
+            _ => println!("{}\n{}", USAGE, error::EXIT_CODES),
+        }
+        return Ok(());
+    }
+
+    let parser = Parser::new(args);
+
+    match command.as_str() {
+        "read" => read::run(read::ReadArgs::parse(parser)?)?,
+        "monitor" => {
+            let args = monitor::MonitorArgs::parse(parser)?;
+            install_signal_handlers();
+            monitor::run(args, &STOP)?;
+        }
+        "calibrate" => calibrate::run(calibrate::CalibrateArgs::parse(parser)?)?,
+        "selftest" => selftest::run(selftest::SelftestArgs::parse(parser)?)?,
+        "dump" => dump::run(dump::DumpArgs::parse(parser)?)?,
+        "replay" => {
+            let args = replay::ReplayArgs::parse(parser)?;
+            install_signal_handlers();
+            replay::run(args, &STOP)?;
+        }
+        "serve" => {
+            let args = serve::ServeArgs::parse(parser)?;
+            install_signal_handlers();
+            serve::run(args, &STOP)?;
+        }
+        "help" | "--help" => println!("{}\n{}", USAGE, error::EXIT_CODES),
+        _ => return Err(ArgError(format!("unknown command '{}'\n\n{}", command, USAGE)).into()),
+    }
+
+    Ok(())
+}
+
+/// Whether JSON output was requested, so errors can be reported as JSON even if the rest of the
+/// command line is invalid.
+fn wants_json(args: &[String]) -> bool {
+    args.iter()
+        .zip(args.iter().skip(1))
+        .any(|(flag, value)| flag == "--format" && value == "json")
+        || args.iter().any(|arg| arg == "--format=json")
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let json = wants_json(&args);
+
+    if let Err(e) = run(args) {
+        let code = error::exit_code(e.as_ref());
+        error::report(e.as_ref(), code, json);
+        std::process::exit(code as i32);
+    }
+}
@@ -0,0 +1,52 @@
+//! Fetching the sea level reference pressure from a METAR report.
+//!
+//! The report is downloaded with the system `curl`, so no HTTP or TLS stack needs to be compiled
+//! into the binary.
+
+use std::io;
+use std::process::Command;
+
+const METAR_URL: &str = "https://aviationweather.gov/api/data/metar?format=raw&ids=";
+
+/// Fetch the latest METAR report for the station and return its altimeter setting in Pa.
+pub fn fetch_altimeter_pa(station: &str) -> io::Result<f32> {
+    if !bmp280::metar::is_station_id(station) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid station identifier '{}'", station),
+        ));
+    }
+
+    let url = format!("{}{}", METAR_URL, station.to_ascii_uppercase());
+    let output = Command::new("curl")
+        .args([
+            "--silent",
+            "--show-error",
+            "--fail",
+            "--max-time",
+            "10",
+            &url,
+        ])
+        .output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "could not fetch METAR for {}: {}",
+            station,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let report = String::from_utf8_lossy(&output.stdout);
+
+    bmp280::metar::altimeter_pa(&report).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "no altimeter setting in METAR for {}: '{}'",
+                station,
+                report.trim()
+            ),
+        )
+    })
+}
@@ -0,0 +1,318 @@
+//! `bmp280 monitor`: continuously print readings, then a summary.
+
+use crate::args::{self, Arg, ArgError, ArgResult, Parser, SensorArgs};
+use crate::error;
+use crate::output::{self, Format, Output, OutputArgs};
+use bmp280::array::SensorArray;
+use bmp280::sampler::{Sampler, Source};
+use bmp280::trace::{self, Recorder};
+use bmp280::{Calibration, Error, Measurement};
+use i2cdev::core::I2CDevice;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+pub fn usage() -> String {
+    format!(
+        "\
+Usage: bmp280 monitor [OPTIONS]
+
+Print a reading every interval until the count or duration is reached, or until interrupted, and
+then print a min/max/avg summary. Altitudes are relative to the first reading. Several sensors are
+read in turn every interval, with each row labeled by its sensor.
+
+Options:
+{}
+    --interval TIME     Time between readings, e.g. 250ms (default: 250ms)
+    --record FILE       Record the I2C traffic of the sensor to FILE, for `bmp280 replay`
+{}
+{}
+{}",
+        LIMIT_OPTIONS,
+        output::OPTIONS,
+        args::SENSOR_OPTIONS,
+        args::MULTI_SENSOR_OPTIONS
+    )
+}
+
+pub const LIMIT_OPTIONS: &str = "    --count N           Stop after N readings
+    --duration TIME     Stop after TIME, e.g. 30s, 10m or 2h";
+
+/// How many readings to take and how often, shared with `bmp280 replay`.
+#[derive(Default)]
+pub struct SamplingArgs {
+    pub count: Option<u64>,
+    pub duration: Option<Duration>,
+    pub interval: Option<Duration>,
+}
+
+impl SamplingArgs {
+    /// Handle the flag if it is a sampling option, returning whether it was one.
+    pub fn parse(
+        &mut self,
+        parser: &mut Parser,
+        flag: &str,
+        inline: Option<String>,
+    ) -> ArgResult<bool> {
+        match flag {
+            "count" => {
+                let value = parser.value(flag, inline)?;
+                self.count = Some(args::parse_number(flag, &value)?);
+            }
+            "duration" => self.duration = Some(args::parse_duration(&parser.value(flag, inline)?)?),
+            "interval" => self.interval = Some(args::parse_duration(&parser.value(flag, inline)?)?),
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Set up the sampler with these options.
+    pub fn apply<'a, S: Source>(&self, sampler: &mut Sampler<'a, S>, stop: &'a AtomicBool) {
+        sampler.stop_flag(stop);
+
+        if let Some(count) = self.count {
+            sampler.limit(count);
+        }
+        if let Some(duration) = self.duration {
+            sampler.duration(duration);
+        }
+        if let Some(interval) = self.interval {
+            sampler.interval(interval);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct MonitorArgs {
+    sensor: SensorArgs,
+    output: OutputArgs,
+    sampling: SamplingArgs,
+    record: Option<PathBuf>,
+}
+
+impl MonitorArgs {
+    pub fn parse(mut parser: Parser) -> ArgResult<Self> {
+        let mut args = MonitorArgs::default();
+
+        while let Some(arg) = parser.next_arg() {
+            match arg {
+                Arg::Flag(flag, inline) => {
+                    if args.sensor.parse(&mut parser, &flag, inline.clone())?
+                        || args.output.parse(&mut parser, &flag, inline.clone())?
+                        || args.sampling.parse(&mut parser, &flag, inline.clone())?
+                    {
+                        continue;
+                    }
+
+                    match flag.as_str() {
+                        "record" => args.record = Some(parser.value(&flag, inline)?.into()),
+                        _ => return Err(args::unknown(Arg::Flag(flag, inline))),
+                    }
+                }
+                arg => return Err(args::unknown(arg)),
+            }
+        }
+
+        args.output.validate()?;
+        if args.record.is_some() && args.sensor.labeled() {
+            return Err(ArgError("--record only supports a single sensor".into()));
+        }
+
+        Ok(args)
+    }
+}
+
+/// Running min/max/avg of a single value.
+#[derive(Default)]
+struct Stat {
+    min: f32,
+    max: f32,
+    sum: f64,
+    count: u64,
+}
+
+impl Stat {
+    fn add(&mut self, value: f32) {
+        if self.count == 0 || value < self.min {
+            self.min = value;
+        }
+        if self.count == 0 || value > self.max {
+            self.max = value;
+        }
+        self.sum += f64::from(value);
+        self.count += 1;
+    }
+
+    fn print(&self, name: &str, unit: &str) {
+        if self.count == 0 {
+            return;
+        }
+
+        let avg = self.sum / self.count as f64;
+        println!(
+            "{:<12} min {:>9.3}  max {:>9.3}  avg {:>9.3} {}",
+            name, self.min, self.max, avg, unit
+        );
+    }
+}
+
+#[derive(Default)]
+struct Summary {
+    temperature: Stat,
+    pressure: Stat,
+    altitude: Stat,
+    errors: u64,
+}
+
+impl Summary {
+    fn add(&mut self, measurement: &Measurement) {
+        self.temperature.add(measurement.temperature_celsius);
+        self.pressure.add(measurement.pressure_kpa);
+        self.altitude.add(measurement.altitude_m);
+    }
+
+    fn print(&self, label: &str) {
+        println!();
+        if label.is_empty() {
+            println!(
+                "{} readings, {} errors",
+                self.temperature.count, self.errors
+            );
+        } else {
+            println!(
+                "{}: {} readings, {} errors",
+                label, self.temperature.count, self.errors
+            );
+        }
+        self.temperature.print("temperature", "C");
+        self.pressure.print("pressure", "kPa");
+        self.altitude.print("altitude", "m");
+    }
+}
+
+pub fn run(args: MonitorArgs, stop: &AtomicBool) -> bmp280::Result<()> {
+    match &args.record {
+        Some(path) => {
+            let builder = args.sensor.builder();
+            let device = Recorder::create(builder.open()?, path)?;
+
+            let mut array = SensorArray::new();
+            array.add("", builder.build_with(device)?);
+
+            sample(array, &args.sampling, &args.output, false, stop)
+        }
+        None => {
+            let array = args.sensor.build_array()?;
+
+            sample(
+                array,
+                &args.sampling,
+                &args.output,
+                args.sensor.labeled(),
+                stop,
+            )
+        }
+    }
+}
+
+/// Prints readings as they come in, and the summaries of all sensors at the end.
+pub struct Printer {
+    output: Output,
+    summaries: Vec<(String, Summary)>,
+}
+
+impl Printer {
+    pub fn new<'a>(
+        output: &OutputArgs,
+        labeled: bool,
+        labels: impl Iterator<Item = &'a str>,
+    ) -> Self {
+        Printer {
+            output: output.output(labeled),
+            summaries: labels
+                .map(|label| (label.to_string(), Summary::default()))
+                .collect(),
+        }
+    }
+
+    pub fn calibration(&mut self, label: &str, calibration: &Calibration) {
+        self.output.calibration(label, calibration);
+    }
+
+    pub fn reading(&mut self, label: &str, measurement: &bmp280::Result<Measurement>) {
+        let summary = self
+            .summaries
+            .iter_mut()
+            .find(|(l, _)| l == label)
+            .map(|(_, summary)| summary);
+
+        match measurement {
+            Ok(measurement) => {
+                self.output.measurement(label, measurement);
+                if let Some(summary) = summary {
+                    summary.add(measurement);
+                }
+            }
+            Err(e) => {
+                if label.is_empty() {
+                    eprintln!("bmp280: {}", error::message(e));
+                } else {
+                    eprintln!("bmp280: {}: {}", label, error::message(e));
+                }
+                if let Some(summary) = summary {
+                    summary.errors += 1;
+                }
+            }
+        }
+    }
+
+    pub fn finish(&self) {
+        // Plain, JSON and CSV output are meant for scripts, which only want the values.
+        if self.output.format() == Format::Text || self.output.format() == Format::Table {
+            for (label, summary) in &self.summaries {
+                summary.print(label);
+            }
+        }
+    }
+}
+
+/// Zero every sensor, print readings until a limit is reached, and then print the summaries.
+///
+/// The end of a replayed trace ends sampling, and a trace that no longer matches the driver is an
+/// error, as there is no point in reading on after that.
+pub fn sample<D>(
+    mut array: SensorArray<D>,
+    sampling: &SamplingArgs,
+    output: &OutputArgs,
+    labeled: bool,
+    stop: &AtomicBool,
+) -> bmp280::Result<()>
+where
+    D: I2CDevice,
+    Error: From<D::Error>,
+{
+    let mut printer = Printer::new(output, labeled, array.labels());
+
+    for (label, sensor) in array.iter_mut() {
+        sensor.zero()?;
+        printer.calibration(label, sensor.calibration());
+    }
+
+    let mut sampler = Sampler::new(&mut array);
+    sampling.apply(&mut sampler, stop);
+
+    'sampling: for row in sampler {
+        for (label, measurement) in row {
+            match measurement {
+                Err(e) if trace::is_end_of_trace(&e) => break 'sampling,
+                Err(e) if trace::is_divergence(&e) => return Err(e),
+                measurement => printer.reading(&label, &measurement),
+            }
+        }
+    }
+
+    printer.finish();
+
+    Ok(())
+}
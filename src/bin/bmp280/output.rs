@@ -0,0 +1,433 @@
+//! Formatting of readings for humans and scripts.
+
+use crate::args::{ArgError, ArgResult, Parser};
+use bmp280::info::json_string;
+use bmp280::{csv, Calibration, Measurement};
+use std::io;
+
+pub const OPTIONS: &str =
+    "    --format FORMAT     Output format: text, table, plain, json or csv (default: text)
+    --metric METRIC     Only output pressure, altitude or temperature
+    --raw               With --format csv, also write the raw ADC values and, at the top, the
+                        calibration data, so the log can be compensated again later";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    /// Values with units on a single line
+    Text,
+    /// Aligned columns with a header
+    Table,
+    /// A single bare number, for shell scripts
+    Plain,
+    /// One JSON object per reading. Errors are also reported as JSON on stdout.
+    Json,
+    /// Comma separated values with a header row
+    Csv,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Metric {
+    Pressure,
+    Altitude,
+    Temperature,
+}
+
+const ALL_METRICS: [Metric; 3] = [Metric::Pressure, Metric::Altitude, Metric::Temperature];
+
+impl Metric {
+    fn parse(s: &str) -> ArgResult<Self> {
+        match s {
+            "pressure" => Ok(Metric::Pressure),
+            "altitude" => Ok(Metric::Altitude),
+            "temperature" => Ok(Metric::Temperature),
+            _ => Err(ArgError(format!("unknown metric '{}'", s))),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Metric::Pressure => "pressure",
+            Metric::Altitude => "altitude",
+            Metric::Temperature => "temperature",
+        }
+    }
+
+    pub fn unit(self) -> &'static str {
+        match self {
+            Metric::Pressure => "kPa",
+            Metric::Altitude => "m",
+            Metric::Temperature => "C",
+        }
+    }
+
+    pub fn value(self, measurement: &Measurement) -> f32 {
+        match self {
+            Metric::Pressure => measurement.pressure_kpa,
+            Metric::Altitude => measurement.altitude_m,
+            Metric::Temperature => measurement.temperature_celsius,
+        }
+    }
+
+    /// The unit as used in JSON field names, e.g. `pressure_kpa`.
+    fn json_unit(self) -> &'static str {
+        match self {
+            Metric::Pressure => "kpa",
+            Metric::Altitude => "m",
+            Metric::Temperature => "celsius",
+        }
+    }
+
+    fn precision(self) -> usize {
+        match self {
+            Metric::Pressure => 3,
+            Metric::Altitude | Metric::Temperature => 2,
+        }
+    }
+}
+
+/// The `--format`, `--metric` and `--raw` options, shared by commands that print readings.
+pub struct OutputArgs {
+    pub format: Format,
+    pub metric: Option<Metric>,
+    pub raw: bool,
+}
+
+impl Default for OutputArgs {
+    fn default() -> Self {
+        OutputArgs {
+            format: Format::Text,
+            metric: None,
+            raw: false,
+        }
+    }
+}
+
+impl OutputArgs {
+    /// Handle the flag if it is an output option, returning whether it was one.
+    pub fn parse(
+        &mut self,
+        parser: &mut Parser,
+        flag: &str,
+        inline: Option<String>,
+    ) -> ArgResult<bool> {
+        match flag {
+            "format" => {
+                self.format = match parser.value(flag, inline)?.as_str() {
+                    "text" => Format::Text,
+                    "table" => Format::Table,
+                    "plain" => Format::Plain,
+                    "json" => Format::Json,
+                    "csv" => Format::Csv,
+                    other => return Err(ArgError(format!("unknown format '{}'", other))),
+                }
+            }
+            "metric" => self.metric = Some(Metric::parse(&parser.value(flag, inline)?)?),
+            "raw" => self.raw = true,
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Check the combination of options, once all of them are parsed.
+    pub fn validate(&self) -> ArgResult<()> {
+        if self.raw && self.format != Format::Csv {
+            return Err(ArgError("--raw requires --format csv".into()));
+        }
+
+        Ok(())
+    }
+
+    /// Create the output, `labeled` if rows need to show which sensor they come from.
+    pub fn output(&self, labeled: bool) -> Output {
+        let metrics = match (self.metric, self.format) {
+            (Some(metric), _) => vec![metric],
+            (None, Format::Plain) => vec![Metric::Pressure],
+            (None, _) => ALL_METRICS.to_vec(),
+        };
+
+        Output {
+            format: self.format,
+            metrics,
+            labeled,
+            raw: self.raw,
+            header_printed: false,
+        }
+    }
+}
+
+/// Prints readings in the selected format.
+pub struct Output {
+    format: Format,
+    metrics: Vec<Metric>,
+    labeled: bool,
+    raw: bool,
+    header_printed: bool,
+}
+
+/// The width of a table column.
+const COLUMN_WIDTH: usize = 18;
+
+/// The width of the table column holding the sensor label.
+const LABEL_WIDTH: usize = 12;
+
+impl Output {
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// The reading as a JSON object on a single line.
+    pub fn json(&self, label: &str, measurement: &Measurement) -> String {
+        let mut fields: Vec<String> = self
+            .metrics
+            .iter()
+            .map(|m| {
+                format!(
+                    "\"{}_{}\":{}",
+                    m.name(),
+                    m.json_unit(),
+                    json_number(m.value(measurement))
+                )
+            })
+            .collect();
+        if self.labeled {
+            fields.insert(0, format!("\"label\":{}", json_string(label)));
+        }
+        if let Some(sequence) = measurement.sequence {
+            fields.push(format!("\"sequence\":{}", sequence));
+        }
+        format!("{{{}}}", fields.join(","))
+    }
+
+    /// Print the calibration data of a sensor, if the output includes raw values. This has to be
+    /// done for all sensors before the first reading.
+    pub fn calibration(&mut self, label: &str, calibration: &Calibration) {
+        if self.raw {
+            let label = if self.labeled { label } else { "" };
+            // Like println!, a closed stdout is not an error worth reporting here.
+            let _ = csv::write_calibration(io::stdout().lock(), label, calibration);
+        }
+    }
+
+    pub fn measurement(&mut self, label: &str, measurement: &Measurement) {
+        for line in self.lines(label, measurement) {
+            println!("{}", line);
+        }
+    }
+
+    /// The lines printed for a reading, including the header before the first one.
+    fn lines(&mut self, label: &str, measurement: &Measurement) -> Vec<String> {
+        let mut lines = Vec::new();
+        match self.format {
+            Format::Text => {
+                let values: Vec<String> = self
+                    .metrics
+                    .iter()
+                    .map(|m| format!("{:.*} {}", m.precision(), m.value(measurement), m.unit()))
+                    .collect();
+                if self.labeled {
+                    lines.push(format!("{}: {}", label, values.join("  ")));
+                } else {
+                    lines.push(values.join("  "));
+                }
+            }
+            Format::Table => {
+                if !self.header_printed {
+                    let mut header: Vec<String> = self
+                        .metrics
+                        .iter()
+                        .map(|m| {
+                            let title = format!("{} ({})", m.name(), m.unit());
+                            format!("{:>width$}", title, width = COLUMN_WIDTH)
+                        })
+                        .collect();
+                    if self.labeled {
+                        header.insert(0, format!("{:<width$}", "sensor", width = LABEL_WIDTH));
+                    }
+                    lines.push(header.join(""));
+                    self.header_printed = true;
+                }
+
+                let mut row: Vec<String> = self
+                    .metrics
+                    .iter()
+                    .map(|m| {
+                        format!(
+                            "{:>width$.prec$}",
+                            m.value(measurement),
+                            width = COLUMN_WIDTH,
+                            prec = m.precision()
+                        )
+                    })
+                    .collect();
+                if self.labeled {
+                    row.insert(0, format!("{:<width$}", label, width = LABEL_WIDTH));
+                }
+                lines.push(row.join(""));
+            }
+            Format::Plain => {
+                let mut values: Vec<String> = self
+                    .metrics
+                    .iter()
+                    .map(|m| format!("{:.*}", m.precision(), m.value(measurement)))
+                    .collect();
+                if self.labeled {
+                    values.insert(0, label.into());
+                }
+                lines.push(values.join(" "));
+            }
+            Format::Json => lines.push(self.json(label, measurement)),
+            Format::Csv => {
+                if !self.header_printed {
+                    let mut header: Vec<String> = self
+                        .metrics
+                        .iter()
+                        .map(|m| format!("{}_{}", m.name(), m.json_unit()))
+                        .collect();
+                    if self.labeled {
+                        header.insert(0, "label".into());
+                    }
+                    if self.raw {
+                        header.extend(csv::RAW_COLUMNS.iter().map(|c| c.to_string()));
+                    }
+                    lines.push(header.join(","));
+                    self.header_printed = true;
+                }
+
+                let mut values: Vec<String> = self
+                    .metrics
+                    .iter()
+                    .map(|m| format!("{:.*}", m.precision(), m.value(measurement)))
+                    .collect();
+                if self.labeled {
+                    values.insert(0, csv::escape(label));
+                }
+                if self.raw {
+                    values.push(measurement.raw.adc_t.to_string());
+                    values.push(measurement.raw.adc_p.to_string());
+                }
+                lines.push(values.join(","));
+            }
+        }
+        lines
+    }
+}
+
+/// Format a number for JSON, which has no representation for NaN or infinities.
+pub fn json_number(value: f32) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bmp280::emulator::Emulator;
+    use bmp280::{Bmp280Builder, ReferencePressure, STANDARD_SEA_LEVEL_PA};
+
+    /// The reading of the emulator, 25.08 C and 100653 Pa, 56.08 m above sea level.
+    fn measurement() -> Measurement {
+        Bmp280Builder::new()
+            .ground_pressure(ReferencePressure::from_pa(STANDARD_SEA_LEVEL_PA))
+            .build_with(Emulator::new())
+            .expect("Could not build device")
+            .measure()
+            .unwrap()
+    }
+
+    fn lines(args: OutputArgs, label: &str) -> Vec<String> {
+        let mut output = args.output(!label.is_empty());
+        let measurement = measurement();
+        let mut lines = output.lines(label, &measurement);
+        // The header is only printed before the first reading.
+        lines.extend(output.lines(label, &measurement));
+        lines
+    }
+
+    fn format(format: Format) -> OutputArgs {
+        OutputArgs {
+            format,
+            ..OutputArgs::default()
+        }
+    }
+
+    #[test]
+    fn text() {
+        assert_eq!(
+            lines(format(Format::Text), ""),
+            [
+                "100.653 kPa  56.08 m  25.08 C",
+                "100.653 kPa  56.08 m  25.08 C"
+            ]
+        );
+        assert_eq!(
+            lines(format(Format::Text), "outdoor")[0],
+            "outdoor: 100.653 kPa  56.08 m  25.08 C"
+        );
+    }
+
+    #[test]
+    fn table() {
+        assert_eq!(
+            lines(format(Format::Table), "outdoor"),
+            [
+                "sensor          pressure (kPa)      altitude (m)   temperature (C)",
+                "outdoor                100.653             56.08             25.08",
+                "outdoor                100.653             56.08             25.08",
+            ]
+        );
+    }
+
+    #[test]
+    fn plain() {
+        assert_eq!(lines(format(Format::Plain), ""), ["100.653", "100.653"]);
+        let args = OutputArgs {
+            metric: Some(Metric::Temperature),
+            ..format(Format::Plain)
+        };
+        assert_eq!(lines(args, "outdoor")[0], "outdoor 25.08");
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(
+            lines(format(Format::Json), "out\"door")[0],
+            r#"{"label":"out\"door","pressure_kpa":100.65325,"altitude_m":56.07962,"temperature_celsius":25.08}"#
+        );
+    }
+
+    #[test]
+    fn csv() {
+        let args = OutputArgs {
+            raw: true,
+            ..format(Format::Csv)
+        };
+        assert_eq!(
+            lines(args, ""),
+            [
+                "pressure_kpa,altitude_m,temperature_celsius,adc_t,adc_p",
+                "100.653,56.08,25.08,519888,415148",
+                "100.653,56.08,25.08,519888,415148",
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_labels_are_quoted() {
+        assert_eq!(
+            lines(format(Format::Csv), "out,\"door\""),
+            [
+                "label,pressure_kpa,altitude_m,temperature_celsius",
+                r#""out,""door""",100.653,56.08,25.08"#,
+                r#""out,""door""",100.653,56.08,25.08"#,
+            ]
+        );
+        assert_eq!(
+            lines(format(Format::Csv), "outdoor")[1],
+            "outdoor,100.653,56.08,25.08"
+        );
+    }
+}
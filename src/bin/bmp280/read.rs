@@ -0,0 +1,123 @@
+//! `bmp280 read`: print a single reading.
+
+use crate::args::{self, Arg, ArgResult, Parser, SensorArgs};
+use crate::output::{self, OutputArgs};
+use crate::reference::{self, Reference};
+use bmp280::{ReferencePressure, STANDARD_SEA_LEVEL_PA};
+use std::path::PathBuf;
+
+#[cfg(feature = "metar")]
+const METAR_OPTIONS: &str =
+    "\n    --metar STATION     Use the altimeter setting reported by a METAR station, e.g. KSFO";
+#[cfg(not(feature = "metar"))]
+const METAR_OPTIONS: &str = "";
+
+pub fn usage() -> String {
+    format!(
+        "\
+Usage: bmp280 read [OPTIONS]
+
+Print a single reading. Altitudes are above sea level, using the sea level pressure stored by
+`bmp280 calibrate`, or the standard sea level pressure if the sensor has not been calibrated.
+
+Options:
+{}
+{}{}
+{}
+{}",
+        output::OPTIONS,
+        reference::OPTIONS,
+        METAR_OPTIONS,
+        args::SENSOR_OPTIONS,
+        args::MULTI_SENSOR_OPTIONS
+    )
+}
+
+#[derive(Default)]
+pub struct ReadArgs {
+    sensor: SensorArgs,
+    output: OutputArgs,
+    reference: Option<PathBuf>,
+    metar: Option<String>,
+}
+
+impl ReadArgs {
+    pub fn parse(mut parser: Parser) -> ArgResult<Self> {
+        let mut args = ReadArgs::default();
+
+        while let Some(arg) = parser.next_arg() {
+            match arg {
+                Arg::Flag(flag, inline) => {
+                    if args.sensor.parse(&mut parser, &flag, inline.clone())?
+                        || args.output.parse(&mut parser, &flag, inline.clone())?
+                    {
+                        continue;
+                    }
+
+                    match flag.as_str() {
+                        "reference" => args.reference = Some(parser.value(&flag, inline)?.into()),
+                        #[cfg(feature = "metar")]
+                        "metar" => args.metar = Some(parser.value(&flag, inline)?),
+                        _ => return Err(args::unknown(Arg::Flag(flag, inline))),
+                    }
+                }
+                arg => return Err(args::unknown(arg)),
+            }
+        }
+
+        args.output.validate()?;
+
+        Ok(args)
+    }
+}
+
+pub fn run(args: ReadArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let path = args.reference.unwrap_or_else(reference::default_path);
+    let reference = Reference::load(&path)?;
+    let sea_level_pa = match &args.metar {
+        Some(station) => metar_altimeter_pa(station)?,
+        None => reference
+            .map(|reference| reference.sea_level_pa)
+            .unwrap_or(STANDARD_SEA_LEVEL_PA),
+    };
+
+    let mut array = args.sensor.build_array()?;
+    for (_, sensor) in array.iter_mut() {
+        sensor.set_ground_pressure(ReferencePressure::from_pa(sea_level_pa));
+        if let Some(reference) = &reference {
+            reference.correct(sensor);
+        }
+    }
+
+    let mut output = args.output.output(args.sensor.labeled());
+    for (label, sensor) in array.iter_mut() {
+        output.calibration(label, sensor.calibration());
+    }
+
+    let mut failure = None;
+
+    for (label, measurement) in array.measure_all() {
+        match measurement {
+            Ok(measurement) => output.measurement(&label, &measurement),
+            // Report the first failure once all other sensors have been read.
+            Err(e) => {
+                failure.get_or_insert(e);
+            }
+        }
+    }
+
+    match failure {
+        Some(e) => Err(e.into()),
+        None => Ok(()),
+    }
+}
+
+#[cfg(feature = "metar")]
+fn metar_altimeter_pa(station: &str) -> std::io::Result<f32> {
+    crate::metar::fetch_altimeter_pa(station)
+}
+
+#[cfg(not(feature = "metar"))]
+fn metar_altimeter_pa(_station: &str) -> std::io::Result<f32> {
+    unreachable!("--metar is only parsed with the metar feature")
+}
@@ -0,0 +1,129 @@
+//! The reference file, storing the local sea level pressure written by `bmp280 calibrate` and the
+//! offsets that correct the readings of the sensor.
+//!
+//! The file consists of `key = value` lines, which makes it valid TOML:
+//!
+//! ```text
+//! sea_level_pa = 101712.4
+//! elevation_m = 214
+//! temperature_offset_c = -1.5
+//! pressure_gain = 1.0004
+//! pressure_offset_pa = 12
+//! ```
+//!
+//! Pressure readings are corrected to `pressure_gain * pressure + pressure_offset_pa`.
+
+use bmp280::correction::PressureCorrection;
+use bmp280::Bmp280;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const OPTIONS: &str =
+    "    --reference FILE    Reference file (default: ~/.config/bmp280/reference.toml)";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reference {
+    pub sea_level_pa: f32,
+    pub elevation_m: f32,
+    pub temperature_offset_c: f32,
+    pub pressure_gain: f32,
+    pub pressure_offset_pa: f32,
+}
+
+impl Default for Reference {
+    fn default() -> Self {
+        Reference {
+            sea_level_pa: 0.,
+            elevation_m: 0.,
+            temperature_offset_c: 0.,
+            pressure_gain: 1.,
+            pressure_offset_pa: 0.,
+        }
+    }
+}
+
+/// The reference file location, `$XDG_CONFIG_HOME/bmp280/reference.toml` or
+/// `~/.config/bmp280/reference.toml`.
+pub fn default_path() -> PathBuf {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    config.join("bmp280").join("reference.toml")
+}
+
+fn invalid(line: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid line in reference file: '{}'", line),
+    )
+}
+
+impl Reference {
+    /// Load the reference file, returning `None` if it doesn't exist.
+    pub fn load(path: &Path) -> io::Result<Option<Self>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let mut reference = Reference::default();
+
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line.split_once('=').ok_or_else(|| invalid(line))?;
+            let value: f32 = value.trim().parse().map_err(|_| invalid(line))?;
+
+            match key.trim() {
+                "sea_level_pa" => reference.sea_level_pa = value,
+                "elevation_m" => reference.elevation_m = value,
+                "temperature_offset_c" => reference.temperature_offset_c = value,
+                "pressure_gain" => reference.pressure_gain = value,
+                "pressure_offset_pa" => reference.pressure_offset_pa = value,
+                // Ignore unknown keys so newer files can be read by older versions.
+                _ => {}
+            }
+        }
+
+        Ok(Some(reference))
+    }
+
+    pub fn pressure_correction(&self) -> PressureCorrection {
+        PressureCorrection {
+            gain: self.pressure_gain,
+            offset_pa: self.pressure_offset_pa,
+        }
+    }
+
+    /// Set up a sensor to correct its readings as the reference file says.
+    pub fn correct(&self, sensor: &mut Bmp280) {
+        sensor.set_offsets(self.temperature_offset_c, 0.);
+        sensor.set_pressure_correction(self.pressure_correction());
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(
+            path,
+            format!(
+                "# Written by `bmp280 calibrate`\nsea_level_pa = {}\nelevation_m = {}\n\
+                 temperature_offset_c = {}\npressure_gain = {}\npressure_offset_pa = {}\n",
+                self.sea_level_pa,
+                self.elevation_m,
+                self.temperature_offset_c,
+                self.pressure_gain,
+                self.pressure_offset_pa
+            ),
+        )
+    }
+}
@@ -0,0 +1,160 @@
+//! `bmp280 replay`: run a recorded I2C trace or a raw CSV log through the compensation and output
+//! code.
+
+use crate::args::{self, Arg, ArgError, ArgResult, Parser};
+use crate::monitor::{self, Printer, SamplingArgs};
+use crate::output::{self, OutputArgs};
+use bmp280::array::SensorArray;
+use bmp280::csv::RawLog;
+use bmp280::sampler::{Sampler, Source};
+use bmp280::trace::Replayer;
+use bmp280::{Bmp280Builder, Measurement, STANDARD_SEA_LEVEL_PA};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+use std::vec;
+
+pub fn usage() -> String {
+    format!(
+        "\
+Usage: bmp280 replay [OPTIONS] FILE
+
+Play back a trace recorded with `bmp280 monitor --record` as if it came from the sensor, or the
+samples of a log written with `--format csv --raw`, printing a reading for every measurement and
+then a min/max/avg summary. The readings are computed by the current code, so changes to the data
+processing can be checked against captured field data. Altitudes are relative to the first
+reading of each sensor.
+
+Options:
+{}
+    --interval TIME     Time between readings, e.g. 250ms (default: as fast as possible)
+{}",
+        monitor::LIMIT_OPTIONS,
+        output::OPTIONS
+    )
+}
+
+#[derive(Default)]
+pub struct ReplayArgs {
+    trace: Option<PathBuf>,
+    output: OutputArgs,
+    sampling: SamplingArgs,
+}
+
+impl ReplayArgs {
+    pub fn parse(mut parser: Parser) -> ArgResult<Self> {
+        let mut args = ReplayArgs::default();
+
+        while let Some(arg) = parser.next_arg() {
+            match arg {
+                Arg::Flag(flag, inline) => {
+                    if !(args.output.parse(&mut parser, &flag, inline.clone())?
+                        || args.sampling.parse(&mut parser, &flag, inline.clone())?)
+                    {
+                        return Err(args::unknown(Arg::Flag(flag, inline)));
+                    }
+                }
+                Arg::Positional(path) if args.trace.is_none() => args.trace = Some(path.into()),
+                arg => return Err(args::unknown(arg)),
+            }
+        }
+
+        if args.trace.is_none() {
+            return Err(ArgError(format!(
+                "missing trace or log file\n\n{}",
+                usage()
+            )));
+        }
+        args.output.validate()?;
+        if args.sampling.interval.is_none() {
+            args.sampling.interval = Some(Duration::from_secs(0));
+        }
+
+        Ok(args)
+    }
+}
+
+pub fn run(args: ReplayArgs, stop: &AtomicBool) -> bmp280::Result<()> {
+    let path = args.trace.as_ref().expect("checked when parsing");
+
+    if is_trace(path)? {
+        let mut array = SensorArray::new();
+        array.add("", Bmp280Builder::new().build_with(Replayer::open(path)?)?);
+
+        monitor::sample(array, &args.sampling, &args.output, false, stop)
+    } else {
+        replay_log(
+            RawLog::parse(BufReader::new(File::open(path)?))?,
+            &args,
+            stop,
+        )
+    }
+}
+
+/// Whether the file starts like a trace rather than a CSV log.
+fn is_trace(path: &Path) -> bmp280::Result<bool> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    Ok(reader.fill_buf()?.starts_with(b"BMP280TR"))
+}
+
+/// The compensated samples of a log, one at a time.
+struct LogSource {
+    readings: vec::IntoIter<(String, bmp280::Result<Measurement>)>,
+}
+
+impl Source for LogSource {
+    type Output = Option<(String, bmp280::Result<Measurement>)>;
+
+    fn sample(&mut self) -> Self::Output {
+        self.readings.next()
+    }
+}
+
+fn replay_log(log: RawLog, args: &ReplayArgs, stop: &AtomicBool) -> bmp280::Result<()> {
+    let labeled = log.calibrations.iter().any(|(label, _)| !label.is_empty());
+    let labels = log.calibrations.iter().map(|(label, _)| label.as_str());
+    let mut printer = Printer::new(&args.output, labeled, labels);
+
+    // Altitudes are relative to the first reading of each sensor, as in `bmp280 monitor`.
+    let mut grounds: Vec<(&str, f32)> = Vec::new();
+    let mut readings = Vec::with_capacity(log.samples.len());
+    for (label, raw) in &log.samples {
+        let calibration = log.calibration(label).expect("checked when parsing");
+
+        let ground = match grounds.iter().find(|(l, _)| l == label) {
+            Some(&(_, ground)) => ground,
+            None => match calibration.compensate(*raw, STANDARD_SEA_LEVEL_PA) {
+                Ok(first) => {
+                    grounds.push((label, first.pressure_kpa * 1000.));
+                    first.pressure_kpa * 1000.
+                }
+                Err(_) => STANDARD_SEA_LEVEL_PA,
+            },
+        };
+        readings.push((label.clone(), calibration.compensate(*raw, ground)));
+    }
+
+    for (label, calibration) in &log.calibrations {
+        printer.calibration(label, calibration);
+    }
+
+    let mut source = LogSource {
+        readings: readings.into_iter(),
+    };
+    let mut sampler = Sampler::new(&mut source);
+    args.sampling.apply(&mut sampler, stop);
+
+    for reading in sampler {
+        match reading {
+            Some((label, measurement)) => printer.reading(&label, &measurement),
+            None => break,
+        }
+    }
+
+    printer.finish();
+
+    Ok(())
+}
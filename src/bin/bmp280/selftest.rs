@@ -0,0 +1,86 @@
+//! `bmp280 selftest`: print a pass/fail report for production line bring-up.
+
+use crate::args::{self, Arg, ArgResult, Parser, SensorArgs};
+use bmp280::selftest::Report;
+use std::fmt;
+
+pub fn usage() -> String {
+    format!(
+        "\
+Usage: bmp280 selftest [OPTIONS]
+
+Check the chip ID and the calibration data, take a forced measurement, and check that it is within
+300-1100 hPa and -40-85 C. Exits with code 6 if any check fails.
+
+Options:
+{}",
+        args::SENSOR_OPTIONS
+    )
+}
+
+#[derive(Default)]
+pub struct SelftestArgs {
+    sensor: SensorArgs,
+}
+
+impl SelftestArgs {
+    pub fn parse(mut parser: Parser) -> ArgResult<Self> {
+        let mut args = SelftestArgs::default();
+
+        while let Some(arg) = parser.next_arg() {
+            match arg {
+                Arg::Flag(flag, inline) => {
+                    if !args.sensor.parse(&mut parser, &flag, inline.clone())? {
+                        return Err(args::unknown(Arg::Flag(flag, inline)));
+                    }
+                }
+                arg => return Err(args::unknown(arg)),
+            }
+        }
+
+        Ok(args)
+    }
+}
+
+/// Returned when the self-test ran but at least one check failed.
+#[derive(Debug)]
+pub struct SelftestFailed;
+
+impl fmt::Display for SelftestFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "self-test failed")
+    }
+}
+
+impl std::error::Error for SelftestFailed {}
+
+pub fn run(args: SelftestArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let report = match args.sensor.build() {
+        Ok(mut sensor) => bmp280::selftest::run(&mut sensor),
+        // The chip ID and calibration data are already checked when the sensor is set up.
+        Err(bmp280::Error::ChipIdMismatch { found }) => {
+            let mut report = Report::default();
+            report.check("chip id", false, format!("0x{:02X}", found));
+            report
+        }
+        Err(bmp280::Error::InvalidCalibration { register, value }) => {
+            let mut report = Report::default();
+            report.check("chip id", true, format!("0x{:02X}", bmp280::CHIP_ID));
+            report.check(
+                "calibration",
+                false,
+                format!("0x{:02X} is 0x{:04X}", register, value),
+            );
+            report
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    print!("{}", report);
+
+    if report.passed() {
+        Ok(())
+    } else {
+        Err(SelftestFailed.into())
+    }
+}
@@ -0,0 +1,229 @@
+//! `bmp280 serve`: stream readings as newline delimited JSON to local clients over a Unix socket.
+
+use crate::args::{self, Arg, ArgError, ArgResult, Parser, SensorArgs};
+use crate::error;
+use crate::monitor::{self, SamplingArgs};
+use crate::output::{Format, OutputArgs};
+use bmp280::history::{History, Sample};
+use bmp280::sampler::{self, Sampler};
+use bmp280::sink::{self, Sink, Sinks};
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+const DEFAULT_SOCKET: &str = "/run/bmp280.sock";
+
+/// How far back `--history` goes by default, a little more than the 3 hour pressure tendency.
+const DEFAULT_HISTORY_LENGTH: Duration = Duration::from_secs(4 * 3600);
+
+pub fn usage() -> String {
+    format!(
+        "\
+Usage: bmp280 serve [OPTIONS]
+
+Take a reading every interval and send it as a JSON object on a single line to every client
+connected to a Unix socket, until interrupted. Clients that can't keep up are disconnected rather
+than delaying the readings for everyone else. Altitudes are relative to the first reading.
+
+Options:
+    --socket PATH       The socket to listen on (default: {})
+    --history FILE      Also keep the readings of the last hours in FILE, which survives restarts
+    --history-length TIME
+                        How far back the history goes (default: 4h)
+    --sink KIND=TARGET[,OPTION...]
+                        Also write the readings to a sink, e.g. csv=log.csv, json=-,
+                        influx=HOST:PORT, prometheus=FILE.prom or mqtt=HOST:PORT/TOPIC. The options
+                        unit=pa|hpa|kpa|inhg, decimals=N, field=NAME and tag.KEY=VALUE change what
+                        the sink gets, every=N or average=N give it only every Nth reading or
+                        the average of every N, and clock=true tags the readings with the NTP
+                        synchronization state of the system clock. batch=N, delay_s=SECONDS and
+                        spool=FILE send the readings in batches of N or every SECONDS, keeping
+                        them, in FILE if given, while the sink is down. Can be given several
+                        times.
+{}
+    --interval TIME     Time between readings, e.g. 250ms (default: 250ms)
+{}
+{}",
+        DEFAULT_SOCKET,
+        monitor::LIMIT_OPTIONS,
+        args::SENSOR_OPTIONS,
+        args::MULTI_SENSOR_OPTIONS
+    )
+}
+
+#[derive(Default)]
+pub struct ServeArgs {
+    sensor: SensorArgs,
+    sampling: SamplingArgs,
+    socket: Option<PathBuf>,
+    history: Option<PathBuf>,
+    history_length: Option<Duration>,
+    sinks: Vec<String>,
+}
+
+impl ServeArgs {
+    pub fn parse(mut parser: Parser) -> ArgResult<Self> {
+        let mut args = ServeArgs::default();
+
+        while let Some(arg) = parser.next_arg() {
+            match arg {
+                Arg::Flag(flag, inline) => {
+                    if args.sensor.parse(&mut parser, &flag, inline.clone())?
+                        || args.sampling.parse(&mut parser, &flag, inline.clone())?
+                    {
+                        continue;
+                    }
+
+                    match flag.as_str() {
+                        "socket" => args.socket = Some(parser.value(&flag, inline)?.into()),
+                        "history" => args.history = Some(parser.value(&flag, inline)?.into()),
+                        "sink" => args.sinks.push(parser.value(&flag, inline)?),
+                        "history-length" => {
+                            let value = parser.value(&flag, inline)?;
+                            args.history_length = Some(args::parse_duration(&value)?);
+                        }
+                        _ => return Err(args::unknown(Arg::Flag(flag, inline))),
+                    }
+                }
+                arg => return Err(args::unknown(arg)),
+            }
+        }
+
+        if args.history.is_some() && args.sensor.labeled() {
+            return Err(ArgError("--history only supports a single sensor".into()));
+        }
+
+        Ok(args)
+    }
+
+    /// Open the history file, sized to hold a reading every interval over the history length.
+    fn history(&self) -> bmp280::Result<Option<History>> {
+        let path = match &self.history {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+
+        let length = self.history_length.unwrap_or(DEFAULT_HISTORY_LENGTH);
+        let interval = self
+            .sampling
+            .interval
+            .unwrap_or(sampler::DEFAULT_INTERVAL)
+            .max(Duration::from_millis(1));
+        let capacity = (length.as_secs_f64() / interval.as_secs_f64()).ceil() as usize + 1;
+
+        Ok(Some(History::open(path, capacity)?))
+    }
+}
+
+/// The listening socket, which is removed again when dropped.
+struct Server {
+    listener: UnixListener,
+    path: PathBuf,
+    clients: Vec<UnixStream>,
+}
+
+impl Server {
+    fn bind(path: &Path) -> io::Result<Self> {
+        // A socket left behind by a previous run would make binding fail.
+        if let Ok(metadata) = fs::symlink_metadata(path) {
+            if metadata.file_type().is_socket() {
+                fs::remove_file(path)?;
+            }
+        }
+
+        let listener = UnixListener::bind(path)?;
+        listener.set_nonblocking(true)?;
+
+        Ok(Server {
+            listener,
+            path: path.to_owned(),
+            clients: Vec::new(),
+        })
+    }
+
+    fn accept(&mut self) -> io::Result<()> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(true)?;
+                    self.clients.push(stream);
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Send the line to all clients, dropping those that disconnected or whose buffer is full.
+    fn send(&mut self, line: &str) {
+        self.clients
+            .retain_mut(|client| client.write_all(line.as_bytes()).is_ok());
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+pub fn run(args: ServeArgs, stop: &AtomicBool) -> Result<(), Box<dyn std::error::Error>> {
+    let mut array = args.sensor.build_array()?;
+    for (_, sensor) in array.iter_mut() {
+        sensor.zero()?;
+    }
+
+    let path = args
+        .socket
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_SOCKET));
+    let mut server = Server::bind(&path)?;
+    let mut history = args.history()?;
+    let mut sinks = Sinks::new();
+    for spec in &args.sinks {
+        sinks.push(sink::open(spec)?);
+    }
+
+    let output = OutputArgs {
+        format: Format::Json,
+        ..OutputArgs::default()
+    }
+    .output(args.sensor.labeled());
+
+    let mut sampler = Sampler::new(&mut array);
+    args.sampling.apply(&mut sampler, stop);
+
+    for row in sampler {
+        server.accept()?;
+
+        for (label, measurement) in row {
+            match measurement {
+                Ok(measurement) => {
+                    if let Some(history) = &mut history {
+                        history.push(Sample::now(&measurement))?;
+                    }
+
+                    let mut line = output.json(&label, &measurement);
+                    line.push('\n');
+                    server.send(&line);
+
+                    if let Err(e) = sinks.write(&measurement) {
+                        eprintln!("bmp280: sink: {}", error::message(&e));
+                    }
+                }
+                Err(e) if label.is_empty() => eprintln!("bmp280: {}", error::message(&e)),
+                Err(e) => eprintln!("bmp280: {}: {}", label, error::message(&e)),
+            }
+        }
+        if let Err(e) = sinks.flush() {
+            eprintln!("bmp280: sink: {}", error::message(&e));
+        }
+    }
+
+    sinks.close()?;
+    Ok(())
+}
@@ -0,0 +1,518 @@
+//! Batching measurements in front of a network sink, such as an MQTT broker or a time series
+//! database, so a field logger doesn't lose data while the network is down.
+//!
+//! A `Buffered` sink takes measurements like any other `Sink`, and writes them to the sink it
+//! wraps in batches of up to `batch_size`, or whatever has been collected once the oldest of them
+//! waited for `max_delay`. A batch the sink fails to take is tried again after a backoff that
+//! doubles with every failure, and in the meantime is spooled to a file, which is sent first once
+//! the sink takes measurements again:
+//!
+//! ```
+//! use bmp280::buffer::Buffering;
+//! use bmp280::emulator::Emulator;
+//! use bmp280::sink::Sink;
+//! use bmp280::{Bmp280Builder, Measurement};
+//! use std::cell::{Cell, RefCell};
+//! use std::io;
+//! use std::rc::Rc;
+//! use std::time::Duration;
+//!
+//! /// A sink whose network goes down.
+//! struct Flaky {
+//!     online: Rc<Cell<bool>>,
+//!     received: Rc<RefCell<Vec<Measurement>>>,
+//! }
+//!
+//! impl Sink for Flaky {
+//!     fn write(&mut self, measurement: &Measurement) -> bmp280::Result<()> {
+//!         if !self.online.get() {
+//!             return Err(io::Error::new(io::ErrorKind::NotConnected, "network is down").into());
+//!         }
+//!         self.received.borrow_mut().push(measurement.clone());
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut sensor = Bmp280Builder::new()
+//!     .label("outdoor")
+//!     .build_with(Emulator::new())
+//!     .expect("Could not build device");
+//!
+//! let spool = std::env::temp_dir().join(format!("bmp280-spool-{}", std::process::id()));
+//! let online = Rc::new(Cell::new(false));
+//! let received = Rc::new(RefCell::new(Vec::new()));
+//!
+//! let mut buffering = Buffering::default();
+//! buffering.batch_size = 10;
+//! buffering.backoff = Duration::from_millis(20);
+//! buffering.spool = Some(spool.clone());
+//! let mut buffered = buffering
+//!     .open(Flaky {
+//!         online: online.clone(),
+//!         received: received.clone(),
+//!     })
+//!     .unwrap();
+//!
+//! for i in 0..25 {
+//!     let written = buffered.write(&sensor.measure().unwrap());
+//!     // The first full batch fails, and the sink isn't tried again until the backoff is over.
+//!     assert_eq!(written.is_err(), i == 9);
+//! }
+//! assert_eq!((buffered.spooled(), buffered.pending()), (20, 5));
+//!
+//! online.set(true);
+//! std::thread::sleep(Duration::from_millis(30));
+//! buffered.flush().unwrap();
+//! assert_eq!((buffered.spooled(), buffered.pending()), (0, 0));
+//!
+//! let received = received.borrow();
+//! assert_eq!(received.len(), 25);
+//! assert!(received.iter().all(|m| m.label.as_deref() == Some("outdoor")));
+//!
+//! drop(buffered);
+//! std::fs::remove_file(&spool).unwrap();
+//! ```
+//!
+//! Measurements can be sent twice if the program stops between sending spooled measurements and
+//! marking them as sent. Those still in memory when the buffer is closed or dropped are sent if
+//! the sink takes them, or else spooled. Without a spool file they are lost in that case, as are
+//! the oldest ones beyond `max_in_memory` while the sink is down.
+//!
+//! Sinks that stamp measurements with the time they are written, such as `InfluxSink`, stamp
+//! buffered ones with the time they are sent.
+
+use crate::sink::Sink;
+use crate::validate::{Implausibility, Limit, Limits};
+use crate::{Measurement, RawMeasurement, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// The settings of a `Buffered` sink.
+#[derive(Debug, Clone)]
+pub struct Buffering {
+    /// The most measurements sent at once
+    pub batch_size: usize,
+    /// The longest a measurement waits to be sent while the sink is up
+    pub max_delay: Duration,
+    /// The wait before trying again after the first failure, doubled after every further one
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+    /// The file measurements are kept in while the sink is down. Without one, they are kept in
+    /// memory.
+    pub spool: Option<PathBuf>,
+    /// The most measurements kept in memory, the oldest being dropped beyond that
+    pub max_in_memory: usize,
+}
+
+impl Default for Buffering {
+    /// Batches of 100 measurements or 10 seconds, retried after 1 second up to every 5 minutes,
+    /// without a spool file.
+    fn default() -> Self {
+        Buffering {
+            batch_size: 100,
+            max_delay: Duration::from_secs(10),
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(300),
+            spool: None,
+            max_in_memory: 10_000,
+        }
+    }
+}
+
+impl Buffering {
+    /// Put a buffer in front of `sink`, opening the spool file. Measurements spooled by an earlier
+    /// run are sent before any new ones.
+    pub fn open<S: Sink>(&self, sink: S) -> Result<Buffered<S>> {
+        let spool = match &self.spool {
+            Some(path) => Some(Spool::open(path)?),
+            None => None,
+        };
+
+        Ok(Buffered {
+            sink,
+            settings: self.clone(),
+            batch: VecDeque::new(),
+            oldest: None,
+            spool,
+            backoff: self.backoff,
+            retry_at: None,
+            dropped: 0,
+        })
+    }
+}
+
+/// The tags of `Measurement::implausible` in the spool file, 0 being `None`.
+fn implausibility_tag(implausible: Option<Implausibility>) -> (u8, f32) {
+    match implausible {
+        None => (0, 0.),
+        Some(Implausibility::Pressure(pa)) => (1, pa),
+        Some(Implausibility::Temperature(celsius)) => (2, celsius),
+        Some(Implausibility::PressureRate(rate)) => (3, rate),
+        Some(Implausibility::TemperatureRate(rate)) => (4, rate),
+    }
+}
+
+fn limit_tag(limit: Option<Limit>) -> u8 {
+    match limit {
+        None => 0,
+        Some(Limit::Below) => 1,
+        Some(Limit::Above) => 2,
+    }
+}
+
+fn limit_from_tag(tag: u8) -> Option<Limit> {
+    match tag {
+        1 => Some(Limit::Below),
+        2 => Some(Limit::Above),
+        _ => None,
+    }
+}
+
+/// Append a measurement to the spool file contents, after its length.
+fn encode(measurement: &Measurement, out: &mut Vec<u8>) -> io::Result<()> {
+    let mut record = Vec::new();
+    record.write_f32::<LittleEndian>(measurement.temperature_celsius)?;
+    record.write_f32::<LittleEndian>(measurement.pressure_kpa)?;
+    record.write_f32::<LittleEndian>(measurement.altitude_m)?;
+    record.write_i32::<LittleEndian>(measurement.raw.adc_t)?;
+    record.write_i32::<LittleEndian>(measurement.raw.adc_p)?;
+
+    let (implausible, value) = implausibility_tag(measurement.implausible);
+    record.write_u8(implausible)?;
+    record.write_f32::<LittleEndian>(value)?;
+
+    let flags = u8::from(measurement.fresh)
+        | u8::from(measurement.stale_reference) << 1
+        | u8::from(measurement.limits.saturated) << 2
+        | u8::from(measurement.sequence.is_some()) << 3
+        | u8::from(measurement.label.is_some()) << 4;
+    record.write_u8(flags)?;
+    record.write_u8(limit_tag(measurement.limits.pressure))?;
+    record.write_u8(limit_tag(measurement.limits.temperature))?;
+    record.write_u64::<LittleEndian>(measurement.sequence.unwrap_or(0))?;
+    record.write_u32::<LittleEndian>(measurement.temperature_age)?;
+    // The label takes the rest of the record.
+    record.extend_from_slice(measurement.label.as_deref().unwrap_or("").as_bytes());
+
+    let length = u32::try_from(record.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "label too long to spool"))?;
+    out.write_u32::<LittleEndian>(length)?;
+    out.extend_from_slice(&record);
+    Ok(())
+}
+
+/// The measurement of a record of the spool file, without its length.
+fn decode(mut record: &[u8]) -> io::Result<Measurement> {
+    let temperature_celsius = record.read_f32::<LittleEndian>()?;
+    let pressure_kpa = record.read_f32::<LittleEndian>()?;
+    let altitude_m = record.read_f32::<LittleEndian>()?;
+    let raw = RawMeasurement {
+        adc_t: record.read_i32::<LittleEndian>()?,
+        adc_p: record.read_i32::<LittleEndian>()?,
+    };
+
+    let implausible = record.read_u8()?;
+    let value = record.read_f32::<LittleEndian>()?;
+    let implausible = match implausible {
+        1 => Some(Implausibility::Pressure(value)),
+        2 => Some(Implausibility::Temperature(value)),
+        3 => Some(Implausibility::PressureRate(value)),
+        4 => Some(Implausibility::TemperatureRate(value)),
+        _ => None,
+    };
+
+    let flags = record.read_u8()?;
+    let limits = Limits {
+        pressure: limit_from_tag(record.read_u8()?),
+        temperature: limit_from_tag(record.read_u8()?),
+        saturated: flags & 0b100 != 0,
+    };
+    let sequence = record.read_u64::<LittleEndian>()?;
+    let temperature_age = record.read_u32::<LittleEndian>()?;
+    let label = String::from_utf8_lossy(record);
+
+    Ok(Measurement {
+        temperature_celsius,
+        pressure_kpa,
+        altitude_m,
+        raw,
+        implausible,
+        fresh: flags & 0b1 != 0,
+        label: (flags & 0b1_0000 != 0).then(|| label.into()),
+        stale_reference: flags & 0b10 != 0,
+        limits,
+        sequence: (flags & 0b1000 != 0).then_some(sequence),
+        temperature_age,
+    })
+}
+
+/// A file of measurements waiting to be sent, each after its length in bytes, the first `sent`
+/// bytes of which already were.
+struct Spool {
+    file: File,
+    len: u64,
+    sent: u64,
+    /// The measurements after the first `sent` bytes
+    pending: u64,
+}
+
+impl Spool {
+    fn open(path: &Path) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+        let (mut len, mut pending) = (0, 0);
+        while let Some(length) = contents.get(len..len + 4) {
+            let end = len + 4 + u32::from_le_bytes(length.try_into().unwrap()) as usize;
+            if end > contents.len() {
+                break;
+            }
+            len = end;
+            pending += 1;
+        }
+        // A measurement cut short by a crash is dropped.
+        if len < contents.len() {
+            file.set_len(len as u64)?;
+        }
+
+        Ok(Spool {
+            file,
+            len: len as u64,
+            sent: 0,
+            pending,
+        })
+    }
+
+    fn append<'a>(
+        &mut self,
+        measurements: impl Iterator<Item = &'a Measurement>,
+    ) -> io::Result<()> {
+        let mut contents = Vec::new();
+        let mut count = 0;
+        for measurement in measurements {
+            encode(measurement, &mut contents)?;
+            count += 1;
+        }
+
+        self.file.seek(SeekFrom::Start(self.len))?;
+        self.file.write_all(&contents)?;
+        self.len += contents.len() as u64;
+        self.pending += count;
+        Ok(())
+    }
+
+    /// The oldest measurements not sent yet, up to `count`, with the number of bytes each takes.
+    /// Those that don't decode are `None`.
+    fn peek(&mut self, count: usize) -> io::Result<Vec<(Option<Measurement>, u64)>> {
+        self.file.seek(SeekFrom::Start(self.sent))?;
+        let mut reader = BufReader::new((&self.file).take(self.len - self.sent));
+
+        let count = self.pending.min(count as u64);
+        let mut measurements = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let length = reader.read_u32::<LittleEndian>()?;
+            let mut record = vec![0; length as usize];
+            reader.read_exact(&mut record)?;
+            measurements.push((decode(&record).ok(), 4 + u64::from(length)));
+        }
+        Ok(measurements)
+    }
+
+    /// Mark the next measurement, taking `size` bytes, as sent, emptying the file once all of them
+    /// are.
+    fn consume(&mut self, size: u64) -> io::Result<()> {
+        self.sent += size;
+        self.pending -= 1;
+        if self.pending == 0 {
+            self.file.set_len(0)?;
+            self.len = 0;
+            self.sent = 0;
+        }
+        Ok(())
+    }
+}
+
+/// A sink with a buffer in front of it, made by `Buffering::open()`.
+///
+/// Writing a measurement sends the batch if it is due, and flushing sends it once it is due
+/// without new measurements. Either returns the error of the sink if sending fails, in which case
+/// the measurements are kept and sent again after the backoff.
+pub struct Buffered<S: Sink> {
+    sink: S,
+    settings: Buffering,
+    /// The measurements in memory not sent yet, oldest first
+    batch: VecDeque<Measurement>,
+    /// When the oldest measurement in `batch` was written
+    oldest: Option<Instant>,
+    spool: Option<Spool>,
+    /// The wait after the next failure
+    backoff: Duration,
+    /// When to try again after a failure
+    retry_at: Option<Instant>,
+    dropped: u64,
+}
+
+impl<S: Sink> Buffered<S> {
+    /// Send the batch if it is due, for when no measurements come in for a while.
+    pub fn poll(&mut self) -> Result<()> {
+        let full = self.batch.len() >= self.settings.batch_size;
+        let late = self
+            .oldest
+            .is_some_and(|oldest| oldest.elapsed() >= self.settings.max_delay);
+        let spooled = self.spooled() > 0;
+
+        if full || late || spooled {
+            self.send_now()?;
+        }
+        Ok(())
+    }
+
+    /// Send everything now, unless backing off after a failure. Returns whether nothing is left to
+    /// send.
+    pub fn send_now(&mut self) -> Result<bool> {
+        if self.backing_off() {
+            return Ok(false);
+        }
+
+        match self.send_all() {
+            Ok(()) => {
+                self.backoff = self.settings.backoff;
+                self.retry_at = None;
+                Ok(true)
+            }
+            Err(e) => {
+                self.retry_at = Some(Instant::now() + self.backoff);
+                self.backoff = (self.backoff * 2).min(self.settings.max_backoff);
+                self.spool_batch()?;
+                Err(e)
+            }
+        }
+    }
+
+    /// The measurements waiting in the spool file.
+    pub fn spooled(&self) -> u64 {
+        self.spool.as_ref().map_or(0, |spool| spool.pending)
+    }
+
+    /// The measurements waiting in memory.
+    pub fn pending(&self) -> usize {
+        self.batch.len()
+    }
+
+    /// The measurements dropped because too many were kept in memory, or because they didn't
+    /// read back from the spool file.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Whether the last send failed and the backoff isn't over yet.
+    pub fn backing_off(&self) -> bool {
+        self.retry_at.is_some_and(|at| Instant::now() < at)
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    /// Send the spooled measurements, then those in memory, oldest first, marking every one the
+    /// sink takes as sent.
+    fn send_all(&mut self) -> Result<()> {
+        let batch_size = self.settings.batch_size.max(1);
+
+        if let Some(spool) = &mut self.spool {
+            while spool.pending > 0 {
+                for (measurement, size) in spool.peek(batch_size)? {
+                    match measurement {
+                        Some(measurement) => self.sink.write(&measurement)?,
+                        None => self.dropped += 1,
+                    }
+                    spool.consume(size)?;
+                }
+                self.sink.flush()?;
+            }
+        }
+
+        while let Some(measurement) = self.batch.front() {
+            self.sink.write(measurement)?;
+            self.batch.pop_front();
+        }
+        self.sink.flush()?;
+        self.oldest = None;
+
+        Ok(())
+    }
+
+    /// Move the measurements in memory to the spool file, or drop the oldest ones beyond
+    /// `max_in_memory` without one.
+    fn spool_batch(&mut self) -> Result<()> {
+        match &mut self.spool {
+            Some(spool) => {
+                spool.append(self.batch.iter())?;
+                self.batch.clear();
+                self.oldest = None;
+            }
+            None => {
+                let excess = self.batch.len().saturating_sub(self.settings.max_in_memory);
+                self.batch.drain(..excess);
+                self.dropped += excess as u64;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S: Sink> Sink for Buffered<S> {
+    fn write(&mut self, measurement: &Measurement) -> Result<()> {
+        self.batch.push_back(measurement.clone());
+        self.oldest.get_or_insert_with(Instant::now);
+
+        if self.backing_off() {
+            if self.batch.len() >= self.settings.batch_size {
+                self.spool_batch()?;
+            }
+            return Ok(());
+        }
+        self.poll()
+    }
+
+    /// Send the batch if it is due, as `.poll()`.
+    fn flush(&mut self) -> Result<()> {
+        self.poll()
+    }
+
+    /// Send everything, even while backing off, spooling what the sink doesn't take, and close
+    /// the sink.
+    fn close(&mut self) -> Result<()> {
+        self.retry_at = None;
+        let sent = self.send_now();
+        if sent.is_err() && self.spool.is_none() {
+            // Nowhere left to keep them
+            self.dropped += self.batch.len() as u64;
+            self.batch.clear();
+        }
+        let closed = self.sink.close();
+        sent?;
+        closed
+    }
+}
+
+impl<S: Sink> Drop for Buffered<S> {
+    /// Send the measurements left in memory unless backing off, spooling them if that fails.
+    fn drop(&mut self) {
+        if self.batch.is_empty() || (!self.backing_off() && self.send_all().is_ok()) {
+            return;
+        }
+        // There is no one to return an error to, and nowhere else to keep them.
+        let _ = self.spool_batch();
+    }
+}
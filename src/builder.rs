@@ -0,0 +1,721 @@
+//! Setting up sensors.
+
+use crate::atmosphere::Atmosphere;
+use crate::bus::{self, BusSettings};
+use crate::config::{Config, Filter, Mode, Oversampling, Settings, SleepReads, Standby};
+use crate::correction::{PressureCorrection, SelfHeating, ThermalCorrection};
+use crate::driver::FORCED_MEASUREMENT_TIMEOUT;
+use crate::expiry::ExpiryPolicy;
+use crate::latency::BusStats;
+use crate::lazy::LazyDevice;
+use crate::observe::Observer;
+use crate::retry::RetryPolicy;
+use crate::validate::Validation;
+use crate::{is_reference_pressure, Bmp280, Calibration, Error, ReferencePressure, Result, Stats};
+use crate::{ADDRESSES, DEFAULT_I2C_ADDRESS, DEFAULT_I2C_PATH};
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::LinuxI2CDevice;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The major device number of the Linux I2C character devices.
+const I2C_MAJOR: u32 = 89;
+
+/// A builder for Bmp280 sensors.
+///
+/// ```ignore
+/// use bmp280::Bmp280Builder;
+/// let mut sensor = Bmp280Builder::new()
+///     .address(0x76) // Optional
+///     .path("/dev/i2c-1") // Optional
+///     .build().expect("Could not build device");
+///
+/// let altitude = sensor.altitude_m();
+/// ```
+///
+/// The setters take and return the builder, so they chain on `Bmp280Builder::new()` and the result
+/// can be kept in a variable. A builder is cheap to clone, to keep a configuration around and build
+/// several sensors from it:
+///
+/// ```
+/// use bmp280::emulator::Emulator;
+/// use bmp280::{Bmp280Builder, ReferencePressure};
+///
+/// let builder = Bmp280Builder::new()
+///     .ground_pressure(ReferencePressure::STANDARD_SEA_LEVEL)
+///     .retry_division_by_zero(true);
+///
+/// let mut sensors = Vec::new();
+/// for label in vec![String::from("indoor"), String::from("outdoor")] {
+///     let sensor = builder
+///         .clone()
+///         .label(label)
+///         .build_with(Emulator::new())
+///         .expect("Could not build device");
+///     sensors.push(sensor);
+/// }
+/// assert_eq!(sensors[1].label(), Some("outdoor"));
+/// ```
+///
+/// None of the `build` methods consume or change the builder, and `.build_at()` builds a sensor
+/// at another path and address without cloning it first.
+#[derive(Clone)]
+#[must_use = "the setters return the builder they change"]
+pub struct Bmp280Builder {
+    i2c_address: u16,
+    i2c_path: PathBuf,
+    label: Option<Arc<str>>,
+    ground_pressure: f32,
+    retry: RetryPolicy,
+    timeout: Option<Duration>,
+    measurement_timeout: Duration,
+    validation: Option<Validation>,
+    consistency_tolerance: Option<u32>,
+    retry_division_by_zero: bool,
+    auto_zero: bool,
+    recovery_attempts: u32,
+    verify_writes: bool,
+    block_reads: bool,
+    precompute_compensation: bool,
+    settings: Settings,
+    bus: BusSettings,
+    any_address: bool,
+    fine_max_age: Option<Duration>,
+    temperature_every: u32,
+    sleep_reads: SleepReads,
+    calibration_file: Option<PathBuf>,
+    thermal_correction: Option<ThermalCorrection>,
+    atmosphere: Option<Atmosphere>,
+    high_altitude: bool,
+    self_heating: Option<SelfHeating>,
+    reference_expiry: Option<ExpiryPolicy>,
+    observer: Option<Arc<dyn Observer>>,
+}
+
+impl Bmp280Builder {
+    pub fn new() -> Self {
+        Bmp280Builder {
+            i2c_address: DEFAULT_I2C_ADDRESS,
+            i2c_path: PathBuf::from(DEFAULT_I2C_PATH),
+            label: None,
+            ground_pressure: 0.,
+            retry: RetryPolicy::default(),
+            timeout: None,
+            measurement_timeout: FORCED_MEASUREMENT_TIMEOUT,
+            validation: None,
+            consistency_tolerance: None,
+            retry_division_by_zero: false,
+            auto_zero: false,
+            recovery_attempts: 0,
+            verify_writes: false,
+            block_reads: true,
+            precompute_compensation: false,
+            settings: Settings::default(),
+            bus: BusSettings::default(),
+            any_address: false,
+            fine_max_age: None,
+            temperature_every: 1,
+            sleep_reads: SleepReads::Flag,
+            calibration_file: None,
+            thermal_correction: None,
+            atmosphere: None,
+            high_altitude: false,
+            self_heating: None,
+            reference_expiry: None,
+            observer: None,
+        }
+    }
+
+    /// Set the address of the I2C device for the sensor. There is a default value for this, so you
+    /// do not need to specify it explicitly.
+    pub fn address(mut self, address: u16) -> Self {
+        self.i2c_address = address;
+        self
+    }
+
+    /// Accept any I2C address, not only the two a BMP280 can have, e.g. for a sensor behind an
+    /// address translator. By default `.build()` fails with `Error::UnexpectedAddress` for other
+    /// addresses.
+    pub fn allow_any_address(mut self) -> Self {
+        self.any_address = true;
+        self
+    }
+
+    /// Set the path of the I2C device for the sensor.  There is a default value for this, so you
+    /// do not need to specify it explicitly.
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.i2c_path = path.into();
+        self
+    }
+
+    /// Name the sensor, e.g. by its location or serial number. The label is carried on every
+    /// measurement and in `Bmp280::info()`, so streams of several sensors can be told apart.
+    pub fn label(mut self, label: impl AsRef<str>) -> Self {
+        self.label = Some(label.as_ref().into());
+        self
+    }
+
+    /// Set the ground pressure for the sensor. If you do not specify this, call `.zero()` on the
+    /// sensor before reading altitudes, which fail with `Error::NoReferencePressure` until then.
+    pub fn ground_pressure(mut self, pressure: ReferencePressure) -> Self {
+        self.ground_pressure = pressure.pa();
+        self
+    }
+
+    /// Zero the sensor on the first altitude reading if no ground pressure was set, instead of
+    /// returning `Error::NoReferencePressure`. That first reading is at altitude 0.
+    pub fn auto_zero(mut self, auto_zero: bool) -> Self {
+        self.auto_zero = auto_zero;
+        self
+    }
+
+    /// Subtract the warming of the die by its own conversions from the temperature readings of the
+    /// sensor, see `Bmp280::set_self_heating()`.
+    pub fn self_heating(mut self, self_heating: SelfHeating) -> Self {
+        self.self_heating = Some(self_heating);
+        self
+    }
+
+    /// Let the ground pressure expire after a while, see `expiry`.
+    pub fn reference_expiry(mut self, policy: ExpiryPolicy) -> Self {
+        self.reference_expiry = Some(policy);
+        self
+    }
+
+    /// Set how failed register reads and writes are retried. By default they are not.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Give up on a register read or write once it has been failing for this long, including
+    /// retries, returning `Error::Timeout`. A transaction blocking in the kernel can't be
+    /// interrupted, but the I2C adapter times out on its own on a stuck bus, which is reported as
+    /// `Error::Timeout` as well.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set how long to wait for a forced measurement to complete. Defaults to 100ms.
+    pub fn measurement_timeout(mut self, timeout: Duration) -> Self {
+        self.measurement_timeout = timeout;
+        self
+    }
+
+    /// Check every reading returned by `.measure()` against these limits. By default readings are
+    /// not validated.
+    pub fn validation(mut self, validation: Validation) -> Self {
+        self.validation = Some(validation);
+        self
+    }
+
+    /// Read the data registers twice, each time in a single burst, and only accept the values if
+    /// both reads agree within `tolerance` ADC counts. This guards against reading while the sensor
+    /// updates the data registers. Up to 3 reads are made before giving up with
+    /// `Error::InconsistentReading`.
+    pub fn consistency_check(mut self, tolerance: u32) -> Self {
+        self.consistency_tolerance = Some(tolerance);
+        self
+    }
+
+    /// Read temperature and pressure once more when the pressure compensation divides by zero,
+    /// instead of returning `Error::PressureDivisionByZero` right away. The temperature data is
+    /// still invalid right after power-up, which causes this.
+    pub fn retry_division_by_zero(mut self, retry: bool) -> Self {
+        self.retry_division_by_zero = retry;
+        self
+    }
+
+    /// Let `Bmp280::pressure_kpa()` compute the pressure with a temperature read up to `max_age`
+    /// ago, instead of reading the temperature before every pressure. This halves the bus traffic
+    /// of reading pressures alone as fast as possible, like a variometer does, at the cost of
+    /// pressures computed for a slightly outdated temperature.
+    ///
+    /// ```
+    /// use bmp280::emulator::Emulator;
+    /// use bmp280::Bmp280Builder;
+    /// use std::time::Duration;
+    ///
+    /// let mut sensor = Bmp280Builder::new()
+    ///     .reuse_temperature(Duration::from_secs(1))
+    ///     .build_with(Emulator::new())
+    ///     .expect("Could not build device");
+    ///
+    /// sensor.pressure_kpa().unwrap();
+    /// let before = sensor.stats().transactions;
+    /// sensor.pressure_kpa().unwrap();
+    /// assert_eq!(sensor.stats().transactions - before, 1);
+    /// ```
+    pub fn reuse_temperature(mut self, max_age: Duration) -> Self {
+        self.fine_max_age = Some(max_age);
+        self
+    }
+
+    /// Let `Bmp280::measure()` read the temperature with only every `n`th measurement, and compute
+    /// the others with the temperature read last, for reading pressures at 50 Hz and more. The
+    /// measurements in between read only the pressure, which takes one bus transaction instead of
+    /// two. The sensor still converts the temperature every time, so the gain is that of the bus
+    /// alone.
+    ///
+    /// The cost is in the pressure: it is computed for the die temperature of the last reading,
+    /// and with the calibration of the datasheet, every 0.1 °C the die warmed or cooled since makes
+    /// it off by about 15 Pa, or 1.3 m of altitude. The die temperature changes slowly, but
+    /// `n` should be small enough for it not to change much between readings, and
+    /// `Measurement::temperature_age` tells how old the temperature of a measurement is.
+    ///
+    /// ```
+    /// use bmp280::emulator::Emulator;
+    /// use bmp280::Bmp280Builder;
+    ///
+    /// let mut sensor = Bmp280Builder::new()
+    ///     .temperature_every(10)
+    ///     .build_with(Emulator::new())
+    ///     .expect("Could not build device");
+    ///
+    /// let ages: Vec<u32> = (0..12)
+    ///     .map(|_| sensor.measure().unwrap().temperature_age)
+    ///     .collect();
+    /// assert_eq!(ages, [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 0, 1]);
+    /// ```
+    pub fn temperature_every(mut self, n: u32) -> Self {
+        self.temperature_every = n.max(1);
+        self
+    }
+
+    /// When a measurement fails with a bus error that retries didn't fix, reopen the I2C device,
+    /// soft reset the sensor, read its calibration again and measure again, up to `attempts` times.
+    /// The device is only reopened for sensors from `.build()`. By default there is no recovery.
+    pub fn recovery(mut self, attempts: u32) -> Self {
+        self.recovery_attempts = attempts;
+        self
+    }
+
+    /// Read back every register written to the sensor and fail with
+    /// `Error::WriteVerificationFailed` if it doesn't hold the written value. By default writes are
+    /// not verified.
+    pub fn verify_writes(mut self, verify: bool) -> Self {
+        self.verify_writes = verify;
+        self
+    }
+
+    /// Read registers with SMBus block reads, one system call for the write of the register
+    /// address and the read, instead of a separate write and read. This also avoids adapters that
+    /// have trouble with a write followed by a read. Sensors on adapters that don't support block
+    /// reads switch to separate writes and reads by themselves. By default block reads are used,
+    /// but i2cdev returns their data in a `Vec`, so turn them off where allocating on every read
+    /// matters more than the system calls.
+    pub fn block_reads(mut self, block_reads: bool) -> Self {
+        self.block_reads = block_reads;
+        self
+    }
+
+    /// Set the oversampling of the temperature and the pressure measurements. More samples mean
+    /// less noise, but longer conversions. Defaults to x1 for the temperature and x16 for the
+    /// pressure.
+    pub fn oversampling(mut self, temperature: Oversampling, pressure: Oversampling) -> Self {
+        self.settings.oversampling(temperature, pressure);
+        self
+    }
+
+    /// Set the coefficient of the IIR filter the sensor smooths its readings with, against short
+    /// disturbances like a slammed door. Defaults to off.
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.settings.filter(filter);
+        self
+    }
+
+    /// Set the time between conversions in normal mode. Defaults to 0.5ms.
+    pub fn standby(mut self, standby: Standby) -> Self {
+        self.settings.standby(standby);
+        self
+    }
+
+    /// Set the power mode the sensor is left in after setup. In sleep mode, take readings with
+    /// `Bmp280::forced_measurement()`, or see `.sleep_reads()`. Defaults to normal mode.
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.settings.mode(mode);
+        self
+    }
+
+    /// Set what reads do while the sensor is in sleep or forced mode: take a forced measurement
+    /// first, or read the last conversion and flag it as not fresh. Defaults to flagging.
+    ///
+    /// ```
+    /// use bmp280::config::{Mode, SleepReads};
+    /// use bmp280::emulator::Emulator;
+    /// use bmp280::Bmp280Builder;
+    ///
+    /// let mut sensor = Bmp280Builder::new()
+    ///     .mode(Mode::Sleep)
+    ///     .sleep_reads(SleepReads::Force)
+    ///     .build_with(Emulator::new())
+    ///     .expect("Could not build device");
+    /// let measurement = sensor.measure().unwrap();
+    /// assert!(measurement.fresh);
+    /// assert_eq!(measurement.temperature_celsius, 25.08);
+    ///
+    /// sensor.set_sleep_reads(SleepReads::Flag);
+    /// assert!(!sensor.measure().unwrap().fresh);
+    /// ```
+    pub fn sleep_reads(mut self, sleep_reads: SleepReads) -> Self {
+        self.sleep_reads = sleep_reads;
+        self
+    }
+
+    /// The settings of this builder that a `Config` holds.
+    pub fn config(&self) -> Config {
+        Config {
+            path: self.i2c_path.clone(),
+            address: self.i2c_address,
+            settings: self.settings,
+            reference_pa: Some(self.ground_pressure).filter(|&p| is_reference_pressure(p)),
+        }
+    }
+
+    /// Derive the constants of the compensation formulas from the calibration once when the sensor
+    /// is set up, instead of on every reading, see `compensation::Compensation`. This saves some
+    /// arithmetic per reading for very high sample rates on weak CPUs. The readings are the same.
+    pub fn precompute_compensation(mut self, precompute: bool) -> Self {
+        self.precompute_compensation = precompute;
+        self
+    }
+
+    /// Set the timeout of the I2C adapter, how long the kernel waits for a transaction, including
+    /// a peripheral stretching the clock, before failing it. The kernel counts in units of 10ms,
+    /// so the timeout is rounded up to those. Applied whenever the device is opened by `.open()`,
+    /// `.build()` or `.build_lazy()`, for every device on the adapter. By default the timeout of
+    /// the adapter is left as it is, which is 1 second for most.
+    pub fn bus_timeout_ms(mut self, timeout_ms: u32) -> Self {
+        self.bus.timeout_ms = Some(timeout_ms);
+        self
+    }
+
+    /// Set how often the I2C adapter retries a transaction that isn't acknowledged, before it
+    /// fails. Like `.bus_timeout_ms()`, this is a setting of the adapter for all its devices, and
+    /// not all adapters honor it. These retries happen in the kernel, unlike those of
+    /// `.retry_policy()`.
+    pub fn bus_retries(mut self, retries: u32) -> Self {
+        self.bus.retries = Some(retries);
+        self
+    }
+
+    /// Set up the sensor with the calibration saved in this file by `Calibration::save()` instead
+    /// of reading it from the sensor. If the file doesn't exist yet, the calibration is read from
+    /// the sensor and saved to it.
+    pub fn calibration_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.calibration_file = Some(path.into());
+        self
+    }
+
+    /// Correct the pressure readings of the sensor for its thermal bias, see
+    /// `Bmp280::set_thermal_correction()`.
+    pub fn thermal_correction(mut self, correction: ThermalCorrection) -> Self {
+        self.thermal_correction = Some(correction);
+        self
+    }
+
+    /// Calculate altitudes in the given atmosphere, see `Bmp280::set_atmosphere()`.
+    pub fn atmosphere(mut self, atmosphere: Atmosphere) -> Self {
+        self.atmosphere = Some(atmosphere);
+        self
+    }
+
+    /// Set up for high-altitude balloons:
+    ///
+    /// - altitudes are calculated in `Atmosphere::US_STANDARD_1976`, which holds up to 47 km
+    /// - pressures below the 300 hPa floor of the sensor pass the validation, and are flagged in
+    ///   `Measurement::limits` instead
+    /// - pressures the compensation takes to 0 Pa or less saturate at 0 with a NaN altitude, rather
+    ///   than going negative
+    ///
+    /// ```
+    /// use bmp280::emulator::Emulator;
+    /// use bmp280::fixture::Reading;
+    /// use bmp280::validate::Limit;
+    /// use bmp280::vectors::DATASHEET_CALIBRATION;
+    /// use bmp280::{Bmp280Builder, RawMeasurement, ReferencePressure};
+    ///
+    /// let emulator = Emulator::new();
+    /// let mut sensor = Bmp280Builder::new()
+    ///     .ground_pressure(ReferencePressure::STANDARD_SEA_LEVEL)
+    ///     .high_altitude()
+    ///     .build_with(emulator.clone())
+    ///     .expect("Could not build device");
+    ///
+    /// // 5 hPa, about 36 km up
+    /// let reading = Reading::Compensated {
+    ///     temperature_celsius: -30.,
+    ///     pressure_kpa: 0.5,
+    /// };
+    /// emulator.set_raw(reading.raw(&DATASHEET_CALIBRATION));
+    /// let measurement = sensor.measure().unwrap();
+    /// assert_eq!(measurement.limits.pressure, Some(Limit::Below));
+    /// assert!((measurement.altitude_m - 35_800.).abs() < 100.);
+    ///
+    /// emulator.set_raw(RawMeasurement {
+    ///     adc_t: 480000,
+    ///     adc_p: 0xF_FFFF,
+    /// });
+    /// let measurement = sensor.measure().unwrap();
+    /// assert!(measurement.limits.saturated);
+    /// assert_eq!(measurement.pressure_kpa, 0.);
+    /// assert!(measurement.altitude_m.is_nan());
+    /// ```
+    pub fn high_altitude(mut self) -> Self {
+        self.atmosphere = Some(Atmosphere::US_STANDARD_1976);
+        self.high_altitude = true;
+        self
+    }
+
+    /// Report every register read or write and every measurement of the sensor to the observer.
+    pub fn observer(mut self, observer: Arc<dyn Observer>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Open the I2C device at the path and address of this builder, without setting up a sensor.
+    pub fn open(&self) -> Result<LinuxI2CDevice> {
+        check_device(&self.i2c_path, self.i2c_address, self.any_address)?;
+        bus::open(&self.i2c_path, self.i2c_address, self.bus)
+    }
+
+    /// Attempt to build a Bmp280 sensor from this builder.
+    pub fn build(&self) -> Result<Bmp280> {
+        let mut sensor = self
+            .build_with(self.open()?)
+            .map_err(|e| e.not_connected(&self.i2c_path, self.i2c_address))?;
+
+        sensor.path = Some(self.i2c_path.clone());
+
+        let path = self.i2c_path.clone();
+        let address = self.i2c_address;
+        let settings = self.bus;
+        sensor.reopen = Some(Box::new(move || bus::open(&path, address, settings)));
+
+        Ok(sensor)
+    }
+
+    /// Build a Bmp280 sensor with the settings of this builder, but at another I2C path and
+    /// address. The builder is left as it is, so one builder sets up any number of sensors alike,
+    /// such as one on every channel of an I2C multiplexer:
+    ///
+    /// ```no_run
+    /// use bmp280::{Bmp280Builder, ReferencePressure};
+    ///
+    /// let builder = Bmp280Builder::new()
+    ///     .ground_pressure(ReferencePressure::STANDARD_SEA_LEVEL)
+    ///     .bus_timeout_ms(50);
+    ///
+    /// let sensors = (2..6)
+    ///     .map(|channel| builder.build_at(format!("/dev/i2c-{}", channel), 0x76))
+    ///     .collect::<Result<Vec<_>, _>>()
+    ///     .expect("Could not build devices");
+    /// ```
+    pub fn build_at(&self, path: impl Into<PathBuf>, address: u16) -> Result<Bmp280> {
+        self.clone().path(path).address(address).build()
+    }
+
+    /// Build a Bmp280 sensor that talks through the given device instead of opening the I2C path
+    /// and address of this builder.
+    pub fn build_with<D>(&self, device: D) -> Result<Bmp280<D>>
+    where
+        D: I2CDevice,
+        Error: From<D::Error>,
+    {
+        let mut sensor = self.sensor(device);
+        sensor.begin()?;
+
+        Ok(sensor)
+    }
+
+    /// Build a Bmp280 sensor for the I2C path and address of this builder without touching the
+    /// bus. The device is opened and the sensor set up on first use, which is tried again on every
+    /// use until it works. This lets a service start before its I2C device node appears.
+    pub fn build_lazy(&self) -> Bmp280<LazyDevice> {
+        let path = self.i2c_path.clone();
+        let address = self.i2c_address;
+        let settings = self.bus;
+        let any_address = self.any_address;
+        let device = LazyDevice::with_opener(move || {
+            check_device(&path, address, any_address)?;
+            bus::open(&path, address, settings)
+        });
+
+        let mut sensor = self.build_lazy_with(device);
+        sensor.path = Some(self.i2c_path.clone());
+        sensor
+    }
+
+    /// Build a Bmp280 sensor that talks through the given device without touching the bus, to be
+    /// set up on first use as in `.build_lazy()`. With a `LazyDevice` from a `DevicePool`, the
+    /// device can be shared with other sensors.
+    pub fn build_lazy_with<D>(&self, device: D) -> Bmp280<D>
+    where
+        D: I2CDevice,
+        Error: From<D::Error>,
+    {
+        let mut sensor = self.sensor(device);
+        sensor.pending_setup = true;
+        sensor
+    }
+
+    /// The sensor for a device, before it has been set up.
+    fn sensor<D>(&self, device: D) -> Bmp280<D>
+    where
+        D: I2CDevice,
+        Error: From<D::Error>,
+    {
+        let reference_set_at = if is_reference_pressure(self.ground_pressure) {
+            Some(Instant::now())
+        } else {
+            None
+        };
+
+        Bmp280 {
+            i2c_device: device,
+            path: None,
+            address: self.i2c_address,
+            label: self.label.clone(),
+            calibration: Calibration::default(),
+            fine: 0,
+            fine_read: None,
+            fine_max_age: self.fine_max_age,
+            temperature_every: self.temperature_every,
+            temperature_age: 0,
+            sleep_reads: self.sleep_reads,
+            unread_conversion: false,
+            ground_pressure: self.ground_pressure,
+            retry: self.retry,
+            timeout: self.timeout,
+            measurement_timeout: self.measurement_timeout,
+            validation: self.validation.clone().map(|mut validation| {
+                if self.high_altitude {
+                    let end = *validation.pressure_range_pa.end();
+                    validation.pressure_range_pa = 0.0..=end;
+                }
+                validation
+            }),
+            consistency_tolerance: self.consistency_tolerance,
+            retry_division_by_zero: self.retry_division_by_zero,
+            auto_zero: self.auto_zero,
+            recovery_attempts: self.recovery_attempts,
+            verify_writes: self.verify_writes,
+            block_reads: self.block_reads,
+            control: self.settings.control_bits(),
+            config: self.settings.config_bits(),
+            precompute_compensation: self.precompute_compensation,
+            compensation: None,
+            calibration_file: self.calibration_file.clone(),
+            temperature_offset: 0.,
+            self_heating: self.self_heating,
+            self_heating_celsius: 0.,
+            pressure_correction: PressureCorrection::default(),
+            thermal_correction: self.thermal_correction.clone(),
+            external_temperature: None,
+            atmosphere: self.atmosphere,
+            high_altitude: self.high_altitude,
+            reference_expiry: self.reference_expiry.clone(),
+            reference_set_at,
+            refresh_failed_at: None,
+            last_raw: None,
+            stats: Stats::default(),
+            bus_stats: BusStats::default(),
+            observer: self.observer.clone(),
+            reopen: None,
+            pending_setup: false,
+        }
+    }
+}
+
+/// Check that a BMP280 can be at the address, and that the path is an I2C device, for errors that
+/// say what is wrong instead of an error from opening the device or the first transaction.
+fn check_device(path: &Path, address: u16, any_address: bool) -> Result<()> {
+    if !any_address && !ADDRESSES.contains(&address) {
+        return Err(Error::UnexpectedAddress { address });
+    }
+
+    let invalid = |reason| Error::InvalidDevicePath {
+        path: path.to_owned(),
+        reason,
+    };
+    let metadata =
+        match std::fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(invalid(
+                "it doesn't exist, check that the I2C bus is enabled and the i2c-dev module loaded",
+            )),
+            // Opening it will tell what the problem is, like missing permissions.
+            Err(_) => return Ok(()),
+        };
+
+    if !metadata.file_type().is_char_device() {
+        return Err(invalid("it isn't a character device"));
+    }
+    if libc::major(metadata.rdev() as libc::dev_t) != I2C_MAJOR {
+        return Err(invalid("it is a character device of another kind"));
+    }
+
+    Ok(())
+}
+
+impl From<&Config> for Bmp280Builder {
+    fn from(config: &Config) -> Self {
+        let mut builder = Bmp280Builder::new()
+            .path(&config.path)
+            .address(config.address);
+        builder.settings = config.settings;
+        if let Some(reference_pa) = config.reference_pa {
+            builder = builder.ground_pressure(ReferencePressure::from_pa(reference_pa));
+        }
+        builder
+    }
+}
+
+impl Bmp280Builder {
+    /// A builder set up from environment variables, for deployments like containers that are
+    /// configured without code or a config file: those of `Config::from_env()`, and
+    /// `BMP280_LABEL` for the label.
+    ///
+    /// ```
+    /// use bmp280::emulator::Emulator;
+    /// use bmp280::Bmp280Builder;
+    ///
+    /// std::env::set_var("BMP280_ADDRESS", "0x76");
+    /// std::env::set_var("BMP280_GROUND_PRESSURE", "101325");
+    /// std::env::set_var("BMP280_LABEL", "outdoor");
+    ///
+    /// let mut sensor = Bmp280Builder::from_env()
+    ///     .unwrap()
+    ///     .build_with(Emulator::new())
+    ///     .expect("Could not build device");
+    /// assert_eq!(sensor.label(), Some("outdoor"));
+    /// assert!(sensor.altitude_m().is_ok());
+    ///
+    /// std::env::set_var("BMP280_ADDRESS", "0x99999");
+    /// assert!(Bmp280Builder::from_env().is_err());
+    /// ```
+    pub fn from_env() -> Result<Self> {
+        let mut builder = Bmp280Builder::from(&Config::from_env()?);
+        if let Some(label) = std::env::var_os("BMP280_LABEL") {
+            builder = builder.label(label.to_string_lossy());
+        }
+
+        Ok(builder)
+    }
+}
+
+impl Bmp280 {
+    /// Build a sensor with the settings of a config, see `config::Config`.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        Bmp280Builder::from(config).build()
+    }
+}
+
+impl Default for Bmp280Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,64 @@
+//! Kernel settings of the Linux I2C adapter, for buses with peripherals that stretch the clock for
+//! longer than the adapter waits by default.
+//!
+//! i2cdev doesn't expose the `I2C_TIMEOUT` and `I2C_RETRIES` ioctls, so they are made here. Along
+//! with the C interface of `ffi` and the clock state of `clock`, this is the only unsafe code of
+//! the crate.
+
+#![allow(unsafe_code)]
+
+use crate::{Error, Result};
+use i2cdev::linux::LinuxI2CDevice;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+/// From linux/i2c-dev.h
+const I2C_RETRIES: libc::c_ulong = 0x0701;
+const I2C_TIMEOUT: libc::c_ulong = 0x0702;
+
+/// The arguments of `I2C_TIMEOUT` are in units of 10ms.
+const TIMEOUT_UNIT_MS: u32 = 10;
+
+/// The settings made on the adapter whenever the device is opened, unset ones are left as they
+/// are.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct BusSettings {
+    pub(crate) timeout_ms: Option<u32>,
+    pub(crate) retries: Option<u32>,
+}
+
+impl BusSettings {
+    fn apply(&self, device: &LinuxI2CDevice) -> io::Result<()> {
+        if let Some(timeout_ms) = self.timeout_ms {
+            // Rounded up, since a timeout of 0 would fail every transaction that has to wait.
+            let units = timeout_ms.div_ceil(TIMEOUT_UNIT_MS).max(1);
+            ioctl(device, I2C_TIMEOUT, units)?;
+        }
+        if let Some(retries) = self.retries {
+            ioctl(device, I2C_RETRIES, retries)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Open the device at `path` and `address` and apply the settings to its adapter.
+pub(crate) fn open(path: &Path, address: u16, settings: BusSettings) -> Result<LinuxI2CDevice> {
+    let device = LinuxI2CDevice::new(path, address)
+        .map_err(|e| Error::from(e).not_connected(path, address))?;
+    settings.apply(&device)?;
+
+    Ok(device)
+}
+
+fn ioctl(device: &LinuxI2CDevice, request: libc::c_ulong, value: u32) -> io::Result<()> {
+    // SAFETY: the file descriptor is owned by `device` and open for the duration of the call, and
+    // both requests take their argument by value rather than through a pointer.
+    let result = unsafe { libc::ioctl(device.as_raw_fd(), request as _, value as libc::c_ulong) };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,115 @@
+//! The calibration words of a sensor.
+
+use crate::compensation::Compensation;
+use crate::{Measurement, RawMeasurement, Register, Result};
+
+/// Calibration data for the BMP280, read from the sensor when it is set up. The compensation
+/// formulas of the datasheet are implemented on it, so raw readings can also be compensated later.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Calibration {
+    pub dig_t1: u16,
+    pub dig_t2: i16,
+    pub dig_t3: i16,
+
+    pub dig_p1: u16,
+    pub dig_p2: i16,
+    pub dig_p3: i16,
+    pub dig_p4: i16,
+    pub dig_p5: i16,
+    pub dig_p6: i16,
+    pub dig_p7: i16,
+    pub dig_p8: i16,
+    pub dig_p9: i16,
+
+    pub(crate) _dig_h1: u8,
+    pub(crate) _dig_h2: i16,
+    pub(crate) _dig_h3: u8,
+    pub(crate) _dig_h4: i16,
+    pub(crate) _dig_h5: i16,
+    pub(crate) _dig_h6: i8,
+}
+
+impl Calibration {
+    /// Whether the calibration data can be that of a working sensor.
+    pub fn is_valid(&self) -> bool {
+        self.invalid_word().is_none()
+    }
+
+    /// The register and value of a calibration word that can't be right.
+    pub(crate) fn invalid_word(&self) -> Option<(u8, u16)> {
+        // Unprogrammed or unreadable NVM shows up as all zeros or all ones. The all zeros case is
+        // caught below, dig_T1 and dig_P1 are divisors in the compensation formulas and can never
+        // be zero on a working part.
+        if self.words().iter().all(|&w| w == 0xFFFF) {
+            return Some(((&Register::DigT1).into(), 0xFFFF));
+        }
+        if self.dig_t1 == 0 {
+            return Some(((&Register::DigT1).into(), 0));
+        }
+        if self.dig_p1 == 0 {
+            return Some(((&Register::DigP1).into(), 0));
+        }
+
+        None
+    }
+
+    /// The calibration words as they are stored in the sensor.
+    pub(crate) fn words(&self) -> [u16; 12] {
+        [
+            self.dig_t1,
+            self.dig_t2 as u16,
+            self.dig_t3 as u16,
+            self.dig_p1,
+            self.dig_p2 as u16,
+            self.dig_p3 as u16,
+            self.dig_p4 as u16,
+            self.dig_p5 as u16,
+            self.dig_p6 as u16,
+            self.dig_p7 as u16,
+            self.dig_p8 as u16,
+            self.dig_p9 as u16,
+        ]
+    }
+
+    /// Compute temperature, pressure, and the altitude relative to `ground_pressure` (in Pa) from
+    /// raw ADC values.
+    pub fn compensate(&self, raw: RawMeasurement, ground_pressure: f32) -> Result<Measurement> {
+        self.compensation().compensate(raw, ground_pressure)
+    }
+
+    /// The temperature in degrees celsius, and the fine temperature value that the pressure
+    /// compensation needs. The temperature is NaN for values that can't come from the 20 bit ADC;
+    /// see `.checked_compensate_temperature()`.
+    pub fn compensate_temperature(&self, adc_t: i32) -> (f32, i32) {
+        self.checked_compensate_temperature(adc_t)
+            .unwrap_or((f32::NAN, 0))
+    }
+
+    /// Like `.compensate_temperature()`, but returns `Error::TemperatureComputationOverflow` for
+    /// values that can't come from the 20 bit ADC.
+    pub fn checked_compensate_temperature(&self, adc_t: i32) -> Result<(f32, i32)> {
+        self.compensation().checked_compensate_temperature(adc_t)
+    }
+
+    /// The fine temperature value `t_fine` of the datasheet, as computed by its reference code.
+    pub fn fine_temperature(&self, adc_t: i32) -> Result<i32> {
+        self.compensation().fine_temperature(adc_t)
+    }
+
+    /// The pressure in kPa. Returns `Error::PressureComputationOverflow` for ADC values that can't
+    /// come from the 20 bit ADC or a fine temperature value that overflows the formula.
+    pub fn compensate_pressure(&self, adc_p: i32, fine: i32) -> Result<f32> {
+        self.compensation().compensate_pressure(adc_p, fine)
+    }
+
+    /// The pressure in Pa as the Q24.8 fixed point value of the 64 bit reference code of the
+    /// datasheet, with the same errors as `.compensate_pressure()`.
+    pub fn pressure_q24_8(&self, adc_p: i32, fine: i32) -> Result<i64> {
+        self.compensation().pressure_q24_8(adc_p, fine)
+    }
+
+    /// The constants of the compensation formulas, derived from the calibration words.
+    pub fn compensation(&self) -> Compensation {
+        Compensation::new(self)
+    }
+}
@@ -0,0 +1,56 @@
+//! The synchronization state of the system clock, for telling apart the timestamps of devices with
+//! a clock kept in sync by NTP from those of devices with a clock that may be far off, such as a
+//! Raspberry Pi without a network or real time clock.
+//!
+//! The state is the one the kernel keeps, which chrony, ntpd and systemd-timesyncd all update, so it
+//! doesn't matter which of them is running. With `Transform::clock`, sinks tag their measurements
+//! with it:
+//!
+//! ```
+//! use bmp280::clock::ClockSync;
+//!
+//! if let Ok(clock) = ClockSync::read() {
+//!     if !clock.synchronized {
+//!         eprintln!("timestamps may be off by up to {:?}", clock.max_error);
+//!     }
+//! }
+//! ```
+//!
+//! The `adjtimex` call made to read the state is unsafe code, like the ioctls of `bus`.
+
+#![allow(unsafe_code)]
+
+use crate::Result;
+use std::io;
+use std::time::Duration;
+
+/// The synchronization state of the system clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSync {
+    /// Whether a time daemon keeps the clock in sync
+    pub synchronized: bool,
+    /// How far off the clock is estimated to be
+    pub estimated_error: Duration,
+    /// How far off the clock can be at most
+    pub max_error: Duration,
+}
+
+impl ClockSync {
+    /// Read the state from the kernel. This needs no privileges, as nothing is changed.
+    pub fn read() -> Result<Self> {
+        // SAFETY: timex is a plain C struct, for which all zeroes is valid, and modes 0 only reads
+        // the state into it.
+        let mut timex: libc::timex = unsafe { std::mem::zeroed() };
+        let state = unsafe { libc::adjtimex(&mut timex) };
+        if state < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        let micros = |value: libc::c_long| Duration::from_micros(value.max(0) as u64);
+        Ok(ClockSync {
+            synchronized: state != libc::TIME_ERROR && timex.status & libc::STA_UNSYNC == 0,
+            estimated_error: micros(timex.esterror),
+            max_error: micros(timex.maxerror),
+        })
+    }
+}
@@ -0,0 +1,214 @@
+//! The compensation formulas of the datasheet, on constants derived from the calibration words.
+//!
+//! `Calibration` derives the constants on every call, which is cheap, but not free on a weak CPU
+//! polling the sensor at a high rate. A `Compensation` derives them once, and gives the same
+//! results bit for bit:
+//!
+//! ```
+//! use bmp280::compensation::Compensation;
+//! use bmp280::vectors::DATASHEET_CALIBRATION;
+//! use bmp280::RawMeasurement;
+//!
+//! let compensation = Compensation::new(&DATASHEET_CALIBRATION);
+//! let raw = RawMeasurement { adc_t: 519888, adc_p: 415148 };
+//!
+//! assert_eq!(
+//!     compensation.compensate(raw, 101325.).unwrap(),
+//!     DATASHEET_CALIBRATION.compensate(raw, 101325.).unwrap()
+//! );
+//! ```
+//!
+//! Sensors use one with `Bmp280Builder::precompute_compensation()`.
+
+use crate::validate::Limits;
+use crate::ADC_RANGE;
+use crate::{Calibration, Error, Measurement, RawMeasurement, Result};
+
+/// The calibration words widened to the 64 bits the formulas work in, with their constant factors
+/// applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compensation {
+    t1: i64,
+    /// `dig_T1 << 1`
+    t1_x2: i64,
+    t2: i64,
+    t3: i64,
+
+    p1: i64,
+    /// `dig_P2 << 12`
+    p2_x4096: i64,
+    p3: i64,
+    /// `dig_P4 << 35`
+    p4_x2_35: i64,
+    /// `dig_P5 << 17`
+    p5_x2_17: i64,
+    p6: i64,
+    /// `dig_P7 << 4`
+    p7_x16: i64,
+    p8: i64,
+    p9: i64,
+}
+
+impl Compensation {
+    pub fn new(calibration: &Calibration) -> Self {
+        let t1 = calibration.dig_t1 as i64;
+
+        Compensation {
+            t1,
+            t1_x2: t1 << 1,
+            t2: calibration.dig_t2 as i64,
+            t3: calibration.dig_t3 as i64,
+
+            p1: calibration.dig_p1 as i64,
+            p2_x4096: (calibration.dig_p2 as i64) << 12,
+            p3: calibration.dig_p3 as i64,
+            p4_x2_35: (calibration.dig_p4 as i64) << 35,
+            p5_x2_17: (calibration.dig_p5 as i64) << 17,
+            p6: calibration.dig_p6 as i64,
+            p7_x16: (calibration.dig_p7 as i64) << 4,
+            p8: calibration.dig_p8 as i64,
+            p9: calibration.dig_p9 as i64,
+        }
+    }
+
+    /// As `Calibration::compensate()`.
+    pub fn compensate(&self, raw: RawMeasurement, ground_pressure: f32) -> Result<Measurement> {
+        let (temperature_celsius, fine) = self.checked_compensate_temperature(raw.adc_t)?;
+        let pressure_kpa = self.compensate_pressure(raw.adc_p, fine)?;
+        let altitude_m = if is_reference_pressure(ground_pressure) {
+            altitude_from_pressure(pressure_kpa * 1000., ground_pressure)
+        } else {
+            f32::NAN
+        };
+
+        Ok(Measurement {
+            temperature_celsius,
+            pressure_kpa,
+            altitude_m,
+            raw,
+            implausible: None,
+            fresh: true,
+            label: None,
+            stale_reference: false,
+            limits: Limits::of(pressure_kpa * 1000., temperature_celsius),
+            sequence: None,
+            temperature_age: 0,
+        })
+    }
+
+    /// As `Calibration::checked_compensate_temperature()`.
+    pub fn checked_compensate_temperature(&self, adc_t: i32) -> Result<(f32, i32)> {
+        let fine = self.fine_temperature(adc_t)?;
+        let t = ((fine as i64 * 5 + 128) >> 8) as f32;
+        Ok((t / 100., fine))
+    }
+
+    /// As `Calibration::fine_temperature()`.
+    pub fn fine_temperature(&self, adc_t: i32) -> Result<i32> {
+        let overflow = || Error::TemperatureComputationOverflow { adc_t };
+        if !ADC_RANGE.contains(&adc_t) {
+            return Err(overflow());
+        }
+
+        // With 20 bit ADC values and 16 bit calibration words none of this can overflow an i64.
+        let adc_t = adc_t as i64;
+
+        let var1 = (((adc_t >> 3) - self.t1_x2) * self.t2) >> 11;
+        let var2 = (((((adc_t >> 4) - self.t1) * ((adc_t >> 4) - self.t1)) >> 12) * self.t3) >> 14;
+
+        i32::try_from(var1 + var2).map_err(|_| overflow())
+    }
+
+    /// As `Calibration::compensate_pressure()`.
+    pub fn compensate_pressure(&self, adc_p: i32, fine: i32) -> Result<f32> {
+        Ok(self.pressure_q24_8(adc_p, fine)? as f32 / 256000.)
+    }
+
+    /// As `Calibration::pressure_q24_8()`.
+    pub fn pressure_q24_8(&self, adc_p: i32, fine: i32) -> Result<i64> {
+        let overflow = || Error::PressureComputationOverflow {
+            adc_p,
+            t_fine: fine,
+        };
+        if !ADC_RANGE.contains(&adc_p) {
+            return Err(overflow());
+        }
+
+        let (var1, var2) = self.pressure_terms(fine as i64).ok_or_else(overflow)?;
+        if var1 == 0 {
+            return Err(Error::PressureDivisionByZero { t_fine: fine });
+        }
+
+        self.pressure_from_terms(adc_p as i64, var1, var2)
+            .ok_or_else(overflow)
+    }
+
+    /// The divisor and the offset of the pressure formula, which depend on the temperature only.
+    /// A product with a premultiplied constant overflows exactly when multiplying in two steps
+    /// would, so the checks are those of the reference code.
+    fn pressure_terms(&self, fine: i64) -> Option<(i64, i64)> {
+        let var1 = fine - 128000;
+
+        let var2 = var1.checked_mul(var1)?.checked_mul(self.p6)?;
+        let var2 = var2.checked_add(var1.checked_mul(self.p5_x2_17)?)?;
+        let var2 = var2.checked_add(self.p4_x2_35)?;
+
+        let var1 = (var1.checked_mul(var1)?.checked_mul(self.p3)? >> 8)
+            .checked_add(var1.checked_mul(self.p2_x4096)?)?;
+        let var1 = ((1i64 << 47).checked_add(var1)?.checked_mul(self.p1)?) >> 33;
+
+        Some((var1, var2))
+    }
+
+    /// The pressure in Pa as a Q24.8 fixed point value.
+    fn pressure_from_terms(&self, adc_p: i64, var1: i64, var2: i64) -> Option<i64> {
+        let p = 1048576 - adc_p;
+        let p = (p << 31)
+            .checked_sub(var2)?
+            .checked_mul(3125)?
+            .checked_div(var1)?;
+
+        let var1 = self.p9.checked_mul(p >> 13)?.checked_mul(p >> 13)? >> 25;
+        let var2 = self.p8.checked_mul(p)? >> 19;
+
+        Some((p.checked_add(var1)?.checked_add(var2)? >> 8) + self.p7_x16)
+    }
+}
+
+impl From<&Calibration> for Compensation {
+    fn from(calibration: &Calibration) -> Self {
+        Compensation::new(calibration)
+    }
+}
+
+/// Whether a pressure in Pa can be used as the reference of altitudes. The ground pressure is 0 until
+/// it is set.
+pub(crate) fn is_reference_pressure(pressure_pa: f32) -> bool {
+    pressure_pa.is_finite() && pressure_pa > 0.
+}
+
+/// The barometric formula, converting a pressure and a reference pressure (both in Pa) into an
+/// altitude in meters.
+pub(crate) fn altitude_from_pressure(pressure_pa: f32, sea_level_pa: f32) -> f32 {
+    44330. * (1. - (pressure_pa / sea_level_pa).powf(0.1903))
+}
+
+/// The hypsometric formula, converting a pressure and a reference pressure (both in Pa) into an
+/// altitude in meters, given the air temperature in degrees celsius at the pressure. It agrees
+/// with `altitude_from_pressure()` at the temperatures of the standard atmosphere.
+pub(crate) fn hypsometric_altitude(
+    pressure_pa: f32,
+    reference_pa: f32,
+    temperature_celsius: f32,
+) -> f32 {
+    // The temperature lapse rate of the standard atmosphere, in K/m
+    const LAPSE_RATE: f32 = 0.0065;
+
+    ((reference_pa / pressure_pa).powf(0.1903) - 1.) * (temperature_celsius + 273.15) / LAPSE_RATE
+}
+
+/// The inverse of the barometric formula: the sea level pressure in Pa given the pressure in Pa
+/// measured at a known elevation in meters.
+pub fn sea_level_pressure(pressure_pa: f32, elevation_m: f32) -> f32 {
+    pressure_pa / (1. - elevation_m / 44330.).powf(1. / 0.1903)
+}
@@ -0,0 +1,806 @@
+//! Settings of the `ctrl_meas` and `config` registers, and `Config`, all settings of a sensor in a
+//! plain struct.
+//!
+//! A `Config` can be read from a TOML file, so daemons can set up their sensors without code for
+//! each setting. Every key is optional:
+//!
+//! ```
+//! use bmp280::config::{Config, Filter, Oversampling, Standby};
+//!
+//! let config = Config::from_toml(
+//!     r#"
+//!     path = "/dev/i2c-0"
+//!     address = 0x76
+//!     temperature_oversampling = 2
+//!     pressure_oversampling = 16
+//!     filter = 4
+//!     standby_ms = 62.5
+//!     mode = "normal"
+//!     reference_pa = 101325
+//!     "#,
+//! )
+//! .unwrap();
+//!
+//! assert_eq!(config.address, 0x76);
+//! assert_eq!(config.settings.temperature_oversampling, Oversampling::X2);
+//! assert_eq!(config.settings.filter, Filter::X4);
+//! assert_eq!(config.settings.standby, Standby::Ms62_5);
+//! assert_eq!(Config::from_toml(&config.to_toml()).unwrap(), config);
+//!
+//! // Quotes and backslashes in paths are escaped.
+//! let odd = Config {
+//!     path: r#"/dev/i2c-"1"\#"#.into(),
+//!     ..Config::default()
+//! };
+//! assert_eq!(Config::from_toml(&odd.to_toml()).unwrap(), odd);
+//!
+//! // A misspelled key is an error rather than a setting left at its default.
+//! let error = Config::from_toml("pressure_oversampling = 4\noversampling_pressur = 16").unwrap_err();
+//! assert_eq!(error.to_string(), "line 2: unknown key oversampling_pressur");
+//! ```
+//!
+//! Only the subset of TOML that `Config::to_toml()` writes is read: a `key = value` pair per line,
+//! `#` comments, strings in double quotes for `path` and `mode`, with `\\` and `\"` as the only
+//! escapes, integers in decimal or `0x` hexadecimal without `_` separators, and decimal numbers
+//! for `standby_ms` and `reference_pa`. Tables, arrays, multi-line and single-quoted strings
+//! aren't supported, and sections and unknown keys are errors.
+//!
+//! With the `serde` feature, `Config` and its settings implement `Serialize` and `Deserialize`,
+//! with the same keys and values as the TOML file, so a config can be read from JSON or be part of
+//! the config of a daemon:
+//!
+//! ```
+//! # #[cfg(feature = "serde")]
+//! # {
+//! use bmp280::config::{Config, Oversampling};
+//!
+//! let config: Config =
+//!     serde_json::from_str(r#"{"address": 118, "pressure_oversampling": 4, "mode": "forced"}"#)
+//!         .unwrap();
+//! assert_eq!(config.address, 0x76);
+//! assert_eq!(config.settings.pressure_oversampling, Oversampling::X4);
+//! let json = serde_json::to_string(&config).unwrap();
+//! assert_eq!(serde_json::from_str::<Config>(&json).unwrap(), config);
+//!
+//! assert!(serde_json::from_str::<Config>(r#"{"pressure_oversampling": 3}"#).is_err());
+//! assert!(serde_json::from_str::<Config>(r#"{"oversampling_pressur": 16}"#).is_err());
+//! # }
+//! ```
+//!
+//! `Bmp280::from_config()` builds the sensor, and a `Bmp280Builder` made from a config can add
+//! any other setting:
+//!
+//! ```
+//! use bmp280::config::Config;
+//! use bmp280::emulator::Emulator;
+//! use bmp280::Bmp280Builder;
+//!
+//! let config = Config::from_toml("pressure_oversampling = 4").unwrap();
+//! let emulator = Emulator::new();
+//! let mut sensor = Bmp280Builder::from(&config)
+//!     .label("outdoor")
+//!     .build_with(emulator.clone())
+//!     .expect("Could not build device");
+//!
+//! assert_eq!(emulator.register(0xF4), 0b001_011_11);
+//! assert!(sensor.measure().is_ok());
+//! ```
+
+use crate::Result;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The power mode, bits 1..0 of `ctrl_meas`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Mode {
+    /// No measurements are taken
+    Sleep,
+    /// A single measurement is taken, after which the sensor returns to sleep
+    Forced,
+    /// Measurements are taken continuously, separated by the standby time
+    Normal,
+}
+
+impl Mode {
+    pub fn bits(self) -> u8 {
+        match self {
+            Mode::Sleep => 0b00,
+            Mode::Forced => 0b01,
+            Mode::Normal => 0b11,
+        }
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0b00 => Mode::Sleep,
+            0b11 => Mode::Normal,
+            _ => Mode::Forced,
+        }
+    }
+}
+
+/// What reads do while the sensor is asleep, in sleep or forced mode, when the data registers
+/// only hold the result of the last conversion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SleepReads {
+    /// Take a forced measurement first, so the reading is a new one
+    Force,
+    /// Read the last conversion, with `Measurement::fresh` unset. Before the first conversion, the
+    /// data registers hold their reset value of 0x80000.
+    Flag,
+}
+
+/// Oversampling of temperature (bits 7..5 of `ctrl_meas`) or pressure (bits 4..2).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Oversampling {
+    /// The measurement is skipped, and its data register is left at 0x80000
+    Skipped,
+    X1,
+    X2,
+    X4,
+    X8,
+    X16,
+}
+
+impl Oversampling {
+    pub fn bits(self) -> u8 {
+        match self {
+            Oversampling::Skipped => 0b000,
+            Oversampling::X1 => 0b001,
+            Oversampling::X2 => 0b010,
+            Oversampling::X4 => 0b011,
+            Oversampling::X8 => 0b100,
+            Oversampling::X16 => 0b101,
+        }
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        match bits & 0b111 {
+            0b000 => Oversampling::Skipped,
+            0b001 => Oversampling::X1,
+            0b010 => Oversampling::X2,
+            0b011 => Oversampling::X4,
+            0b100 => Oversampling::X8,
+            _ => Oversampling::X16,
+        }
+    }
+
+    /// The number of samples taken, 0 if skipped.
+    pub fn samples(self) -> u32 {
+        match self {
+            Oversampling::Skipped => 0,
+            Oversampling::X1 => 1,
+            Oversampling::X2 => 2,
+            Oversampling::X4 => 4,
+            Oversampling::X8 => 8,
+            Oversampling::X16 => 16,
+        }
+    }
+
+    /// The oversampling taking this many samples, 0 to skip the measurement.
+    pub fn from_samples(samples: u32) -> Option<Self> {
+        match samples {
+            0 => Some(Oversampling::Skipped),
+            1 => Some(Oversampling::X1),
+            2 => Some(Oversampling::X2),
+            4 => Some(Oversampling::X4),
+            8 => Some(Oversampling::X8),
+            16 => Some(Oversampling::X16),
+            _ => None,
+        }
+    }
+}
+
+/// The IIR filter coefficient, bits 4..2 of `config`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Off,
+    X2,
+    X4,
+    X8,
+    X16,
+}
+
+impl Filter {
+    pub fn bits(self) -> u8 {
+        match self {
+            Filter::Off => 0b000,
+            Filter::X2 => 0b001,
+            Filter::X4 => 0b010,
+            Filter::X8 => 0b011,
+            Filter::X16 => 0b100,
+        }
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        match bits & 0b111 {
+            0b000 => Filter::Off,
+            0b001 => Filter::X2,
+            0b010 => Filter::X4,
+            0b011 => Filter::X8,
+            _ => Filter::X16,
+        }
+    }
+
+    /// The filter coefficient, 0 if the filter is off.
+    pub fn coefficient(self) -> u32 {
+        match self {
+            Filter::Off => 0,
+            Filter::X2 => 2,
+            Filter::X4 => 4,
+            Filter::X8 => 8,
+            Filter::X16 => 16,
+        }
+    }
+
+    /// The filter with this coefficient, 0 or 1 for none.
+    pub fn from_coefficient(coefficient: u32) -> Option<Self> {
+        match coefficient {
+            0 | 1 => Some(Filter::Off),
+            2 => Some(Filter::X2),
+            4 => Some(Filter::X4),
+            8 => Some(Filter::X8),
+            16 => Some(Filter::X16),
+            _ => None,
+        }
+    }
+}
+
+/// The inactive time between measurements in normal mode, bits 7..5 of `config`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Standby {
+    Ms0_5,
+    Ms62_5,
+    Ms125,
+    Ms250,
+    Ms500,
+    Ms1000,
+    Ms2000,
+    Ms4000,
+}
+
+impl Standby {
+    pub fn bits(self) -> u8 {
+        match self {
+            Standby::Ms0_5 => 0b000,
+            Standby::Ms62_5 => 0b001,
+            Standby::Ms125 => 0b010,
+            Standby::Ms250 => 0b011,
+            Standby::Ms500 => 0b100,
+            Standby::Ms1000 => 0b101,
+            Standby::Ms2000 => 0b110,
+            Standby::Ms4000 => 0b111,
+        }
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        match bits & 0b111 {
+            0b000 => Standby::Ms0_5,
+            0b001 => Standby::Ms62_5,
+            0b010 => Standby::Ms125,
+            0b011 => Standby::Ms250,
+            0b100 => Standby::Ms500,
+            0b101 => Standby::Ms1000,
+            0b110 => Standby::Ms2000,
+            _ => Standby::Ms4000,
+        }
+    }
+
+    /// The standby time in milliseconds.
+    pub fn millis(self) -> f32 {
+        match self {
+            Standby::Ms0_5 => 0.5,
+            Standby::Ms62_5 => 62.5,
+            Standby::Ms125 => 125.,
+            Standby::Ms250 => 250.,
+            Standby::Ms500 => 500.,
+            Standby::Ms1000 => 1000.,
+            Standby::Ms2000 => 2000.,
+            Standby::Ms4000 => 4000.,
+        }
+    }
+
+    /// The standby time of this many milliseconds.
+    pub fn from_millis(millis: f32) -> Option<Self> {
+        [
+            Standby::Ms0_5,
+            Standby::Ms62_5,
+            Standby::Ms125,
+            Standby::Ms250,
+            Standby::Ms500,
+            Standby::Ms1000,
+            Standby::Ms2000,
+            Standby::Ms4000,
+        ]
+        .iter()
+        .copied()
+        .find(|standby| standby.millis() == millis)
+    }
+}
+
+/// The typical time in milliseconds a conversion takes, from the datasheet.
+pub fn measurement_millis(temperature: Oversampling, pressure: Oversampling) -> f32 {
+    let mut millis = 1. + 2. * temperature.samples() as f32;
+    if pressure != Oversampling::Skipped {
+        millis += 2. * pressure.samples() as f32 + 0.5;
+    }
+    millis
+}
+
+/// The fraction of the time the sensor spends converting in normal mode.
+pub fn duty_cycle(temperature: Oversampling, pressure: Oversampling, standby: Standby) -> f32 {
+    let measuring = measurement_millis(temperature, pressure);
+    measuring / (measuring + standby.millis())
+}
+
+/// The contents of the `status` register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status {
+    /// A conversion is running
+    pub measuring: bool,
+    /// The calibration data is being copied from NVM to the image registers
+    pub im_update: bool,
+}
+
+impl Status {
+    pub fn from_bits(bits: u8) -> Self {
+        Status {
+            measuring: bits & 0b1000 != 0,
+            im_update: bits & 0b1 != 0,
+        }
+    }
+}
+
+/// The settings kept in the registers of the sensor, set up by `Bmp280Builder` and changed
+/// together by `Bmp280::apply()`. The setters are those of the builder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct Settings {
+    pub temperature_oversampling: Oversampling,
+    pub pressure_oversampling: Oversampling,
+    pub filter: Filter,
+    #[cfg_attr(feature = "serde", serde(rename = "standby_ms"))]
+    pub standby: Standby,
+    pub mode: Mode,
+}
+
+impl Default for Settings {
+    /// x1 temperature and x16 pressure oversampling without the filter, in normal mode with 0.5ms
+    /// of standby.
+    fn default() -> Self {
+        Settings {
+            temperature_oversampling: Oversampling::X1,
+            pressure_oversampling: Oversampling::X16,
+            filter: Filter::Off,
+            standby: Standby::Ms0_5,
+            mode: Mode::Normal,
+        }
+    }
+}
+
+impl Settings {
+    /// The settings in the `ctrl_meas` and `config` registers.
+    pub fn from_bits(control: u8, config: u8) -> Self {
+        Settings {
+            temperature_oversampling: Oversampling::from_bits(control >> 5),
+            pressure_oversampling: Oversampling::from_bits(control >> 2),
+            filter: Filter::from_bits(config >> 2),
+            standby: Standby::from_bits(config >> 5),
+            mode: Mode::from_bits(control),
+        }
+    }
+
+    pub fn oversampling(&mut self, temperature: Oversampling, pressure: Oversampling) -> &mut Self {
+        self.temperature_oversampling = temperature;
+        self.pressure_oversampling = pressure;
+        self
+    }
+
+    pub fn filter(&mut self, filter: Filter) -> &mut Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn standby(&mut self, standby: Standby) -> &mut Self {
+        self.standby = standby;
+        self
+    }
+
+    pub fn mode(&mut self, mode: Mode) -> &mut Self {
+        self.mode = mode;
+        self
+    }
+
+    /// The value of the `ctrl_meas` register.
+    pub fn control_bits(&self) -> u8 {
+        self.temperature_oversampling.bits() << 5
+            | self.pressure_oversampling.bits() << 2
+            | self.mode.bits()
+    }
+
+    /// The value of the `config` register.
+    pub fn config_bits(&self) -> u8 {
+        self.standby.bits() << 5 | self.filter.bits() << 2
+    }
+}
+
+/// The settings of a sensor. The defaults are those of `Bmp280Builder::new()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// The path of the I2C device
+    pub path: PathBuf,
+    pub address: u16,
+    pub settings: Settings,
+    /// The reference pressure in Pa that altitudes are relative to, see
+    /// `Bmp280Builder::ground_pressure()`. Only finite positive pressures are read.
+    pub reference_pa: Option<f32>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            path: PathBuf::from(crate::DEFAULT_I2C_PATH),
+            address: crate::DEFAULT_I2C_ADDRESS,
+            settings: Settings::default(),
+            reference_pa: None,
+        }
+    }
+}
+
+fn invalid(line: usize, message: impl Into<String>) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line, message.into()),
+    )
+}
+
+/// The environment variables read by `Config::from_env()` and the TOML keys they stand for.
+const ENV_KEYS: &[(&str, &str)] = &[
+    ("BMP280_I2C_PATH", "path"),
+    ("BMP280_ADDRESS", "address"),
+    (
+        "BMP280_TEMPERATURE_OVERSAMPLING",
+        "temperature_oversampling",
+    ),
+    ("BMP280_PRESSURE_OVERSAMPLING", "pressure_oversampling"),
+    ("BMP280_FILTER", "filter"),
+    ("BMP280_STANDBY_MS", "standby_ms"),
+    ("BMP280_MODE", "mode"),
+    ("BMP280_GROUND_PRESSURE", "reference_pa"),
+];
+
+/// The line without its comment. A # in a quoted path doesn't start one.
+fn strip_comment(line: &str) -> &str {
+    let (mut quoted, mut escaped) = (false, false);
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// A TOML string in double quotes, with `\\` and `\"` escaped.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The contents of a string written by `quote()`. Other escapes are rejected rather than read
+/// wrong.
+fn unquote(value: &str) -> Option<String> {
+    let mut chars = value.strip_prefix('"')?.strip_suffix('"')?.chars();
+    let mut s = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                c @ ('\\' | '"') => s.push(c),
+                _ => return None,
+            },
+            '"' => return None,
+            c => s.push(c),
+        }
+    }
+    Some(s)
+}
+
+/// An integer in decimal or, as TOML allows, `0x` hexadecimal.
+fn parse_integer(value: &str) -> Option<u32> {
+    match value.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+impl Config {
+    /// Read a config from the TOML subset described in the module docs. Keys that are left out
+    /// keep their defaults.
+    pub fn from_toml(toml: &str) -> io::Result<Self> {
+        let mut config = Config::default();
+
+        for (i, line) in toml.lines().enumerate() {
+            let number = i + 1;
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') {
+                return Err(invalid(number, format!("unexpected section {}", line)));
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid(number, "expected key = value"))?;
+            let (key, value) = (key.trim(), value.trim());
+            // Every key can also be set from the environment.
+            if !ENV_KEYS.iter().any(|&(_, known)| known == key) {
+                return Err(invalid(number, format!("unknown key {}", key)));
+            }
+            let invalid_value = || invalid(number, format!("invalid value for {}", key));
+
+            let value = match key {
+                "path" | "mode" => unquote(value).ok_or_else(invalid_value)?,
+                _ => value.into(),
+            };
+            if !config.set(key, &value) {
+                return Err(invalid_value());
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Read a config from the environment variables `BMP280_I2C_PATH`, `BMP280_ADDRESS`,
+    /// `BMP280_TEMPERATURE_OVERSAMPLING`, `BMP280_PRESSURE_OVERSAMPLING`, `BMP280_FILTER`,
+    /// `BMP280_STANDBY_MS`, `BMP280_MODE` and `BMP280_GROUND_PRESSURE`, with the values of the
+    /// TOML keys without quotes. If `BMP280_CONFIG` names a TOML file, the variables override the
+    /// settings in it. Variables that aren't set keep their defaults.
+    pub fn from_env() -> Result<Self> {
+        let mut config = match env::var_os("BMP280_CONFIG") {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+
+        for &(variable, key) in ENV_KEYS {
+            let value = match env::var_os(variable) {
+                Some(value) => value,
+                None => continue,
+            };
+
+            let valid = match value.to_str() {
+                Some(value) => config.set(key, value.trim()),
+                // Paths don't need to be unicode.
+                None if key == "path" => {
+                    config.path = PathBuf::from(&value);
+                    true
+                }
+                None => false,
+            };
+            if !valid {
+                let message = format!("invalid value for {}: {:?}", variable, value);
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, message).into());
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Set the setting of a TOML key from its value without quotes, returning whether the key is
+    /// known and the value valid.
+    fn set(&mut self, key: &str, value: &str) -> bool {
+        match key {
+            "path" => self.path = PathBuf::from(value),
+            "address" => match parse_integer(value).and_then(|a| u16::try_from(a).ok()) {
+                Some(address) => self.address = address,
+                None => return false,
+            },
+            "temperature_oversampling" => {
+                match parse_integer(value).and_then(Oversampling::from_samples) {
+                    Some(oversampling) => self.settings.temperature_oversampling = oversampling,
+                    None => return false,
+                }
+            }
+            "pressure_oversampling" => {
+                match parse_integer(value).and_then(Oversampling::from_samples) {
+                    Some(oversampling) => self.settings.pressure_oversampling = oversampling,
+                    None => return false,
+                }
+            }
+            "filter" => match parse_integer(value).and_then(Filter::from_coefficient) {
+                Some(filter) => self.settings.filter = filter,
+                None => return false,
+            },
+            "standby_ms" => match value.parse().ok().and_then(Standby::from_millis) {
+                Some(standby) => self.settings.standby = standby,
+                None => return false,
+            },
+            "mode" => {
+                self.settings.mode = match value {
+                    "sleep" => Mode::Sleep,
+                    "forced" => Mode::Forced,
+                    "normal" => Mode::Normal,
+                    _ => return false,
+                }
+            }
+            "reference_pa" => match value.parse::<f32>() {
+                Ok(pa) if pa.is_finite() && pa > 0. => self.reference_pa = Some(pa),
+                _ => return false,
+            },
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// The config as a TOML document.
+    pub fn to_toml(&self) -> String {
+        let settings = &self.settings;
+        let mode = match settings.mode {
+            Mode::Sleep => "sleep",
+            Mode::Forced => "forced",
+            Mode::Normal => "normal",
+        };
+
+        // Writing to a String can't fail.
+        let mut toml = String::new();
+        let _ = writeln!(toml, "path = {}", quote(&self.path.display().to_string()));
+        let _ = writeln!(toml, "address = 0x{:02x}", self.address);
+        let _ = writeln!(
+            toml,
+            "temperature_oversampling = {}",
+            settings.temperature_oversampling.samples()
+        );
+        let _ = writeln!(
+            toml,
+            "pressure_oversampling = {}",
+            settings.pressure_oversampling.samples()
+        );
+        let _ = writeln!(toml, "filter = {}", settings.filter.coefficient());
+        let _ = writeln!(toml, "standby_ms = {}", settings.standby.millis());
+        let _ = writeln!(toml, "mode = \"{}\"", mode);
+        if let Some(reference_pa) = self.reference_pa {
+            let _ = writeln!(toml, "reference_pa = {}", reference_pa);
+        }
+        toml
+    }
+
+    /// Read a config from a TOML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Config::from_toml(&fs::read_to_string(path)?)?)
+    }
+}
+
+/// `Config` is (de)serialized with the keys of its TOML file, with the settings as numbers:
+/// oversampling as the number of samples, the filter as its coefficient and the standby time in
+/// milliseconds.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::{Config, Filter, Mode, Oversampling, Settings, Standby};
+    use serde::de::Error as _;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::path::PathBuf;
+
+    impl Serialize for Oversampling {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_u32(self.samples())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Oversampling {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let samples = u32::deserialize(deserializer)?;
+            Oversampling::from_samples(samples)
+                .ok_or_else(|| D::Error::custom(format!("invalid oversampling {}", samples)))
+        }
+    }
+
+    impl Serialize for Filter {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_u32(self.coefficient())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Filter {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let coefficient = u32::deserialize(deserializer)?;
+            Filter::from_coefficient(coefficient)
+                .ok_or_else(|| D::Error::custom(format!("invalid filter {}", coefficient)))
+        }
+    }
+
+    impl Serialize for Standby {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_f32(self.millis())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Standby {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let millis = f32::deserialize(deserializer)?;
+            Standby::from_millis(millis)
+                .ok_or_else(|| D::Error::custom(format!("invalid standby time {}", millis)))
+        }
+    }
+
+    /// The keys of the TOML file, every one optional.
+    #[derive(Serialize, Deserialize)]
+    #[serde(default, deny_unknown_fields)]
+    struct Fields {
+        path: PathBuf,
+        address: u16,
+        temperature_oversampling: Oversampling,
+        pressure_oversampling: Oversampling,
+        filter: Filter,
+        standby_ms: Standby,
+        mode: Mode,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reference_pa: Option<f32>,
+    }
+
+    impl Default for Fields {
+        fn default() -> Self {
+            Config::default().into()
+        }
+    }
+
+    impl From<Config> for Fields {
+        fn from(config: Config) -> Self {
+            let settings = config.settings;
+            Fields {
+                path: config.path,
+                address: config.address,
+                temperature_oversampling: settings.temperature_oversampling,
+                pressure_oversampling: settings.pressure_oversampling,
+                filter: settings.filter,
+                standby_ms: settings.standby,
+                mode: settings.mode,
+                reference_pa: config.reference_pa,
+            }
+        }
+    }
+
+    impl Serialize for Config {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            Fields::from(self.clone()).serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Config {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let fields = Fields::deserialize(deserializer)?;
+            if let Some(pa) = fields.reference_pa {
+                if !(pa.is_finite() && pa > 0.) {
+                    return Err(D::Error::custom(format!("invalid reference_pa {}", pa)));
+                }
+            }
+
+            Ok(Config {
+                path: fields.path,
+                address: fields.address,
+                settings: Settings {
+                    temperature_oversampling: fields.temperature_oversampling,
+                    pressure_oversampling: fields.pressure_oversampling,
+                    filter: fields.filter,
+                    standby: fields.standby_ms,
+                    mode: fields.mode,
+                },
+                reference_pa: fields.reference_pa,
+            })
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! Linear corrections of the pressure readings against a calibrated reference instrument, like a
+//! manometer.
+//!
+//! ```
+//! use bmp280::correction::PressureCorrection;
+//!
+//! // The sensor read 100020 Pa and 90070 Pa where the manometer showed 100000 Pa and 90000 Pa.
+//! let correction = PressureCorrection::two_point((100000., 100020.), (90000., 90070.)).unwrap();
+//!
+//! assert!((correction.apply(100020.) - 100000.).abs() < 0.01);
+//! assert!((correction.apply(95045.) - 95000.).abs() < 0.01);
+//! ```
+//!
+//! Set the correction on a sensor with `Bmp280::set_pressure_correction()`. A temperature dependent
+//! offset can be added with `ThermalCorrection`, and the warming of the die by its own conversions
+//! removed from the temperature with `SelfHeating`.
+
+use crate::config::{self, Oversampling, Standby};
+use crate::Result;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A gain and an offset applied to every pressure reading: `gain * pressure + offset_pa`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PressureCorrection {
+    pub gain: f32,
+    pub offset_pa: f32,
+}
+
+impl Default for PressureCorrection {
+    /// No correction.
+    fn default() -> Self {
+        PressureCorrection {
+            gain: 1.,
+            offset_pa: 0.,
+        }
+    }
+}
+
+impl PressureCorrection {
+    /// Just an offset, as from `Bmp280::set_offsets()`.
+    pub fn offset(offset_pa: f32) -> Self {
+        PressureCorrection {
+            offset_pa,
+            ..PressureCorrection::default()
+        }
+    }
+
+    /// The correction that maps two measured pressures exactly onto the reference pressures, from
+    /// `(reference, measured)` pairs in Pa. Returns `None` if the measured pressures are too close
+    /// together to tell a gain from noise.
+    pub fn two_point(first: (f32, f32), second: (f32, f32)) -> Option<Self> {
+        let (reference_1, measured_1) = (first.0 as f64, first.1 as f64);
+        let (reference_2, measured_2) = (second.0 as f64, second.1 as f64);
+
+        // 1 Pa is several times the noise of the sensor.
+        if (measured_2 - measured_1).abs() < 1. {
+            return None;
+        }
+
+        let gain = (reference_2 - reference_1) / (measured_2 - measured_1);
+        let offset = reference_1 - gain * measured_1;
+        if !gain.is_finite() || !offset.is_finite() || gain <= 0. {
+            return None;
+        }
+
+        Some(PressureCorrection {
+            gain: gain as f32,
+            offset_pa: offset as f32,
+        })
+    }
+
+    /// The corrected pressure in Pa.
+    pub fn apply(&self, pressure_pa: f32) -> f32 {
+        // Computed in f64 since the offset is large next to the correction after a gain change.
+        (self.gain as f64 * pressure_pa as f64 + self.offset_pa as f64) as f32
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == PressureCorrection::default()
+    }
+}
+
+/// A pressure offset that depends on the temperature, for boards whose thermal bias has been
+/// measured, e.g. in an environmental chamber. The offset in Pa is the polynomial
+/// `c0 + c1 * dt + c2 * dt^2 + ...` of `dt`, the die temperature of the sensor minus the reference
+/// temperature.
+///
+/// The coefficients can be kept in a TOML file:
+///
+/// ```
+/// use bmp280::correction::ThermalCorrection;
+///
+/// let correction = ThermalCorrection::from_toml(
+///     "
+///     reference_celsius = 25
+///     coefficients = [0.5, -1.2, 0.03]
+///     ",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(correction.offset_pa(25.), 0.5);
+/// assert!((correction.offset_pa(35.) - -8.5).abs() < 0.001);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ThermalCorrection {
+    pub reference_celsius: f32,
+    /// The coefficients by rising power, starting with the constant offset
+    pub coefficients: Vec<f32>,
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+impl ThermalCorrection {
+    /// The pressure offset in Pa at a die temperature.
+    pub fn offset_pa(&self, temperature_celsius: f32) -> f32 {
+        let dt = (temperature_celsius - self.reference_celsius) as f64;
+
+        // Horner's method
+        let offset = self
+            .coefficients
+            .iter()
+            .rev()
+            .fold(0., |offset, &c| offset * dt + c as f64);
+        offset as f32
+    }
+
+    /// Read the `reference_celsius` and `coefficients` keys of a TOML document. The reference
+    /// temperature defaults to 0.
+    pub fn from_toml(toml: &str) -> io::Result<Self> {
+        let mut correction = ThermalCorrection::default();
+        let mut coefficients = None;
+
+        for line in toml.lines() {
+            let line = match line.split_once('#') {
+                Some((line, _)) => line.trim(),
+                None => line.trim(),
+            };
+            if line.is_empty() || line.starts_with('[') {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("expected key = value, found '{}'", line)))?;
+            let value = value.trim();
+
+            match key.trim() {
+                "reference_celsius" => {
+                    correction.reference_celsius = value
+                        .parse()
+                        .map_err(|_| invalid("invalid value for reference_celsius"))?
+                }
+                "coefficients" => {
+                    let list = value
+                        .strip_prefix('[')
+                        .and_then(|v| v.strip_suffix(']'))
+                        .ok_or_else(|| invalid("coefficients must be an array"))?;
+                    let parsed: std::result::Result<Vec<f32>, _> = list
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|c| !c.is_empty())
+                        .map(str::parse)
+                        .collect();
+                    coefficients =
+                        Some(parsed.map_err(|_| invalid("invalid value in coefficients"))?);
+                }
+                // Ignore unknown keys, e.g. notes about the characterization.
+                _ => {}
+            }
+        }
+
+        correction.coefficients = coefficients.ok_or_else(|| invalid("missing coefficients"))?;
+        Ok(correction)
+    }
+
+    /// Read a TOML file as in `ThermalCorrection::from_toml()`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(ThermalCorrection::from_toml(&fs::read_to_string(path)?)?)
+    }
+}
+
+/// The warming of the die by its own conversions, which makes the sensor read warmer than the air
+/// around it. It grows with the fraction of the time the sensor spends converting, so with the
+/// oversampling and the standby time.
+///
+/// ```
+/// use bmp280::config::{Oversampling, Standby};
+/// use bmp280::correction::SelfHeating;
+///
+/// let heating = SelfHeating {
+///     continuous_celsius: 1.2,
+/// };
+///
+/// let fast = heating.delta_celsius(Oversampling::X16, Oversampling::X16, Standby::Ms0_5);
+/// let slow = heating.delta_celsius(Oversampling::X1, Oversampling::X1, Standby::Ms1000);
+/// assert!(fast > 1.1);
+/// assert!(slow < 0.01);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelfHeating {
+    /// The warming in degrees celsius when the sensor converts without pause, as measured against
+    /// a reference thermometer
+    pub continuous_celsius: f32,
+}
+
+impl SelfHeating {
+    /// The warming in degrees celsius in normal mode with the given settings.
+    pub fn delta_celsius(
+        &self,
+        temperature: Oversampling,
+        pressure: Oversampling,
+        standby: Standby,
+    ) -> f32 {
+        self.continuous_celsius * config::duty_cycle(temperature, pressure, standby)
+    }
+}
@@ -0,0 +1,282 @@
+//! CSV logs of raw samples, which can be compensated again later with improved formulas.
+//!
+//! A raw log starts with one comment line per sensor holding its calibration data, followed by a
+//! header row and one row per sample:
+//!
+//! ```text
+//! # calibration outdoor dig_T1=27504 dig_T2=26435 dig_T3=-1000 dig_P1=36477 ...
+//! label,pressure_kpa,altitude_m,temperature_celsius,adc_t,adc_p
+//! outdoor,100.653,0.00,25.08,519888,415148
+//! ```
+//!
+//! The label is left out of the calibration line and the rows when there is only a single sensor,
+//! and quoted in the rows if it holds a `,` or `"`, see `escape()`.
+//! Only the `adc_t` and `adc_p` columns are needed to compensate the samples again; all other
+//! columns are ignored by `RawLog::parse()`.
+//!
+//! ```
+//! use bmp280::csv::RawLog;
+//!
+//! let log = "\
+//! ## calibration dig_T1=27504 dig_T2=26435 dig_T3=-1000 dig_P1=36477 dig_P2=-10685 dig_P3=3024 \
+//!     dig_P4=2855 dig_P5=140 dig_P6=-7 dig_P7=15500 dig_P8=-14600 dig_P9=6000
+//! pressure_kpa,adc_t,adc_p
+//! 100.653,519888,415148
+//! ";
+//!
+//! let log = RawLog::parse(log.as_bytes()).unwrap();
+//! for (_, measurement) in log.compensate(bmp280::STANDARD_SEA_LEVEL_PA) {
+//!     let measurement = measurement.unwrap();
+//!     assert_eq!(measurement.temperature_celsius, 25.08);
+//!     assert!((measurement.pressure_kpa - 100.653).abs() < 0.001);
+//! }
+//! ```
+
+use crate::{Calibration, Measurement, RawMeasurement, Result};
+use std::io::{self, BufRead, Write};
+
+const CALIBRATION_PREFIX: &str = "# calibration";
+
+/// The names of the calibration words, as in the datasheet.
+pub(crate) const NAMES: [&str; 12] = [
+    "dig_T1", "dig_T2", "dig_T3", "dig_P1", "dig_P2", "dig_P3", "dig_P4", "dig_P5", "dig_P6",
+    "dig_P7", "dig_P8", "dig_P9",
+];
+
+/// The header columns holding the raw values.
+pub const RAW_COLUMNS: [&str; 2] = ["adc_t", "adc_p"];
+
+/// The calibration words in the order of `NAMES`.
+pub(crate) fn values(cal: &Calibration) -> [i32; 12] {
+    [
+        i32::from(cal.dig_t1),
+        i32::from(cal.dig_t2),
+        i32::from(cal.dig_t3),
+        i32::from(cal.dig_p1),
+        i32::from(cal.dig_p2),
+        i32::from(cal.dig_p3),
+        i32::from(cal.dig_p4),
+        i32::from(cal.dig_p5),
+        i32::from(cal.dig_p6),
+        i32::from(cal.dig_p7),
+        i32::from(cal.dig_p8),
+        i32::from(cal.dig_p9),
+    ]
+}
+
+/// The calibration with the words in the order of `NAMES`, or what is wrong with them.
+pub(crate) fn from_values(values: &[Option<i32>; 12]) -> std::result::Result<Calibration, String> {
+    let mut words = [0; 12];
+    for (i, value) in values.iter().enumerate() {
+        words[i] = value.ok_or_else(|| format!("missing {}", NAMES[i]))?;
+    }
+
+    // dig_T1 and dig_P1 are unsigned, all other words signed.
+    let unsigned =
+        |i: usize| u16::try_from(words[i]).map_err(|_| format!("{} out of range", NAMES[i]));
+    let signed =
+        |i: usize| i16::try_from(words[i]).map_err(|_| format!("{} out of range", NAMES[i]));
+
+    Ok(Calibration {
+        dig_t1: unsigned(0)?,
+        dig_t2: signed(1)?,
+        dig_t3: signed(2)?,
+        dig_p1: unsigned(3)?,
+        dig_p2: signed(4)?,
+        dig_p3: signed(5)?,
+        dig_p4: signed(6)?,
+        dig_p5: signed(7)?,
+        dig_p6: signed(8)?,
+        dig_p7: signed(9)?,
+        dig_p8: signed(10)?,
+        dig_p9: signed(11)?,
+        ..Calibration::default()
+    })
+}
+
+/// Quote a CSV value if it needs to be, as the label column of a log.
+///
+/// ```
+/// use bmp280::csv;
+///
+/// assert_eq!(csv::escape("outdoor"), "outdoor");
+/// assert_eq!(csv::escape("roof, \"north\""), r#""roof, ""north""""#);
+/// ```
+pub fn escape(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// The fields of a row, unquoted.
+fn fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields
+        .iter()
+        .map(|field| field.trim().to_string())
+        .collect()
+}
+
+/// Write the comment line holding the calibration data of the sensor with the given label, which
+/// may be empty for a single sensor.
+pub fn write_calibration(mut writer: impl Write, label: &str, cal: &Calibration) -> io::Result<()> {
+    let values = values(cal);
+
+    write!(writer, "{}", CALIBRATION_PREFIX)?;
+    if !label.is_empty() {
+        write!(writer, " {}", label)?;
+    }
+    for (name, value) in NAMES.iter().zip(values.iter()) {
+        write!(writer, " {}={}", name, value)?;
+    }
+    writeln!(writer)
+}
+
+fn invalid(line: usize, message: impl Into<String>) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line, message.into()),
+    )
+}
+
+fn parse_calibration(line: usize, s: &str) -> io::Result<(String, Calibration)> {
+    let mut label = String::new();
+    let mut values = [None; 12];
+
+    for part in s.split_whitespace() {
+        let (name, value) = match part.split_once('=') {
+            Some(pair) => pair,
+            None if label.is_empty() => {
+                label = part.into();
+                continue;
+            }
+            None => return Err(invalid(line, format!("unexpected '{}'", part))),
+        };
+
+        let index = NAMES
+            .iter()
+            .position(|&n| n == name)
+            .ok_or_else(|| invalid(line, format!("unknown calibration word '{}'", name)))?;
+        let value: i32 = value
+            .parse()
+            .map_err(|_| invalid(line, format!("invalid value for {}", name)))?;
+        values[index] = Some(value);
+    }
+
+    let cal = from_values(&values).map_err(|message| invalid(line, message))?;
+
+    Ok((label, cal))
+}
+
+/// The calibration data and raw samples of a log.
+#[derive(Debug, Clone, Default)]
+pub struct RawLog {
+    pub calibrations: Vec<(String, Calibration)>,
+    pub samples: Vec<(String, RawMeasurement)>,
+}
+
+impl RawLog {
+    /// Read a log written with calibration lines and `adc_t` and `adc_p` columns.
+    pub fn parse(input: impl BufRead) -> io::Result<Self> {
+        let mut log = RawLog::default();
+        let mut columns: Option<(Option<usize>, usize, usize)> = None;
+
+        for (i, line) in input.lines().enumerate() {
+            let line = line?;
+            let number = i + 1;
+            let line = line.trim();
+
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(rest) = line.strip_prefix(CALIBRATION_PREFIX) {
+                log.calibrations.push(parse_calibration(number, rest)?);
+                continue;
+            }
+            if line.starts_with('#') {
+                continue;
+            }
+
+            let fields = fields(line);
+
+            let (label, adc_t, adc_p) = match columns {
+                Some(columns) => columns,
+                None => {
+                    let find = |name: &str| fields.iter().position(|field| field == name);
+                    let adc_t = find("adc_t").ok_or_else(|| invalid(number, "no adc_t column"))?;
+                    let adc_p = find("adc_p").ok_or_else(|| invalid(number, "no adc_p column"))?;
+                    columns = Some((find("label"), adc_t, adc_p));
+                    continue;
+                }
+            };
+
+            let field = |index: usize| {
+                fields
+                    .get(index)
+                    .map(String::as_str)
+                    .ok_or_else(|| invalid(number, "missing column"))
+            };
+            let number_field = |index: usize| -> io::Result<i32> {
+                field(index)?
+                    .parse()
+                    .map_err(|_| invalid(number, "invalid ADC value"))
+            };
+
+            let label = match label {
+                Some(index) => field(index)?.to_string(),
+                None => String::new(),
+            };
+            if log.calibration(&label).is_none() {
+                return Err(invalid(number, format!("no calibration for '{}'", label)));
+            }
+
+            log.samples.push((
+                label,
+                RawMeasurement {
+                    adc_t: number_field(adc_t)?,
+                    adc_p: number_field(adc_p)?,
+                },
+            ));
+        }
+
+        Ok(log)
+    }
+
+    /// The calibration data of the sensor with the given label.
+    pub fn calibration(&self, label: &str) -> Option<&Calibration> {
+        self.calibrations
+            .iter()
+            .find(|(l, _)| l == label)
+            .map(|(_, cal)| cal)
+    }
+
+    /// Compensate all samples, in order, with altitudes relative to `ground_pressure` in Pa.
+    pub fn compensate(&self, ground_pressure: f32) -> Vec<(String, Result<Measurement>)> {
+        self.samples
+            .iter()
+            .map(|(label, raw)| {
+                let cal = self.calibration(label).expect("checked when parsing");
+                (label.clone(), cal.compensate(*raw, ground_pressure))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,168 @@
+//! Uploading weather reports to the Citizen Weather Observer Program (CWOP), over the TCP
+//! connection of APRS-IS. Built with the `cwop` feature, since it talks to the network.
+//!
+//! A `CwopUploader` makes the packets of `weather::cwop_packet()` from the readings of a sensor,
+//! working out the sea level pressure from the elevation of the station, and submits them no more
+//! often than CWOP asks for:
+//!
+//! ```
+//! use bmp280::cwop::CwopUploader;
+//! use bmp280::weather::Station;
+//! use std::io::{BufRead, BufReader, Write};
+//! use std::net::TcpListener;
+//! use std::time::SystemTime;
+//!
+//! // A stand-in for cwop.aprs.net
+//! let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+//! let server = listener.local_addr().unwrap().to_string();
+//! let received = std::thread::spawn(move || {
+//!     let (mut stream, _) = listener.accept().unwrap();
+//!     stream.write_all(b"# aprsc 2.1.10\r\n").unwrap();
+//!     let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+//!     let login = lines.next().unwrap().unwrap();
+//!     stream.write_all(b"# logresp CW0001 unverified, server T2TEST\r\n").unwrap();
+//!     (login, lines.next().unwrap().unwrap())
+//! });
+//!
+//! let station = Station {
+//!     callsign: "CW0001".into(),
+//!     latitude: 37.6188,
+//!     longitude: -122.375,
+//! };
+//! let mut uploader = CwopUploader::new(station, 4.);
+//! uploader.server = server;
+//!
+//! assert!(uploader.is_due());
+//! uploader.upload(SystemTime::now(), 100653., 25.08).unwrap();
+//! assert!(!uploader.is_due());
+//!
+//! let (login, packet) = received.join().unwrap();
+//! assert!(login.starts_with("user CW0001 pass -1 vers bmp280"));
+//! assert!(packet.starts_with("CW0001>APRS,TCPIP*:@"));
+//! assert!(packet.ends_with("t077b10070"));
+//! ```
+
+use crate::weather::{cwop_packet, Station};
+use crate::{sea_level_pressure, Bmp280, Error, Result};
+use i2cdev::core::I2CDevice;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant, SystemTime};
+
+/// The APRS-IS server of CWOP.
+pub const CWOP_SERVER: &str = "cwop.aprs.net:14580";
+
+/// CWOP asks stations not to report more often than this.
+pub const MIN_UPLOAD_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// How long to wait for the server before giving up on an upload.
+const SERVER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Submits the readings of a station to CWOP.
+#[derive(Debug, Clone)]
+pub struct CwopUploader {
+    pub station: Station,
+    /// The elevation of the sensor in meters, to work out the sea level pressure
+    pub elevation_m: f32,
+    /// The APRS-IS server, `CWOP_SERVER` by default
+    pub server: String,
+    /// How often `.upload_sensor()` uploads, 10 minutes by default and at least
+    /// `MIN_UPLOAD_INTERVAL`
+    pub interval: Duration,
+    /// The APRS-IS passcode of a licensed radio amateur; CWOP stations without one log in with -1
+    pub passcode: Option<u16>,
+    last_upload: Option<Instant>,
+}
+
+impl CwopUploader {
+    pub fn new(station: Station, elevation_m: f32) -> Self {
+        CwopUploader {
+            station,
+            elevation_m,
+            server: CWOP_SERVER.to_owned(),
+            interval: Duration::from_secs(10 * 60),
+            passcode: None,
+            last_upload: None,
+        }
+    }
+
+    /// Whether the interval has passed since the last upload.
+    pub fn is_due(&self) -> bool {
+        let interval = self.interval.max(MIN_UPLOAD_INTERVAL);
+
+        self.last_upload
+            .is_none_or(|last| last.elapsed() >= interval)
+    }
+
+    /// Read the sensor and upload the reading if it is due, returning whether it was.
+    pub fn upload_sensor<D>(&mut self, sensor: &mut Bmp280<D>) -> Result<bool>
+    where
+        D: I2CDevice,
+        Error: From<D::Error>,
+    {
+        if !self.is_due() {
+            return Ok(false);
+        }
+
+        let measurement = sensor.measure()?;
+        self.upload(
+            SystemTime::now(),
+            measurement.pressure_kpa * 1000.,
+            measurement.temperature_celsius,
+        )?;
+
+        Ok(true)
+    }
+
+    /// Upload a reading of the pressure in Pa at the sensor and the temperature taken at `at`,
+    /// whether it is due or not.
+    pub fn upload(
+        &mut self,
+        at: SystemTime,
+        pressure_pa: f32,
+        temperature_celsius: f32,
+    ) -> Result<()> {
+        let sea_level_pa = sea_level_pressure(pressure_pa, self.elevation_m);
+        let packet = cwop_packet(&self.station, at, sea_level_pa, temperature_celsius);
+        let passcode = self.passcode.map_or(-1, i32::from);
+        let login = format!(
+            "user {} pass {} vers bmp280 {}",
+            self.station.callsign,
+            passcode,
+            env!("CARGO_PKG_VERSION")
+        );
+
+        submit(&self.server, &login, &packet)?;
+        self.last_upload = Some(Instant::now());
+
+        Ok(())
+    }
+}
+
+/// Log in to the APRS-IS server and send the packet.
+fn submit(server: impl ToSocketAddrs, login: &str, packet: &str) -> io::Result<()> {
+    let addr = server
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "the server has no address"))?;
+    let mut stream = TcpStream::connect_timeout(&addr, SERVER_TIMEOUT)?;
+    stream.set_read_timeout(Some(SERVER_TIMEOUT))?;
+    stream.set_write_timeout(Some(SERVER_TIMEOUT))?;
+
+    // The server greets with a comment line, and answers the login with another one.
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    write!(stream, "{}\r\n", login)?;
+    line.clear();
+    reader.read_line(&mut line)?;
+    if !line.starts_with("# logresp") {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unexpected answer to the login: {}", line.trim_end()),
+        ));
+    }
+
+    write!(stream, "{}\r\n", packet)?;
+    stream.flush()
+}
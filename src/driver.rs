@@ -0,0 +1,1426 @@
+//! Reading the sensor.
+
+use crate::atmosphere::Atmosphere;
+use crate::compensation::Compensation;
+use crate::config::{measurement_millis, Mode, Oversampling, Settings, SleepReads};
+use crate::config::{Standby, Status};
+use crate::correction::{PressureCorrection, SelfHeating, ThermalCorrection};
+use crate::error::{is_bus_failure, is_unsupported};
+use crate::expiry::{ExpiryPolicy, Refresh};
+use crate::field::{self, Byte, Field};
+use crate::latency::BusStats;
+use crate::observe::{Observer, Transaction};
+use crate::retry::RetryPolicy;
+use crate::validate::{self, Implausibility, Limits, Validation};
+use crate::{altitude_from_pressure, hypsometric_altitude, is_reference_pressure};
+use crate::{Calibration, Error, RawMeasurement, ReferencePressure, Register, Result};
+use crate::{CHIP_ID, SOFT_RESET};
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::LinuxI2CDevice;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The default for the longest a forced measurement may take. The datasheet gives 43.2ms at x16
+/// oversampling.
+pub(crate) const FORCED_MEASUREMENT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// How often the data registers are read at most to get two consecutive reads that agree, when
+/// the consistency check is enabled.
+const CONSISTENCY_READS: u32 = 3;
+
+/// The longest SMBus block read.
+const SMBUS_BLOCK_MAX: usize = 32;
+
+/// The start-up time of the sensor after a reset, according to the datasheet.
+const START_UP_TIME: Duration = Duration::from_millis(2);
+
+/// The longest to wait for the calibration data to be copied from NVM, a few times the start-up
+/// time in which the datasheet says it is done.
+const NVM_COPY_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// The shortest wait between two reads of the status register by `Bmp280::wait_until_ready()`.
+const MIN_POLL_INTERVAL: Duration = Duration::from_micros(500);
+
+/// A single reading of all values from the sensor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement {
+    pub temperature_celsius: f32,
+    pub pressure_kpa: f32,
+    /// Altitude relative to the ground pressure of the sensor, NaN if the sensor has none
+    pub altitude_m: f32,
+    /// The ADC values the other fields were computed from
+    pub raw: RawMeasurement,
+    /// Why the reading failed validation, if it is set up to flag rather than reject readings
+    pub implausible: Option<Implausibility>,
+    /// Whether the reading comes from a new conversion, rather than from reading the data
+    /// registers again before the sensor updated them
+    pub fresh: bool,
+    /// The label of the sensor, see `Bmp280Builder::label()`
+    pub label: Option<Arc<str>>,
+    /// Whether the altitude is relative to a reference pressure that expired by the
+    /// `ExpiryPolicy` of the sensor
+    pub stale_reference: bool,
+    /// Where the reading is outside the operating range of the sensor, as compensated before the
+    /// corrections of the sensor are applied
+    pub limits: Limits,
+    /// The number of the sample among those taken by a `Sampler`, `None` outside of one
+    pub sequence: Option<u64>,
+    /// How many measurements ago the temperature was read, 0 if it was read with this one, see
+    /// `Bmp280Builder::temperature_every()`
+    pub temperature_age: u32,
+}
+
+/// Counters of the bus traffic of a sensor, to keep an eye on the quality of the link.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Register reads and writes that succeeded, possibly after retries
+    pub transactions: u64,
+    /// Register reads and writes that failed, after all retries
+    pub failures: u64,
+    /// Attempts that were retried
+    pub retries: u64,
+    /// Attempts the sensor didn't acknowledge
+    pub naks: u64,
+    /// Register reads and writes that timed out
+    pub timeouts: u64,
+    /// Times the sensor was reset and set up again after a bus failure
+    pub recoveries: u64,
+}
+
+/// A single BMP280 sensor, connected through a Linux I2C device by default.
+pub struct Bmp280<D = LinuxI2CDevice> {
+    pub(crate) fine: i32,
+    /// When `fine` was read, and the die temperature it stands for
+    pub(crate) fine_read: Option<(Instant, f32)>,
+    /// How old `fine` may be to compute pressures with, instead of reading the temperature again
+    pub(crate) fine_max_age: Option<Duration>,
+    /// Read the temperature with every this many measurements, see
+    /// `Bmp280Builder::temperature_every()`
+    pub(crate) temperature_every: u32,
+    /// How many measurements ago the temperature in `last_raw` was read
+    pub(crate) temperature_age: u32,
+    pub(crate) sleep_reads: SleepReads,
+    /// Whether a forced conversion finished that no reading has read yet
+    pub(crate) unread_conversion: bool,
+    pub(crate) calibration: Calibration,
+    pub(crate) i2c_device: D,
+    /// The device node, if the sensor was built from one
+    pub(crate) path: Option<PathBuf>,
+    pub(crate) address: u16,
+    pub(crate) label: Option<Arc<str>>,
+    pub(crate) ground_pressure: f32,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) measurement_timeout: Duration,
+    pub(crate) validation: Option<Validation>,
+    pub(crate) consistency_tolerance: Option<u32>,
+    pub(crate) retry_division_by_zero: bool,
+    pub(crate) auto_zero: bool,
+    pub(crate) recovery_attempts: u32,
+    pub(crate) verify_writes: bool,
+    /// Whether registers are read with SMBus block reads, until the adapter turns out not to
+    /// support them
+    pub(crate) block_reads: bool,
+    /// The value written to the `ctrl_meas` register when the sensor is set up
+    pub(crate) control: u8,
+    /// The value written to the `config` register when the sensor is set up
+    pub(crate) config: u8,
+    pub(crate) precompute_compensation: bool,
+    /// The compensation precomputed at setup, if `precompute_compensation` is set
+    pub(crate) compensation: Option<Compensation>,
+    /// Where the calibration is read from instead of the sensor, once it has been saved there
+    pub(crate) calibration_file: Option<PathBuf>,
+    /// Added to every temperature reading, in degrees celsius
+    pub(crate) temperature_offset: f32,
+    pub(crate) self_heating: Option<SelfHeating>,
+    /// The warming of the die at the current settings, subtracted from every temperature reading
+    pub(crate) self_heating_celsius: f32,
+    /// Applied to every pressure reading
+    pub(crate) pressure_correction: PressureCorrection,
+    /// Added to every pressure reading before `pressure_correction`
+    pub(crate) thermal_correction: Option<ThermalCorrection>,
+    /// The outside air temperature in degrees celsius, for altitudes by the hypsometric formula
+    pub(crate) external_temperature: Option<f32>,
+    /// The model of the atmosphere for altitudes, instead of the barometric formula
+    pub(crate) atmosphere: Option<Atmosphere>,
+    /// Whether pressures below 0 Pa saturate, see `Bmp280Builder::high_altitude()`
+    pub(crate) high_altitude: bool,
+    pub(crate) reference_expiry: Option<ExpiryPolicy>,
+    /// When the ground pressure was last set
+    pub(crate) reference_set_at: Option<Instant>,
+    /// When taking a new reference pressure last failed
+    pub(crate) refresh_failed_at: Option<Instant>,
+    /// The ADC values of the previous measurement
+    pub(crate) last_raw: Option<RawMeasurement>,
+    pub(crate) stats: Stats,
+    pub(crate) bus_stats: BusStats,
+    pub(crate) observer: Option<Arc<dyn Observer>>,
+    /// Opens the device again during recovery, for sensors built from a path and address
+    pub(crate) reopen: Option<Box<dyn FnMut() -> Result<D> + Send>>,
+    /// Whether the sensor is yet to be set up, for sensors built by `Bmp280Builder::build_lazy()`
+    pub(crate) pending_setup: bool,
+}
+
+impl<D> Bmp280<D>
+where
+    D: I2CDevice,
+    Error: From<D::Error>,
+{
+    /// Write the register address, then read `buf.len()` bytes starting at that register.
+    fn read_bytes(&mut self, reg: &Register, buf: &mut [u8]) -> Result<()> {
+        let register = reg.into();
+
+        if self.block_reads && buf.len() <= SMBUS_BLOCK_MAX {
+            let mut read = 0;
+            let result = self.transfer(register, false, |device| {
+                let data = device.smbus_read_i2c_block_data(register, buf.len() as u8)?;
+                read = data.len().min(buf.len());
+                buf[..read].copy_from_slice(&data[..read]);
+                Ok(())
+            });
+
+            match result {
+                Ok(()) if read == buf.len() => return Ok(()),
+                // An adapter returning short blocks doesn't support them properly either.
+                Ok(()) => self.block_reads = false,
+                Err(e) if is_unsupported(&e) => self.block_reads = false,
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.transfer(register, false, |device| {
+            device.write(&[register])?;
+            device.read(buf)
+        })
+    }
+
+    /// Write `data`, which starts with the register address.
+    fn write_bytes(&mut self, data: &[u8]) -> Result<()> {
+        self.transfer(data[0], true, |device| device.write(data))
+    }
+
+    /// Run a transaction on `register`, retrying it according to the retry policy until the
+    /// timeout.
+    fn transfer<F>(&mut self, register: u8, write: bool, f: F) -> Result<()>
+    where
+        F: FnMut(&mut D) -> std::result::Result<(), D::Error>,
+    {
+        if self.pending_setup {
+            // Cleared first, since setting up transfers as well.
+            self.pending_setup = false;
+            if let Err(e) = self.begin() {
+                self.pending_setup = true;
+                return Err(e);
+            }
+        }
+
+        let started = Instant::now();
+        let (result, attempts) = self.transfer_with_retries(register, write, started, f);
+        self.bus_stats.transactions.record(started.elapsed());
+
+        if let Some(observer) = &self.observer {
+            observer.transaction(&Transaction {
+                address: self.address,
+                register,
+                write,
+                attempts,
+                duration: started.elapsed(),
+                error: result.as_ref().err(),
+            });
+        }
+
+        result
+    }
+
+    /// The result of the transaction, and how often it was tried.
+    fn transfer_with_retries<F>(
+        &mut self,
+        register: u8,
+        write: bool,
+        started: Instant,
+        mut f: F,
+    ) -> (Result<()>, u32)
+    where
+        F: FnMut(&mut D) -> std::result::Result<(), D::Error>,
+    {
+        let mut attempt = 1;
+
+        loop {
+            let error = match f(&mut self.i2c_device) {
+                Ok(()) => {
+                    self.stats.transactions += 1;
+                    return (Ok(()), attempt);
+                }
+                Err(e) => Error::from(e),
+            };
+
+            if matches!(
+                error.raw_os_error(),
+                Some(libc::ENXIO) | Some(libc::EREMOTEIO)
+            ) {
+                self.stats.naks += 1;
+            }
+
+            let delay = self.retry.delay(attempt);
+            let out_of_time = self
+                .timeout
+                .is_some_and(|timeout| started.elapsed() + delay >= timeout);
+
+            if attempt >= self.retry.attempts || !(self.retry.retryable)(&error) || out_of_time {
+                self.stats.failures += 1;
+
+                if out_of_time || error.raw_os_error() == Some(libc::ETIMEDOUT) {
+                    self.stats.timeouts += 1;
+                    let error = Error::Timeout {
+                        address: self.address,
+                        register,
+                        write,
+                        elapsed: started.elapsed(),
+                        source: Box::new(error),
+                    };
+                    return (Err(error), attempt);
+                }
+
+                let error = Error::Transfer {
+                    address: self.address,
+                    register,
+                    write,
+                    source: Box::new(error),
+                };
+                return (Err(error), attempt);
+            }
+
+            if let Some(observer) = &self.observer {
+                observer.retrying(self.address, register, write, attempt, &error);
+            }
+
+            thread::sleep(delay);
+            attempt += 1;
+            self.stats.retries += 1;
+        }
+    }
+
+    fn write(&mut self, field: Byte, value: u8) -> Result<()> {
+        self.write_bytes(&[field.register().into(), value])?;
+
+        if self.verify_writes {
+            self.verify_write(field, value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads back a register that was just written, failing if it doesn't hold the written value.
+    fn verify_write(&mut self, field: Byte, wrote: u8) -> Result<()> {
+        // The reset register always reads as zero, and a forced measurement may already be done,
+        // putting the sensor back into sleep mode.
+        let mask = match field.register() {
+            Register::SoftReset => return Ok(()),
+            Register::Control if Mode::from_bits(wrote) == Mode::Forced => !0b11,
+            _ => 0xFF,
+        };
+
+        let read = self.read(field)?;
+        if read & mask != wrote & mask {
+            return Err(Error::WriteVerificationFailed {
+                register: field.register().into(),
+                wrote,
+                read,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Set the pressure read now as the ground pressure, for altitudes relative to where the sensor
+    /// is. Returns the ground pressure.
+    pub fn zero(&mut self) -> Result<ReferencePressure> {
+        let pressure = self.pressure_kpa()? * 1000.;
+        self.set_ground_pressure_pa(pressure);
+
+        Ok(ReferencePressure::from_pa(pressure))
+    }
+
+    /// The label set by `Bmp280Builder::label()` or `.set_label()`.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn set_label(&mut self, label: impl AsRef<str>) {
+        self.label = Some(label.as_ref().into());
+    }
+
+    /// Correct all following readings by adding these offsets to the compensated values, e.g. for
+    /// self-heating of the board or a fixed pressure bias against a reference instrument.
+    /// Altitudes are computed from the corrected pressure. This replaces any pressure correction
+    /// set with `.set_pressure_correction()`.
+    pub fn set_offsets(&mut self, temperature_offset_celsius: f32, pressure_offset_pa: f32) {
+        self.temperature_offset = temperature_offset_celsius;
+        self.pressure_correction = PressureCorrection::offset(pressure_offset_pa);
+    }
+
+    /// The temperature offset in degrees celsius and pressure offset in Pa set by
+    /// `.set_offsets()`.
+    pub fn offsets(&self) -> (f32, f32) {
+        (self.temperature_offset, self.pressure_correction.offset_pa)
+    }
+
+    /// Correct all following pressure readings with a gain and an offset, e.g. from
+    /// `PressureCorrection::two_point()`.
+    pub fn set_pressure_correction(&mut self, correction: PressureCorrection) {
+        self.pressure_correction = correction;
+    }
+
+    pub fn pressure_correction(&self) -> PressureCorrection {
+        self.pressure_correction
+    }
+
+    /// Add a pressure offset depending on the die temperature to all following pressure
+    /// readings, before the correction of `.set_pressure_correction()`. The die temperature is
+    /// the one the sensor measures, without the offset of `.set_offsets()`.
+    pub fn set_thermal_correction(&mut self, correction: Option<ThermalCorrection>) {
+        self.thermal_correction = correction;
+    }
+
+    /// Subtract the warming of the die by its own conversions from all following temperature
+    /// readings, for the oversampling and standby settings the sensor runs with. This reads the
+    /// settings from the sensor.
+    ///
+    /// ```
+    /// use bmp280::correction::SelfHeating;
+    /// use bmp280::emulator::Emulator;
+    /// use bmp280::Bmp280Builder;
+    ///
+    /// let mut sensor = Bmp280Builder::new()
+    ///     .build_with(Emulator::new())
+    ///     .expect("Could not build device");
+    /// let heated = sensor.temperature_celsius().unwrap();
+    ///
+    /// sensor
+    ///     .set_self_heating(Some(SelfHeating {
+    ///         continuous_celsius: 1.2,
+    ///     }))
+    ///     .unwrap();
+    /// let ambient = sensor.temperature_celsius().unwrap();
+    /// assert!(heated - ambient > 1.1);
+    /// ```
+    pub fn set_self_heating(&mut self, self_heating: Option<SelfHeating>) -> Result<()> {
+        self.self_heating = self_heating;
+        self.update_self_heating()
+    }
+
+    /// The warming of the die in degrees celsius subtracted from the temperature readings.
+    pub fn self_heating_celsius(&self) -> f32 {
+        self.self_heating_celsius
+    }
+
+    /// Work out the warming of the die for the settings of the sensor.
+    fn update_self_heating(&mut self) -> Result<()> {
+        let self_heating = match self.self_heating {
+            Some(self_heating) => self_heating,
+            None => {
+                self.self_heating_celsius = 0.;
+                return Ok(());
+            }
+        };
+
+        let control = self.read(field::CONTROL)?;
+        let config = self.read(field::CONFIG)?;
+        self.self_heating_celsius = self_heating.delta_celsius(
+            Oversampling::from_bits(control >> 5),
+            Oversampling::from_bits(control >> 2),
+            Standby::from_bits(config >> 5),
+        );
+
+        Ok(())
+    }
+
+    /// Compute all following altitudes with the hypsometric formula for the given outside air
+    /// temperature in degrees celsius, instead of the standard atmosphere. The die temperature is
+    /// no good for this, as the sensor warms itself and the board. `None` goes back to the
+    /// standard atmosphere.
+    ///
+    /// ```
+    /// use bmp280::emulator::Emulator;
+    /// use bmp280::{Bmp280Builder, ReferencePressure};
+    ///
+    /// let mut sensor = Bmp280Builder::new()
+    ///     .ground_pressure(ReferencePressure::STANDARD_SEA_LEVEL)
+    ///     .build_with(Emulator::new())
+    ///     .expect("Could not build device");
+    /// let standard = sensor.altitude_m().unwrap();
+    ///
+    /// // Warm air is less dense, so the same pressure difference spans more height.
+    /// sensor.set_external_temperature(Some(35.));
+    /// assert!(sensor.altitude_m().unwrap() > standard);
+    /// ```
+    pub fn set_external_temperature(&mut self, temperature_celsius: Option<f32>) {
+        self.external_temperature = temperature_celsius;
+    }
+
+    pub fn external_temperature(&self) -> Option<f32> {
+        self.external_temperature
+    }
+
+    /// Calculate altitudes in the given atmosphere instead of by the barometric formula of the
+    /// datasheet, e.g. `Atmosphere::US_STANDARD_1976` for a balloon going above 11 km. An outside
+    /// air temperature set with `.set_external_temperature()` takes precedence. `None` goes back to
+    /// the barometric formula.
+    ///
+    /// ```
+    /// use bmp280::atmosphere::Atmosphere;
+    /// use bmp280::emulator::Emulator;
+    /// use bmp280::{Bmp280Builder, ReferencePressure};
+    ///
+    /// let mut sensor = Bmp280Builder::new()
+    ///     .ground_pressure(ReferencePressure::STANDARD_SEA_LEVEL)
+    ///     .build_with(Emulator::new())
+    ///     .expect("Could not build device");
+    /// let standard = sensor.altitude_m().unwrap();
+    ///
+    /// // A cold day, -10 degrees celsius on the ground: the same pressure difference spans less height.
+    /// sensor.set_atmosphere(Some(Atmosphere::troposphere(263.15, 0.0065)));
+    /// assert!(sensor.altitude_m().unwrap() < standard);
+    /// ```
+    pub fn set_atmosphere(&mut self, atmosphere: Option<Atmosphere>) {
+        self.atmosphere = atmosphere;
+    }
+
+    pub fn atmosphere(&self) -> Option<&Atmosphere> {
+        self.atmosphere.as_ref()
+    }
+
+    /// The altitude in meters of a pressure relative to a reference pressure, both in Pa.
+    fn altitude(&self, pressure_pa: f32, reference_pa: f32) -> f32 {
+        match (self.external_temperature, &self.atmosphere) {
+            (Some(temperature), _) => hypsometric_altitude(pressure_pa, reference_pa, temperature),
+            (None, Some(atmosphere)) => atmosphere.altitude_m(pressure_pa, reference_pa),
+            (None, None) => altitude_from_pressure(pressure_pa, reference_pa),
+        }
+    }
+
+    /// The pressure in Pa with all corrections applied, given the die temperature.
+    fn corrected_pressure_pa(&self, pressure_pa: f32, temperature_celsius: f32) -> f32 {
+        let pressure_pa = match &self.thermal_correction {
+            Some(correction) => pressure_pa + correction.offset_pa(temperature_celsius),
+            None => pressure_pa,
+        };
+
+        if self.pressure_correction.is_identity() {
+            return pressure_pa;
+        }
+        self.pressure_correction.apply(pressure_pa)
+    }
+
+    /// Set the pressure that altitudes are relative to, e.g. `ReferencePressure::STANDARD_SEA_LEVEL`
+    /// to read altitudes above sea level.
+    pub fn set_ground_pressure(&mut self, pressure: ReferencePressure) {
+        self.set_ground_pressure_pa(pressure.pa());
+    }
+
+    /// The pressure that altitudes are relative to, `None` until it is set.
+    pub fn ground_pressure(&self) -> Option<ReferencePressure> {
+        Some(self.ground_pressure)
+            .filter(|&pa| is_reference_pressure(pa))
+            .map(ReferencePressure::from_pa)
+    }
+
+    fn set_ground_pressure_pa(&mut self, pressure_pa: f32) {
+        self.ground_pressure = pressure_pa;
+        self.reference_set_at = Some(Instant::now());
+        self.refresh_failed_at = None;
+    }
+
+    /// How long ago the ground pressure was set, if it was.
+    pub fn reference_age(&self) -> Option<Duration> {
+        if !is_reference_pressure(self.ground_pressure) {
+            return None;
+        }
+        self.reference_set_at.map(|at| at.elapsed())
+    }
+
+    /// Whether the ground pressure expired by the policy of `Bmp280Builder::reference_expiry()`.
+    pub fn reference_stale(&self) -> bool {
+        match (&self.reference_expiry, self.reference_age()) {
+            (Some(policy), Some(age)) => age > policy.max_age,
+            _ => false,
+        }
+    }
+
+    /// Take a new ground pressure if it expired and the policy says how. A failure leaves the
+    /// old one in place, to be flagged as stale.
+    fn refresh_reference(&mut self) {
+        if !self.reference_stale() {
+            return;
+        }
+        let policy = match &self.reference_expiry {
+            Some(policy) => policy,
+            None => return,
+        };
+        let refresh = match &policy.refresh {
+            Some(refresh) => refresh.clone(),
+            None => return,
+        };
+        if let Some(failed_at) = self.refresh_failed_at {
+            if failed_at.elapsed() < policy.retry_interval {
+                return;
+            }
+        }
+
+        let age = self.reference_age().unwrap_or_default();
+        let result = match refresh {
+            Refresh::Average(count) => self.average_pressure_pa(count),
+            Refresh::Source(source) => source().and_then(|pressure| {
+                if is_reference_pressure(pressure) {
+                    Ok(pressure)
+                } else {
+                    Err(Error::NoReferencePressure)
+                }
+            }),
+        };
+
+        if let Some(observer) = &self.observer {
+            observer.reference_expired(self.address, age, result.as_ref().copied());
+        }
+        match result {
+            Ok(pressure) => self.set_ground_pressure_pa(pressure),
+            Err(_) => self.refresh_failed_at = Some(Instant::now()),
+        }
+    }
+
+    /// The average pressure in Pa of `count` measurements, at least one.
+    fn average_pressure_pa(&mut self, count: u32) -> Result<f32> {
+        let count = count.max(1);
+        let mut sum = 0.;
+        for _ in 0..count {
+            sum += self.measure_with_recovery()?.pressure_kpa as f64 * 1000.;
+        }
+
+        Ok((sum / count as f64) as f32)
+    }
+
+    /// Reads a register, decoded as its field says. The bytes are read into a fixed buffer on the
+    /// stack, so reading a sample doesn't allocate.
+    pub(crate) fn read<T, const N: usize>(&mut self, field: Field<T, N>) -> Result<T> {
+        let mut buf = [0u8; N];
+
+        self.read_bytes(&field.register(), &mut buf)?;
+
+        Ok(field.decode(buf))
+    }
+
+    fn read_coefficients(&mut self) -> Result<()> {
+        self.calibration.dig_t1 = self.read(field::DIG_T1)?;
+        self.calibration.dig_t2 = self.read(field::DIG_T2)?;
+        self.calibration.dig_t3 = self.read(field::DIG_T3)?;
+
+        self.calibration.dig_p1 = self.read(field::DIG_P1)?;
+        self.calibration.dig_p2 = self.read(field::DIG_P2)?;
+        self.calibration.dig_p3 = self.read(field::DIG_P3)?;
+        self.calibration.dig_p4 = self.read(field::DIG_P4)?;
+        self.calibration.dig_p5 = self.read(field::DIG_P5)?;
+        self.calibration.dig_p6 = self.read(field::DIG_P6)?;
+        self.calibration.dig_p7 = self.read(field::DIG_P7)?;
+        self.calibration.dig_p8 = self.read(field::DIG_P8)?;
+        self.calibration.dig_p9 = self.read(field::DIG_P9)?;
+
+        Ok(())
+    }
+
+    pub(crate) fn begin(&mut self) -> Result<()> {
+        self.check_settings()?;
+        self.ping()?;
+
+        let cached = match &self.calibration_file {
+            Some(path) if path.exists() => Some(Calibration::load(path)?),
+            _ => None,
+        };
+        match cached {
+            Some(calibration) => self.calibration = calibration,
+            None => {
+                // The calibration data reads as zeros until it has been copied from NVM after a
+                // reset.
+                self.wait_for_nvm_copy()?;
+                self.read_coefficients()?;
+            }
+        }
+
+        if let Some((register, value)) = self.calibration.invalid_word() {
+            return Err(Error::InvalidCalibration { register, value });
+        }
+
+        if let Some(path) = &self.calibration_file {
+            if !path.exists() {
+                self.calibration.save(path)?;
+            }
+        }
+        if self.precompute_compensation {
+            self.compensation = Some(self.calibration.compensation());
+        }
+
+        // Writes to `config` may be ignored in normal mode, so it goes first, while the sensor is
+        // still asleep after a reset or the previous setup.
+        self.write(field::CONTROL, self.control & !0b11)?;
+        self.write(field::CONFIG, self.config)?;
+        self.write(field::CONTROL, self.control)?;
+        self.update_self_heating()?;
+
+        if let Some(observer) = &self.observer {
+            observer.initialized(self.address, &self.calibration);
+        }
+
+        Ok(())
+    }
+
+    /// The settings the sensor was set up with, or last changed to by `.apply()`.
+    pub fn settings(&self) -> Settings {
+        Settings::from_bits(self.control, self.config)
+    }
+
+    /// Change several settings of the sensor at once, with as few register writes as possible:
+    /// each register is written only if it changes, and a change of the `config` register puts the
+    /// sensor to sleep once for all of them, rather than once per setting. Settings that can't
+    /// work together fail with `Error::IncompatibleSettings` before anything is written.
+    ///
+    /// ```
+    /// use bmp280::config::{Filter, Oversampling};
+    /// use bmp280::emulator::Emulator;
+    /// use bmp280::Bmp280Builder;
+    ///
+    /// let mut sensor = Bmp280Builder::new()
+    ///     .build_with(Emulator::new())
+    ///     .expect("Could not build device");
+    ///
+    /// let before = sensor.stats().transactions;
+    /// sensor
+    ///     .apply(|settings| {
+    ///         settings.oversampling(Oversampling::X2, Oversampling::X16);
+    ///         settings.filter(Filter::X4);
+    ///     })
+    ///     .unwrap();
+    /// // To sleep, the filter, and back to normal mode with the new oversampling.
+    /// assert_eq!(sensor.stats().transactions - before, 3);
+    /// assert_eq!(sensor.settings().filter, Filter::X4);
+    ///
+    /// let before = sensor.stats().transactions;
+    /// sensor.apply(|settings| { settings.filter(Filter::X4); }).unwrap();
+    /// assert_eq!(sensor.stats().transactions - before, 0);
+    /// ```
+    pub fn apply(&mut self, change: impl FnOnce(&mut Settings)) -> Result<()> {
+        let mut settings = self.settings();
+        change(&mut settings);
+
+        let previous = (self.control, self.config);
+        self.control = settings.control_bits();
+        self.config = settings.config_bits();
+
+        let result = self
+            .check_settings()
+            .and_then(|()| self.write_changed_settings(previous));
+        if result.is_err() {
+            (self.control, self.config) = previous;
+        }
+        result?;
+
+        self.update_self_heating()
+    }
+
+    /// Write `self.control` and `self.config` to the sensor, where they changed from the
+    /// `previous` values of the registers.
+    fn write_changed_settings(&mut self, previous: (u8, u8)) -> Result<()> {
+        let (mut control, config) = previous;
+
+        if self.config != config {
+            // Writes to `config` may be ignored in normal mode, as in `.begin()`.
+            if Mode::from_bits(control) == Mode::Normal {
+                control &= !0b11;
+                self.write(field::CONTROL, control)?;
+            }
+            self.write(field::CONFIG, self.config)?;
+        }
+        if self.control != control {
+            self.write(field::CONTROL, self.control)?;
+        }
+
+        Ok(())
+    }
+
+    /// Fails with `Error::IncompatibleSettings` for settings that can't work together, before the
+    /// sensor is set up with them.
+    ///
+    /// ```
+    /// use bmp280::config::{Mode, Oversampling, Standby};
+    /// use bmp280::emulator::Emulator;
+    /// use bmp280::{Bmp280Builder, Error, ReferencePressure};
+    ///
+    /// // The standby time is the pause between conversions in normal mode only.
+    /// let result = Bmp280Builder::new()
+    ///     .mode(Mode::Sleep)
+    ///     .standby(Standby::Ms1000)
+    ///     .build_with(Emulator::new());
+    /// assert!(matches!(result, Err(Error::IncompatibleSettings { .. })));
+    ///
+    /// // Altitudes need pressures.
+    /// let result = Bmp280Builder::new()
+    ///     .oversampling(Oversampling::X1, Oversampling::Skipped)
+    ///     .ground_pressure(ReferencePressure::STANDARD_SEA_LEVEL)
+    ///     .build_with(Emulator::new());
+    /// assert!(matches!(result, Err(Error::IncompatibleSettings { .. })));
+    ///
+    /// // Temperatures alone are fine.
+    /// let result = Bmp280Builder::new()
+    ///     .oversampling(Oversampling::X1, Oversampling::Skipped)
+    ///     .build_with(Emulator::new());
+    /// assert!(result.is_ok());
+    /// ```
+    fn check_settings(&self) -> Result<()> {
+        let incompatible = |reason| Err(Error::IncompatibleSettings { reason });
+        let temperature = Oversampling::from_bits(self.control >> 5);
+        let pressure = Oversampling::from_bits(self.control >> 2);
+        let mode = Mode::from_bits(self.control);
+
+        if mode != Mode::Normal && Standby::from_bits(self.config >> 5) != Standby::Ms0_5 {
+            return incompatible("a standby time only applies in normal mode");
+        }
+        if temperature == Oversampling::Skipped && pressure != Oversampling::Skipped {
+            return incompatible("the pressure can't be compensated without the temperature");
+        }
+        if pressure == Oversampling::Skipped {
+            if is_reference_pressure(self.ground_pressure) || self.auto_zero {
+                return incompatible("altitudes need the pressure, which is skipped");
+            }
+            if self.reference_expiry.is_some() {
+                return incompatible("a reference expiry needs the pressure, which is skipped");
+            }
+        }
+        if self.self_heating.is_some() && mode != Mode::Normal {
+            return incompatible("the self-heating correction is worked out for normal mode");
+        }
+
+        Ok(())
+    }
+
+    /// Gets the sensor going again after a bus failure: reopens the device if possible, resets the
+    /// sensor and sets it up again as in `Bmp280Builder::build()`. The ground pressure is kept.
+    pub fn recover(&mut self) -> Result<()> {
+        self.stats.recoveries += 1;
+
+        if let Some(reopen) = &mut self.reopen {
+            self.i2c_device = reopen()?;
+        }
+
+        self.write(field::SOFT_RESET, SOFT_RESET)?;
+        thread::sleep(START_UP_TIME);
+        self.fine_read = None;
+
+        self.begin()
+    }
+
+    /// Waits until the status register says the calibration data is not being copied.
+    fn wait_for_nvm_copy(&mut self) -> Result<()> {
+        let started = Instant::now();
+
+        while self.status()?.im_update {
+            if started.elapsed() > NVM_COPY_TIMEOUT {
+                return Err(Error::NvmCopyTimeout {
+                    register: (&Register::Status).into(),
+                    waited: started.elapsed(),
+                });
+            }
+            thread::sleep(Duration::from_micros(500));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the sensor is still on the bus by reading its chip ID, without changing any
+    /// state of the sensor or of this struct.
+    pub fn ping(&mut self) -> Result<()> {
+        let chip_id = self.read(field::CHIP_ID)?;
+        if chip_id != CHIP_ID {
+            return Err(Error::ChipIdMismatch { found: chip_id });
+        }
+
+        Ok(())
+    }
+
+    /// Reads the altitude from the sensor relative to the given sea level pressure.
+    pub fn altitude_m_relative(&mut self, sea_level_pa: f32) -> Result<f32> {
+        if !is_reference_pressure(sea_level_pa) {
+            return Err(Error::NoReferencePressure);
+        }
+
+        let pressure = self.pressure_kpa()? * 1000.;
+
+        Ok(self.altitude(pressure, sea_level_pa))
+    }
+
+    /// Reads the altitude from the sensor relative to the zeroed altitude set by `.zero()`,
+    /// `.set_ground_pressure()` or `Bmp280Builder::ground_pressure()`. Returns
+    /// `Error::NoReferencePressure` if none of those were used, unless the sensor was built with
+    /// `Bmp280Builder::auto_zero()`.
+    pub fn altitude_m(&mut self) -> Result<f32> {
+        if self.auto_zero && !is_reference_pressure(self.ground_pressure) {
+            self.zero()?;
+            return Ok(0.);
+        }
+
+        let pressure = self.ground_pressure;
+
+        self.altitude_m_relative(pressure)
+    }
+
+    /// Reads the status register.
+    pub fn status(&mut self) -> Result<Status> {
+        Ok(Status::from_bits(self.read(field::STATUS)?))
+    }
+
+    /// How long to wait between two reads of the status register for a conversion to finish: an
+    /// eighth of the typical conversion time for the oversampling the sensor was set up with, so
+    /// slow conversions aren't polled more often than fast ones.
+    pub fn poll_interval(&self) -> Duration {
+        let millis = measurement_millis(
+            Oversampling::from_bits(self.control >> 5),
+            Oversampling::from_bits(self.control >> 2),
+        );
+        Duration::from_secs_f32(millis / 8. / 1000.).max(MIN_POLL_INTERVAL)
+    }
+
+    /// Checks once whether the data registers hold the result of the latest conversion, for
+    /// async code waiting with a timer of its own: `None` if they do, or how long to wait before
+    /// checking again.
+    pub fn poll_ready(&mut self) -> Result<Option<Duration>> {
+        if self.status()?.measuring {
+            return Ok(Some(self.poll_interval()));
+        }
+        Ok(None)
+    }
+
+    /// Waits until no conversion is running, so the data registers hold the result of the latest
+    /// one, e.g. after starting a forced conversion. Returns `Error::MeasurementTimeout` if a
+    /// conversion is still running after `timeout`.
+    ///
+    /// ```
+    /// use bmp280::emulator::Emulator;
+    /// use bmp280::Bmp280Builder;
+    /// use i2cdev::core::I2CDevice;
+    /// use std::time::Duration;
+    ///
+    /// let emulator = Emulator::new();
+    /// emulator.set_conversion_reads(3);
+    /// let mut sensor = Bmp280Builder::new()
+    ///     .build_with(emulator)
+    ///     .expect("Could not build device");
+    ///
+    /// // Start a forced conversion.
+    /// let control = sensor.inner_mut().smbus_read_byte_data(0xF4).unwrap();
+    /// sensor
+    ///     .inner_mut()
+    ///     .smbus_write_byte_data(0xF4, (control & !0b11) | 0b01)
+    ///     .unwrap();
+    /// assert!(sensor.poll_ready().unwrap().is_some());
+    ///
+    /// sensor.wait_until_ready(Duration::from_millis(100)).unwrap();
+    /// assert_eq!(sensor.poll_ready().unwrap(), None);
+    /// ```
+    pub fn wait_until_ready(&mut self, timeout: Duration) -> Result<()> {
+        let started = Instant::now();
+
+        while let Some(interval) = self.poll_ready()? {
+            let waited = started.elapsed();
+            if waited >= timeout {
+                return Err(Error::MeasurementTimeout {
+                    register: (&Register::Status).into(),
+                    waited,
+                });
+            }
+            thread::sleep(interval.min(timeout - waited));
+        }
+
+        Ok(())
+    }
+
+    /// Takes a single measurement in forced mode, waiting for the conversion to complete. The
+    /// oversampling settings are kept, and the previous mode is restored afterwards.
+    pub fn forced_measurement(&mut self) -> Result<Measurement> {
+        let control = self.convert_forced()?;
+        self.finish_forced_measurement(control)
+    }
+
+    /// Set what reads do while the sensor is asleep, see `Bmp280Builder::sleep_reads()`.
+    pub fn set_sleep_reads(&mut self, sleep_reads: SleepReads) {
+        self.sleep_reads = sleep_reads;
+    }
+
+    /// Whether the sensor was set up in sleep or forced mode, rather than converting continuously.
+    fn asleep(&self) -> bool {
+        Mode::from_bits(self.control) != Mode::Normal
+    }
+
+    /// Takes a forced conversion if the sensor is asleep and `SleepReads::Force` is set, unless
+    /// one finished that wasn't read yet. Returns whether the data registers hold a conversion
+    /// no reading has read yet, which the reading about to be made then uses up.
+    fn convert_if_asleep(&mut self) -> Result<bool> {
+        if self.asleep() && self.sleep_reads == SleepReads::Force && !self.unread_conversion {
+            self.convert_forced()?;
+        }
+        Ok(std::mem::take(&mut self.unread_conversion))
+    }
+
+    /// Starts a conversion in forced mode and waits for it to finish, polling at the
+    /// `poll_interval()`, returning the control register to restore afterwards.
+    fn convert_forced(&mut self) -> Result<u8> {
+        let control = self.start_forced_conversion()?;
+        let interval = self.poll_interval();
+
+        let started = Instant::now();
+        loop {
+            thread::sleep(interval);
+
+            if self.conversion_finished(started)? {
+                return Ok(control);
+            }
+        }
+    }
+
+    /// Starts a conversion in forced mode, returning the control register to restore afterwards.
+    pub(crate) fn start_forced_conversion(&mut self) -> Result<u8> {
+        let control = self.read(field::CONTROL)?;
+        self.write(field::CONTROL, (control & !0b11) | Mode::Forced.bits())?;
+
+        Ok(control)
+    }
+
+    /// Whether the conversion started at `started` has finished, or an error once it has taken
+    /// longer than the measurement timeout.
+    pub(crate) fn conversion_finished(&mut self, started: Instant) -> Result<bool> {
+        if !self.status()?.measuring {
+            self.bus_stats.conversions.record(started.elapsed());
+            self.unread_conversion = true;
+            return Ok(true);
+        }
+        if started.elapsed() > self.measurement_timeout {
+            return Err(Error::MeasurementTimeout {
+                register: (&Register::Status).into(),
+                waited: started.elapsed(),
+            });
+        }
+
+        Ok(false)
+    }
+
+    /// Reads the result of a finished forced conversion and restores the control register.
+    pub(crate) fn finish_forced_measurement(&mut self, control: u8) -> Result<Measurement> {
+        let mut measurement = self.measure()?;
+        measurement.fresh = true;
+
+        // The sensor is back in sleep mode after a forced measurement.
+        if Mode::from_bits(control) == Mode::Normal {
+            self.write(field::CONTROL, control)?;
+        }
+
+        Ok(measurement)
+    }
+
+    /// Reads temperature, pressure, and altitude in one go. This avoids reading the temperature
+    /// twice, which happens when calling `.temperature_celsius()` and `.pressure_kpa()` separately.
+    pub fn measure(&mut self) -> Result<Measurement> {
+        self.refresh_reference();
+
+        let started = Instant::now();
+        let result = self.measure_with_recovery();
+
+        if let Some(observer) = &self.observer {
+            observer.measurement(self.address, started.elapsed(), result.as_ref());
+        }
+
+        result
+    }
+
+    fn measure_with_recovery(&mut self) -> Result<Measurement> {
+        let mut result = self.measure_once();
+
+        for _ in 0..self.recovery_attempts {
+            match &result {
+                Err(e) if is_bus_failure(e) => {
+                    if let Some(observer) = &self.observer {
+                        observer.recovering(self.address, e);
+                    }
+                }
+                _ => break,
+            }
+            result = self.recover().and_then(|()| self.measure_once());
+        }
+
+        result
+    }
+
+    fn measure_once(&mut self) -> Result<Measurement> {
+        let converted = self.convert_if_asleep()?;
+        let raw = self.measure_raw_at_rate()?;
+        let mut measurement = match self.compensation().compensate(raw, self.ground_pressure) {
+            Err(Error::PressureDivisionByZero { .. }) if self.retry_division_by_zero => {
+                let raw = self.measure_raw()?;
+                self.temperature_age = 0;
+                self.compensation().compensate(raw, self.ground_pressure)?
+            }
+            result => result?,
+        };
+        measurement.temperature_age = self.temperature_age;
+        self.correct(&mut measurement);
+        if self.high_altitude && measurement.limits.saturated {
+            measurement.pressure_kpa = 0.;
+            measurement.altitude_m = f32::NAN;
+        }
+        measurement.label = self.label.clone();
+
+        // The 20 bit values practically never repeat exactly between conversions, so unchanged
+        // values mean the sensor hasn't finished a new conversion yet. Asleep, there is no new one
+        // unless it was forced.
+        measurement.fresh = converted || (!self.asleep() && self.last_raw != Some(measurement.raw));
+        self.last_raw = Some(measurement.raw);
+
+        if let Some(validation) = &mut self.validation {
+            if let Some(reason) = validation.check(&measurement, Instant::now()) {
+                match validation.action {
+                    validate::Action::Reject => return Err(Error::ImplausibleReading { reason }),
+                    validate::Action::Flag => measurement.implausible = Some(reason),
+                }
+            }
+        }
+
+        if self.auto_zero && !is_reference_pressure(self.ground_pressure) {
+            self.set_ground_pressure_pa(measurement.pressure_kpa * 1000.);
+            measurement.altitude_m = 0.;
+        }
+        measurement.stale_reference = self.reference_stale();
+
+        Ok(measurement)
+    }
+
+    /// Apply the offsets to a compensated measurement.
+    fn correct(&self, measurement: &mut Measurement) {
+        if self.temperature_offset == 0.
+            && self.self_heating_celsius == 0.
+            && self.pressure_correction.is_identity()
+            && self.thermal_correction.is_none()
+            && self.external_temperature.is_none()
+            && self.atmosphere.is_none()
+        {
+            return;
+        }
+
+        let pressure_pa = measurement.pressure_kpa * 1000.;
+        measurement.pressure_kpa =
+            self.corrected_pressure_pa(pressure_pa, measurement.temperature_celsius) / 1000.;
+        measurement.temperature_celsius += self.temperature_offset - self.self_heating_celsius;
+        if is_reference_pressure(self.ground_pressure) {
+            measurement.altitude_m =
+                self.altitude(measurement.pressure_kpa * 1000., self.ground_pressure);
+        }
+    }
+
+    /// Reads the ADC values for `measure()`, only the pressure if the temperature of the last
+    /// measurement may be used again by `Bmp280Builder::temperature_every()`.
+    fn measure_raw_at_rate(&mut self) -> Result<RawMeasurement> {
+        match self.last_raw {
+            Some(last) if self.temperature_age + 1 < self.temperature_every => {
+                let adc_p = self.read(field::PRESSURE_DATA)?;
+                self.temperature_age += 1;
+                Ok(RawMeasurement {
+                    adc_t: last.adc_t,
+                    adc_p,
+                })
+            }
+            _ => {
+                let raw = self.measure_raw()?;
+                self.temperature_age = 0;
+                Ok(raw)
+            }
+        }
+    }
+
+    /// Reads the uncompensated ADC values.
+    pub fn measure_raw(&mut self) -> Result<RawMeasurement> {
+        let tolerance = match self.consistency_tolerance {
+            Some(tolerance) => tolerance,
+            None => {
+                return Ok(RawMeasurement {
+                    adc_t: self.read(field::TEMPERATURE_DATA)?,
+                    adc_p: self.read(field::PRESSURE_DATA)?,
+                })
+            }
+        };
+
+        let mut previous = self.read_data_burst()?;
+        let mut reads = 1;
+        loop {
+            let current = self.read_data_burst()?;
+            reads += 1;
+
+            if previous.adc_t.abs_diff(current.adc_t) <= tolerance
+                && previous.adc_p.abs_diff(current.adc_p) <= tolerance
+            {
+                return Ok(current);
+            }
+            if reads >= CONSISTENCY_READS {
+                return Err(Error::InconsistentReading { previous, current });
+            }
+            previous = current;
+        }
+    }
+
+    /// Reads temperature, pressure and altitude with a single burst read of the data registers,
+    /// one write of the register address and one read of 6 bytes, for control loops that need
+    /// readings with the least latency. The calibration read at setup is all it needs, and the
+    /// offsets and corrections of the sensor are applied as in `.measure()`. Unlike `.measure()`,
+    /// there is no consistency check, validation, reference expiry or recovery, observers only see
+    /// the transaction, and a sensor that is asleep isn't forced to convert, whatever its
+    /// `SleepReads`: the reading is flagged as not fresh instead.
+    ///
+    /// The time the read takes is that of the bus: 9 bits for each of the 2 address bytes, the
+    /// register address and the 6 data bytes is 81 bit times, about 0.2 ms at 400 kHz. A Linux
+    /// system call adds some tens of microseconds, one for both with SMBus block reads or one each
+    /// for the write and the read without, see `Bmp280Builder::block_reads()`. The Raspberry
+    /// Pi runs its I2C bus at 100 kHz, which makes the read about 1 ms, unless it is configured
+    /// with `dtparam=i2c_arm_baudrate=400000`. The `fast_read` example measures the latency
+    /// achieved on a board.
+    ///
+    /// ```
+    /// use bmp280::emulator::Emulator;
+    /// use bmp280::Bmp280Builder;
+    ///
+    /// let mut sensor = Bmp280Builder::new()
+    ///     .build_with(Emulator::new())
+    ///     .expect("Could not build device");
+    ///
+    /// let before = sensor.stats().transactions;
+    /// let measurement = sensor.read_all_fast().unwrap();
+    /// assert_eq!(sensor.stats().transactions - before, 1);
+    /// assert_eq!(measurement.temperature_celsius, 25.08);
+    /// ```
+    pub fn read_all_fast(&mut self) -> Result<Measurement> {
+        let raw = self.read_data_burst()?;
+        let mut measurement = self.compensation().compensate(raw, self.ground_pressure)?;
+        self.correct(&mut measurement);
+        measurement.label = self.label.clone();
+
+        let converted = std::mem::take(&mut self.unread_conversion);
+        measurement.fresh = converted || (!self.asleep() && self.last_raw != Some(raw));
+        self.last_raw = Some(raw);
+        self.temperature_age = 0;
+
+        Ok(measurement)
+    }
+
+    /// Reads pressure and temperature data registers in a single transaction, so they come from
+    /// the same conversion.
+    fn read_data_burst(&mut self) -> Result<RawMeasurement> {
+        self.read(field::DATA)
+    }
+
+    /// The bus traffic counters since the sensor was built or the counters were reset.
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// The device the sensor talks through.
+    pub fn inner(&self) -> &D {
+        &self.i2c_device
+    }
+
+    /// The device the sensor talks through, e.g. to talk to another device at the same address
+    /// through it. Transactions made on it directly bypass the retries, statistics and observer of
+    /// the sensor, and changing the settings of the sensor through it confuses the driver.
+    pub fn inner_mut(&mut self) -> &mut D {
+        &mut self.i2c_device
+    }
+
+    /// Give up the sensor and get back the device it talks through, to hand it to another driver.
+    /// The sensor is left in the mode it is in.
+    ///
+    /// ```
+    /// use bmp280::emulator::Emulator;
+    /// use bmp280::Bmp280Builder;
+    ///
+    /// let sensor = Bmp280Builder::new()
+    ///     .build_with(Emulator::new())
+    ///     .expect("Could not build device");
+    ///
+    /// let emulator: Emulator = sensor.into_inner();
+    /// assert_eq!(emulator.register(0xD0), bmp280::CHIP_ID);
+    /// ```
+    pub fn into_inner(self) -> D {
+        self.i2c_device
+    }
+
+    /// The latencies of register reads and writes and of forced measurements since the sensor was
+    /// built or the counters were reset.
+    pub fn bus_stats(&self) -> &BusStats {
+        &self.bus_stats
+    }
+
+    /// Start counting from zero again, for `.stats()` and `.bus_stats()`.
+    pub fn reset_stats(&mut self) {
+        self.stats = Stats::default();
+        self.bus_stats = BusStats::default();
+    }
+
+    /// Whether registers are read with SMBus block reads, see `Bmp280Builder::block_reads()`.
+    pub fn block_reads(&self) -> bool {
+        self.block_reads
+    }
+
+    /// The calibration data read from the sensor.
+    pub fn calibration(&self) -> &Calibration {
+        &self.calibration
+    }
+
+    /// The compensation formulas for the calibration, precomputed if the sensor was built with
+    /// `Bmp280Builder::precompute_compensation()`.
+    fn compensation(&self) -> Compensation {
+        self.compensation
+            .unwrap_or_else(|| self.calibration.compensation())
+    }
+
+    /// Reads the temperature. While the sensor is asleep, this is the temperature of the last
+    /// conversion, unless the sensor was built with `SleepReads::Force`.
+    pub fn temperature_celsius(&mut self) -> Result<f32> {
+        self.convert_if_asleep()?;
+        Ok(self.die_temperature_celsius()? + self.temperature_offset - self.self_heating_celsius)
+    }
+
+    /// Reads the temperature without the offset, updating `self.fine`.
+    fn die_temperature_celsius(&mut self) -> Result<f32> {
+        let adc_t = self.read(field::TEMPERATURE_DATA)?;
+        let (temperature, fine) = self.compensation().checked_compensate_temperature(adc_t)?;
+
+        self.fine = fine;
+        self.fine_read = Some((Instant::now(), temperature));
+        Ok(temperature)
+    }
+
+    /// The die temperature `self.fine` stands for, if it is recent enough to be used again by
+    /// `Bmp280Builder::reuse_temperature()`.
+    fn recent_die_temperature(&self) -> Option<f32> {
+        let max_age = self.fine_max_age?;
+        let (read_at, temperature) = self.fine_read?;
+
+        if read_at.elapsed() > max_age {
+            return None;
+        }
+        Some(temperature)
+    }
+
+    /// Reads the pressure. While the sensor is asleep, this is the pressure of the last conversion,
+    /// unless the sensor was built with `SleepReads::Force`.
+    pub fn pressure_kpa(&mut self) -> Result<f32> {
+        self.convert_if_asleep()?;
+        // This is done to initialize the self.fine value.
+        let mut temperature = match self.recent_die_temperature() {
+            Some(temperature) => temperature,
+            None => self.die_temperature_celsius()?,
+        };
+
+        let pressure = match self.read_pressure_kpa() {
+            Err(Error::PressureDivisionByZero { .. }) if self.retry_division_by_zero => {
+                temperature = self.die_temperature_celsius()?;
+                self.read_pressure_kpa()
+            }
+            result => result,
+        }?;
+
+        Ok(self.corrected_pressure_pa(pressure * 1000., temperature) / 1000.)
+    }
+
+    /// Reads the pressure using the current `self.fine` value.
+    fn read_pressure_kpa(&mut self) -> Result<f32> {
+        let adc_p = self.read(field::PRESSURE_DATA)?;
+
+        self.compensation().compensate_pressure(adc_p, self.fine)
+    }
+}
+
+/// The settings of the sensor and where it is, without the device.
+///
+/// ```
+/// use bmp280::emulator::Emulator;
+/// use bmp280::Bmp280Builder;
+///
+/// let sensor = Bmp280Builder::new()
+///     .label("outdoor")
+///     .build_with(Emulator::new())
+///     .expect("Could not build device");
+///
+/// let debug = format!("{:?}", sensor);
+/// assert!(debug.starts_with("Bmp280 { path: None, address: 0x77, label: Some(\"outdoor\")"));
+/// ```
+impl<D> fmt::Debug for Bmp280<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reference_pa = Some(self.ground_pressure).filter(|&p| is_reference_pressure(p));
+        let settings = Settings::from_bits(self.control, self.config);
+
+        f.debug_struct("Bmp280")
+            .field("path", &self.path)
+            .field("address", &format_args!("0x{:02x}", self.address))
+            .field("label", &self.label)
+            .field("mode", &settings.mode)
+            .field(
+                "temperature_oversampling",
+                &settings.temperature_oversampling,
+            )
+            .field("pressure_oversampling", &settings.pressure_oversampling)
+            .field("filter", &settings.filter)
+            .field("standby", &settings.standby)
+            .field("reference_pa", &reference_pa)
+            .field("stats", &self.stats)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The reading with units, after the label of the sensor if it has one.
+///
+/// ```
+/// use bmp280::emulator::Emulator;
+/// use bmp280::Bmp280Builder;
+///
+/// let mut sensor = Bmp280Builder::new()
+///     .label("outdoor")
+///     .build_with(Emulator::new())
+///     .expect("Could not build device");
+///
+/// let measurement = sensor.measure().unwrap();
+/// assert_eq!(measurement.to_string(), "outdoor: 25.08 C, 100.653 kPa");
+/// ```
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(label) = &self.label {
+            write!(f, "{}: ", label)?;
+        }
+        write!(
+            f,
+            "{:.2} C, {:.3} kPa",
+            self.temperature_celsius, self.pressure_kpa
+        )?;
+
+        if !self.altitude_m.is_nan() {
+            write!(f, ", {:.2} m", self.altitude_m)?;
+            if self.stale_reference {
+                write!(f, " (stale reference)")?;
+            }
+        }
+        if let Some(reason) = &self.implausible {
+            write!(f, " (implausible {})", reason)?;
+        }
+        if self.limits.saturated {
+            write!(f, " (saturated)")?;
+        } else if !self.limits.is_within() {
+            write!(f, " (out of range)")?;
+        }
+
+        Ok(())
+    }
+}
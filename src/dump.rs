@@ -0,0 +1,196 @@
+//! A snapshot of all registers, decoded by its `Display` implementation for debugging
+//! misconfigured sensors.
+//!
+//! `Bmp280Builder::dump()` reads the registers without setting the sensor up first, so the
+//! configuration written by another program can be inspected as well.
+//!
+//! ```ignore
+//! use bmp280::Bmp280Builder;
+//!
+//! let dump = Bmp280Builder::new().dump().expect("Could not read registers");
+//! print!("{}", dump);
+//! ```
+
+use crate::config::{Filter, Mode, Oversampling, Standby, Status};
+use crate::{adc_value, Bmp280, Bmp280Builder, Error, Register, Result, CHIP_ID};
+use i2cdev::core::I2CDevice;
+use std::fmt;
+
+/// The raw contents of the BMP280 registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterDump {
+    /// 0x88..=0x9F, the calibration words dig_T1 to dig_P9
+    pub calibration: [u8; 24],
+    /// 0xD0
+    pub chip_id: u8,
+    /// 0xE0
+    pub reset: u8,
+    /// 0xF3
+    pub status: u8,
+    /// 0xF4
+    pub ctrl_meas: u8,
+    /// 0xF5
+    pub config: u8,
+    /// 0xF7..=0xF9, press_msb, press_lsb and press_xlsb
+    pub press: [u8; 3],
+    /// 0xFA..=0xFC, temp_msb, temp_lsb and temp_xlsb
+    pub temp: [u8; 3],
+}
+
+const CALIBRATION_NAMES: [&str; 12] = [
+    "dig_T1", "dig_T2", "dig_T3", "dig_P1", "dig_P2", "dig_P3", "dig_P4", "dig_P5", "dig_P6",
+    "dig_P7", "dig_P8", "dig_P9",
+];
+
+impl RegisterDump {
+    /// The uncompensated pressure reading.
+    pub fn adc_p(&self) -> u32 {
+        adc_value(self.press) as u32
+    }
+
+    /// The uncompensated temperature reading.
+    pub fn adc_t(&self) -> u32 {
+        adc_value(self.temp) as u32
+    }
+}
+
+impl fmt::Display for RegisterDump {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let chip = if self.chip_id == CHIP_ID {
+            "BMP280"
+        } else {
+            "unknown chip"
+        };
+        writeln!(f, "0xD0 id         0x{:02X}  {}", self.chip_id, chip)?;
+        writeln!(f, "0xE0 reset      0x{:02X}", self.reset)?;
+
+        let status = Status::from_bits(self.status);
+        writeln!(
+            f,
+            "0xF3 status     0x{:02X}  measuring={} im_update={}",
+            self.status, status.measuring as u8, status.im_update as u8
+        )?;
+
+        writeln!(
+            f,
+            "0xF4 ctrl_meas  0x{:02X}  osrs_t={:?} osrs_p={:?} mode={:?}",
+            self.ctrl_meas,
+            Oversampling::from_bits(self.ctrl_meas >> 5),
+            Oversampling::from_bits(self.ctrl_meas >> 2),
+            Mode::from_bits(self.ctrl_meas)
+        )?;
+
+        writeln!(
+            f,
+            "0xF5 config     0x{:02X}  t_sb={}ms filter={:?} spi3w_en={}",
+            self.config,
+            Standby::from_bits(self.config >> 5).millis(),
+            Filter::from_bits(self.config >> 2),
+            self.config & 1
+        )?;
+
+        writeln!(
+            f,
+            "0xF7 press      0x{:02X} 0x{:02X} 0x{:02X}  adc_P={}",
+            self.press[0],
+            self.press[1],
+            self.press[2],
+            self.adc_p()
+        )?;
+        writeln!(
+            f,
+            "0xFA temp       0x{:02X} 0x{:02X} 0x{:02X}  adc_T={}",
+            self.temp[0],
+            self.temp[1],
+            self.temp[2],
+            self.adc_t()
+        )?;
+
+        for (i, name) in CALIBRATION_NAMES.iter().enumerate() {
+            let word = u16::from_le_bytes([self.calibration[2 * i], self.calibration[2 * i + 1]]);
+            // dig_T1 and dig_P1 are unsigned, all other words are signed.
+            let value = if i == 0 || i == 3 {
+                i32::from(word)
+            } else {
+                i32::from(word as i16)
+            };
+            writeln!(
+                f,
+                "0x{:02X} {:<10} 0x{:04X}  {}",
+                0x88 + 2 * i,
+                name,
+                word,
+                value
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn read_bytes<D>(device: &mut D, reg: &Register, buf: &mut [u8]) -> Result<()>
+where
+    D: I2CDevice,
+    Error: From<D::Error>,
+{
+    device.write(&[reg.into()])?;
+    device.read(buf)?;
+
+    Ok(())
+}
+
+fn read8<D>(device: &mut D, reg: &Register) -> Result<u8>
+where
+    D: I2CDevice,
+    Error: From<D::Error>,
+{
+    let mut buf = [0];
+    read_bytes(device, reg, &mut buf)?;
+
+    Ok(buf[0])
+}
+
+impl RegisterDump {
+    /// Read all registers from the device.
+    pub fn read<D>(device: &mut D) -> Result<Self>
+    where
+        D: I2CDevice,
+        Error: From<D::Error>,
+    {
+        let mut calibration = [0; 24];
+        read_bytes(device, &Register::DigT1, &mut calibration)?;
+
+        let mut data = [0; 6];
+        read_bytes(device, &Register::PressureData, &mut data)?;
+
+        Ok(RegisterDump {
+            calibration,
+            chip_id: read8(device, &Register::ChipId)?,
+            reset: read8(device, &Register::SoftReset)?,
+            status: read8(device, &Register::Status)?,
+            ctrl_meas: read8(device, &Register::Control)?,
+            config: read8(device, &Register::Config)?,
+            press: [data[0], data[1], data[2]],
+            temp: [data[3], data[4], data[5]],
+        })
+    }
+}
+
+impl<D> Bmp280<D>
+where
+    D: I2CDevice,
+    Error: From<D::Error>,
+{
+    /// Read all registers.
+    pub fn dump(&mut self) -> Result<RegisterDump> {
+        RegisterDump::read(&mut self.i2c_device)
+    }
+}
+
+impl Bmp280Builder {
+    /// Open the device and read all registers, without checking the chip ID or configuring the
+    /// sensor like `.build()` does.
+    pub fn dump(&self) -> Result<RegisterDump> {
+        RegisterDump::read(&mut self.open()?)
+    }
+}
@@ -0,0 +1,432 @@
+//! An in-memory emulation of the registers of a BMP280, to test code using the sensor without
+//! hardware.
+//!
+//! The emulator is a handle to shared state, so a clone can be kept to control the emulated
+//! sensor after handing the emulator to `Bmp280Builder::build_with()`.
+//!
+//! ```
+//! use bmp280::emulator::Emulator;
+//! use bmp280::{Bmp280Builder, RawMeasurement};
+//!
+//! let emulator = Emulator::new();
+//! let mut sensor = Bmp280Builder::new()
+//!     .build_with(emulator.clone())
+//!     .expect("Could not build device");
+//!
+//! // Set up with the datasheet calibration and example ADC values.
+//! let measurement = sensor.measure().unwrap();
+//! assert_eq!(measurement.temperature_celsius, 25.08);
+//!
+//! // A forced measurement waits for the conversion, then puts the sensor back into normal mode.
+//! emulator.set_raw(RawMeasurement { adc_t: 560000, adc_p: 480000 });
+//! emulator.set_conversion_reads(3);
+//! let measurement = sensor.forced_measurement().unwrap();
+//! assert_eq!(measurement.temperature_celsius, 37.63);
+//! assert_eq!(emulator.register(0xF4), 0x37);
+//!
+//! // An unplugged sensor fails to answer.
+//! emulator.disconnect();
+//! assert!(sensor.measure().is_err());
+//! emulator.connect();
+//! assert!(sensor.measure().is_ok());
+//! ```
+//!
+//! Setting up a sensor goes through the same checks as with hardware:
+//!
+//! ```
+//! use bmp280::emulator::Emulator;
+//! use bmp280::{Bmp280Builder, Error};
+//!
+//! let emulator = Emulator::new();
+//! emulator.set_chip_id(0x60);
+//!
+//! match Bmp280Builder::new().build_with(emulator) {
+//!     Err(Error::ChipIdMismatch { found }) => assert_eq!(found, 0x60),
+//!     result => panic!("unexpected result {:?}", result.map(|_| ())),
+//! }
+//!
+//! // Unprogrammed calibration NVM
+//! let emulator = Emulator::new();
+//! emulator.set_registers(0x88, &[0, 0]);
+//!
+//! match Bmp280Builder::new().build_with(emulator) {
+//!     Err(Error::InvalidCalibration { register, value }) => assert_eq!((register, value), (0x88, 0)),
+//!     result => panic!("unexpected result {:?}", result.map(|_| ())),
+//! }
+//! ```
+
+use crate::config::Mode;
+use crate::fixture::Fixture;
+use crate::vectors::{DATASHEET_CALIBRATION, VECTORS};
+use crate::{Calibration, RawMeasurement, CHIP_ID, SOFT_RESET};
+use i2cdev::core::I2CDevice;
+use std::io;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+
+const DIG_T1: usize = 0x88;
+const CHIP_ID_REGISTER: usize = 0xD0;
+const RESET: usize = 0xE0;
+const STATUS: usize = 0xF3;
+const CONTROL: usize = 0xF4;
+const CONFIG: usize = 0xF5;
+const PRESSURE_DATA: usize = 0xF7;
+const TEMPERATURE_DATA: usize = 0xFA;
+
+/// The value of the data registers before the first conversion, and of skipped measurements.
+const SKIPPED: i32 = 0x80000;
+
+/// How `Emulator::play()` moves through a fixture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pacing {
+    /// Every conversion takes the next frame, whatever the time, which makes tests deterministic
+    Step,
+    /// Conversions take the frame that is due by the time since `Emulator::play()`
+    RealTime,
+}
+
+/// An emulated BMP280. Reads auto-increment the register address, are taken from a snapshot of
+/// the registers at the start of the read like the shadow registers of the sensor, and writes
+/// to read-only registers are ignored. In normal mode, a conversion is done before every read of
+/// the temperature, which the driver reads first.
+#[derive(Clone)]
+pub struct Emulator {
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    registers: [u8; 256],
+    /// The register the next read or write starts at
+    pointer: u8,
+    chip_id: u8,
+    calibration: Calibration,
+    /// The ADC values the next conversion results in
+    raw: RawMeasurement,
+    /// The number of status reads a conversion takes
+    conversion_reads: u32,
+    /// The status reads left until the running conversion is done
+    converting: u32,
+    connected: bool,
+    /// Whether the emulated adapter supports SMBus block reads
+    block_reads: bool,
+    playback: Option<Playback>,
+}
+
+struct Playback {
+    fixture: Fixture,
+    pacing: Pacing,
+    started: Instant,
+    /// The next frame for `Pacing::Step`
+    next: usize,
+}
+
+impl Default for Emulator {
+    fn default() -> Self {
+        Emulator::new()
+    }
+}
+
+impl Emulator {
+    /// An emulated BMP280 with the calibration and first ADC values of `vectors`, which converts
+    /// instantly.
+    pub fn new() -> Self {
+        let vector = VECTORS[0];
+        let mut state = State {
+            registers: [0; 256],
+            pointer: 0,
+            chip_id: CHIP_ID,
+            calibration: DATASHEET_CALIBRATION,
+            raw: RawMeasurement {
+                adc_t: vector.adc_t,
+                adc_p: vector.adc_p,
+            },
+            conversion_reads: 0,
+            converting: 0,
+            connected: true,
+            block_reads: true,
+            playback: None,
+        };
+        state.reset();
+
+        Emulator {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // The state is consistent after every method, even if a panic poisoned the lock.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Set the chip ID register, e.g. to emulate a BME280 with 0x60.
+    pub fn set_chip_id(&self, chip_id: u8) {
+        let mut state = self.state();
+        state.chip_id = chip_id;
+        state.registers[CHIP_ID_REGISTER] = chip_id;
+    }
+
+    /// Set the calibration words.
+    pub fn set_calibration(&self, calibration: Calibration) {
+        let mut state = self.state();
+        state.calibration = calibration;
+        state.load_calibration();
+    }
+
+    /// Set the ADC values of the following conversions.
+    pub fn set_raw(&self, raw: RawMeasurement) {
+        self.state().raw = raw;
+    }
+
+    /// Take the ADC values of the following conversions from a fixture, until `.stop()`. The last
+    /// frame is repeated once the end of the fixture is reached.
+    pub fn play(&self, fixture: Fixture, pacing: Pacing) {
+        self.state().playback = Some(Playback {
+            fixture,
+            pacing,
+            started: Instant::now(),
+            next: 0,
+        });
+    }
+
+    /// Go back to the ADC values of `.set_raw()`.
+    pub fn stop(&self) {
+        self.state().playback = None;
+    }
+
+    /// Set how many status reads a conversion takes, 0 to convert instantly.
+    pub fn set_conversion_reads(&self, reads: u32) {
+        self.state().conversion_reads = reads;
+    }
+
+    /// Fail all transactions as if nothing answered on the address, until `.connect()`.
+    pub fn disconnect(&self) {
+        self.state().connected = false;
+    }
+
+    pub fn connect(&self) {
+        self.state().connected = true;
+    }
+
+    /// Emulate an adapter with or without support for SMBus block reads, which fail with
+    /// `EOPNOTSUPP` without it.
+    ///
+    /// ```
+    /// use bmp280::emulator::Emulator;
+    /// use bmp280::Bmp280Builder;
+    ///
+    /// let emulator = Emulator::new();
+    /// emulator.set_block_reads(false);
+    /// let mut sensor = Bmp280Builder::new()
+    ///     .build_with(emulator)
+    ///     .expect("Could not build device");
+    ///
+    /// // The sensor got by with separate writes and reads.
+    /// assert!(!sensor.block_reads());
+    /// assert_eq!(sensor.measure().unwrap().temperature_celsius, 25.08);
+    /// ```
+    pub fn set_block_reads(&self, supported: bool) {
+        self.state().block_reads = supported;
+    }
+
+    /// The current value of a register, without the side effects of reading it over the bus.
+    pub fn register(&self, register: u8) -> u8 {
+        self.state().registers[register as usize]
+    }
+
+    /// Write registers directly, e.g. to emulate corrupt calibration data.
+    pub fn set_registers(&self, register: u8, values: &[u8]) {
+        let mut state = self.state();
+        let start = register as usize;
+        state.registers[start..start + values.len()].copy_from_slice(values);
+    }
+}
+
+impl State {
+    /// The power-on reset, which also copies the calibration from NVM.
+    fn reset(&mut self) {
+        self.registers = [0; 256];
+        self.registers[CHIP_ID_REGISTER] = self.chip_id;
+        self.load_calibration();
+        self.latch(RawMeasurement {
+            adc_t: SKIPPED,
+            adc_p: SKIPPED,
+        });
+        self.converting = 0;
+    }
+
+    fn load_calibration(&mut self) {
+        let words = self.calibration.words();
+
+        for (i, word) in words.iter().enumerate() {
+            let register = DIG_T1 + 2 * i;
+            self.registers[register..register + 2].copy_from_slice(&word.to_le_bytes());
+        }
+    }
+
+    /// Put ADC values into the data registers.
+    fn latch(&mut self, raw: RawMeasurement) {
+        for &(register, value) in [(PRESSURE_DATA, raw.adc_p), (TEMPERATURE_DATA, raw.adc_t)].iter()
+        {
+            let bits = (value as u32) << 4;
+            self.registers[register] = (bits >> 16) as u8;
+            self.registers[register + 1] = (bits >> 8) as u8;
+            self.registers[register + 2] = bits as u8;
+        }
+    }
+
+    fn mode(&self) -> Mode {
+        Mode::from_bits(self.registers[CONTROL])
+    }
+
+    fn start_conversion(&mut self) {
+        self.converting = self.conversion_reads;
+        if self.converting == 0 {
+            self.finish_conversion();
+        }
+    }
+
+    /// The ADC values of the conversion that is finishing.
+    fn conversion(&mut self) -> RawMeasurement {
+        let playback = match &mut self.playback {
+            Some(playback) if !playback.fixture.frames.is_empty() => playback,
+            _ => return self.raw,
+        };
+
+        let frames = &playback.fixture.frames;
+        let index = match playback.pacing {
+            Pacing::Step => {
+                let index = playback.next.min(frames.len() - 1);
+                playback.next = index + 1;
+                index
+            }
+            Pacing::RealTime => playback.fixture.index_at(playback.started.elapsed()),
+        };
+
+        frames[index].reading.raw(&self.calibration)
+    }
+
+    fn finish_conversion(&mut self) {
+        let raw = self.conversion();
+        self.latch(raw);
+        if self.mode() == Mode::Forced {
+            self.registers[CONTROL] &= !0b11;
+        }
+    }
+
+    fn write_register(&mut self, register: usize, value: u8) {
+        match register {
+            RESET if value == SOFT_RESET => self.reset(),
+            CONTROL => {
+                self.registers[CONTROL] = value;
+                if self.mode() == Mode::Forced {
+                    self.start_conversion();
+                }
+            }
+            CONFIG => self.registers[CONFIG] = value,
+            _ => {}
+        }
+    }
+
+    /// The status register, counting down a running conversion.
+    fn read_status(&mut self) -> u8 {
+        if self.converting == 0 {
+            return 0;
+        }
+
+        self.converting -= 1;
+        if self.converting == 0 {
+            self.finish_conversion();
+        }
+        0b1000
+    }
+}
+
+fn not_connected() -> io::Error {
+    io::Error::from_raw_os_error(libc::ENXIO)
+}
+
+fn unsupported() -> io::Error {
+    io::Error::other("not supported by the emulator")
+}
+
+impl I2CDevice for Emulator {
+    type Error = io::Error;
+
+    fn read(&mut self, data: &mut [u8]) -> io::Result<()> {
+        let mut state = self.state();
+        if !state.connected {
+            return Err(not_connected());
+        }
+
+        let start = state.pointer as usize;
+        let temperature = (start..start + data.len()).contains(&TEMPERATURE_DATA);
+        if state.mode() == Mode::Normal && state.converting == 0 && temperature {
+            state.finish_conversion();
+        }
+
+        let snapshot = state.registers;
+        for byte in data.iter_mut() {
+            let register = state.pointer as usize;
+            *byte = match register {
+                STATUS => state.read_status(),
+                _ => snapshot[register],
+            };
+            state.pointer = state.pointer.wrapping_add(1);
+        }
+
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        let mut state = self.state();
+        if !state.connected {
+            return Err(not_connected());
+        }
+
+        let (&register, values) = match data.split_first() {
+            Some(split) => split,
+            None => return Ok(()),
+        };
+
+        state.pointer = register;
+        for &value in values {
+            let register = state.pointer as usize;
+            state.write_register(register, value);
+            state.pointer = state.pointer.wrapping_add(1);
+        }
+
+        Ok(())
+    }
+
+    fn smbus_write_quick(&mut self, _bit: bool) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    fn smbus_read_block_data(&mut self, _register: u8) -> io::Result<Vec<u8>> {
+        Err(unsupported())
+    }
+
+    fn smbus_read_i2c_block_data(&mut self, register: u8, len: u8) -> io::Result<Vec<u8>> {
+        if !self.state().block_reads {
+            return Err(io::Error::from_raw_os_error(libc::EOPNOTSUPP));
+        }
+
+        // Like the sensor, which sees the same bus traffic as for a write and a read.
+        let mut data = vec![0; usize::from(len)];
+        self.write(&[register])?;
+        self.read(&mut data)?;
+        Ok(data)
+    }
+
+    fn smbus_write_block_data(&mut self, _register: u8, _values: &[u8]) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    fn smbus_write_i2c_block_data(&mut self, _register: u8, _values: &[u8]) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    fn smbus_process_block(&mut self, _register: u8, _values: &[u8]) -> io::Result<Vec<u8>> {
+        Err(unsupported())
+    }
+}
@@ -0,0 +1,343 @@
+//! The errors of the crate.
+
+use crate::retry;
+use crate::validate::Implausibility;
+use crate::{RawMeasurement, Register, ADDRESSES, CHIP_ID};
+use i2cdev::linux::LinuxI2CError;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Wrapper type for results
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors that all functions could return.
+#[derive(Debug)]
+pub enum Error {
+    I2cError(LinuxI2CError),
+    IoError(std::io::Error),
+    /// Reading or writing `register` of the sensor at `address` failed with `source`
+    Transfer {
+        address: u16,
+        register: u8,
+        write: bool,
+        source: Box<Error>,
+    },
+    /// A transaction on `register` of the sensor at `address` timed out, either in the I2C
+    /// adapter or because retrying it took longer than the configured timeout. `source` is the
+    /// error of the last attempt.
+    Timeout {
+        address: u16,
+        register: u8,
+        write: bool,
+        elapsed: Duration,
+        source: Box<Error>,
+    },
+    /// There is no device node at `path`, or no device acknowledged `address`
+    NotConnected {
+        path: PathBuf,
+        address: u16,
+        source: LinuxI2CError,
+    },
+    /// The I2C address is neither of the two a BMP280 can have, see
+    /// `Bmp280Builder::allow_any_address()`
+    UnexpectedAddress {
+        address: u16,
+    },
+    /// The path of the I2C device can't be one, for `reason`
+    InvalidDevicePath {
+        path: PathBuf,
+        reason: &'static str,
+    },
+    /// Settings of the builder contradict each other, for `reason`
+    IncompatibleSettings {
+        reason: &'static str,
+    },
+    /// The chip ID register did not contain the BMP280 chip ID
+    ChipIdMismatch {
+        found: u8,
+    },
+    /// The calibration word at `register` can't be right, e.g. a divisor of the compensation
+    /// formulas is zero
+    InvalidCalibration {
+        register: u8,
+        value: u16,
+    },
+    /// A forced measurement was still running after `waited`, according to the status register at
+    /// `register`
+    MeasurementTimeout {
+        register: u8,
+        waited: Duration,
+    },
+    /// The calibration data was still being copied from NVM after `waited`, according to the
+    /// status register at `register`
+    NvmCopyTimeout {
+        register: u8,
+        waited: Duration,
+    },
+    /// The pressure compensation formula went out of range for these raw values
+    PressureComputationOverflow {
+        adc_p: i32,
+        t_fine: i32,
+    },
+    /// The temperature compensation formula went out of range for this raw value
+    TemperatureComputationOverflow {
+        adc_t: i32,
+    },
+    /// The divisor of the pressure compensation formula is zero for this fine temperature value.
+    /// This happens when the temperature is read right after power-up, before the first conversion.
+    PressureDivisionByZero {
+        t_fine: i32,
+    },
+    /// Reading back `register` after writing `wrote` to it returned `read`
+    WriteVerificationFailed {
+        register: u8,
+        wrote: u8,
+        read: u8,
+    },
+    /// An altitude was requested, but the sensor was neither zeroed nor given a ground pressure
+    NoReferencePressure,
+    /// Consecutive reads of the data registers kept disagreeing by more than the tolerance of the
+    /// consistency check; these are the last two
+    InconsistentReading {
+        previous: RawMeasurement,
+        current: RawMeasurement,
+    },
+    /// A reading was rejected by the validation set up with `Bmp280Builder::validation()`
+    ImplausibleReading {
+        reason: Implausibility,
+    },
+}
+
+impl Error {
+    /// The OS error code of the underlying I2C or IO error, if there is one.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        match self.innermost() {
+            Error::I2cError(e) | Error::NotConnected { source: e, .. } => match e {
+                LinuxI2CError::Nix(e) => e.as_errno().map(|errno| errno as i32),
+                LinuxI2CError::Io(e) => e.raw_os_error(),
+            },
+            Error::IoError(e) => e.raw_os_error(),
+            _ => None,
+        }
+    }
+
+    /// The error without the register context added by `Transfer`.
+    pub fn innermost(&self) -> &Error {
+        match self {
+            Error::Transfer { source, .. } => source.innermost(),
+            error => error,
+        }
+    }
+
+    fn into_innermost(self) -> Error {
+        match self {
+            Error::Transfer { source, .. } => source.into_innermost(),
+            error => error,
+        }
+    }
+
+    /// Turn errors that mean there is no sensor at the path and address into `NotConnected`.
+    pub(crate) fn not_connected(self, path: &Path, address: u16) -> Self {
+        let missing = match self.raw_os_error() {
+            Some(libc::ENOENT) | Some(libc::ENXIO) | Some(libc::EREMOTEIO) => {
+                matches!(self.innermost(), Error::I2cError(_))
+            }
+            _ => false,
+        };
+        if !missing {
+            return self;
+        }
+
+        match self.into_innermost() {
+            Error::I2cError(source) => Error::NotConnected {
+                path: path.to_owned(),
+                address,
+                source,
+            },
+            error => error,
+        }
+    }
+}
+
+impl From<LinuxI2CError> for Error {
+    fn from(f: LinuxI2CError) -> Self {
+        Error::I2cError(f)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(f: std::io::Error) -> Self {
+        Error::IoError(f)
+    }
+}
+
+/// Whether the error means the adapter doesn't support the kind of transaction.
+pub(crate) fn is_unsupported(error: &Error) -> bool {
+    if let Error::IoError(e) = error.innermost() {
+        if e.kind() == std::io::ErrorKind::Unsupported {
+            return true;
+        }
+    }
+
+    matches!(
+        error.raw_os_error(),
+        Some(libc::EOPNOTSUPP) | Some(libc::ENOSYS) | Some(libc::ENOTTY) | Some(libc::EINVAL)
+    )
+}
+
+/// Whether the error means the sensor stopped answering on the bus.
+pub(crate) fn is_bus_failure(error: &Error) -> bool {
+    matches!(error.innermost(), Error::Timeout { .. }) || retry::is_transient(error)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::I2cError(_) => write!(f, "I2C transaction failed"),
+            Error::IoError(_) => write!(f, "I/O error"),
+            Error::Transfer {
+                address,
+                register,
+                write,
+                ..
+            } => write!(
+                f,
+                "{} register 0x{:02X} of the sensor at address 0x{:02X} failed",
+                if *write { "writing" } else { "reading" },
+                register,
+                address
+            ),
+            Error::Timeout {
+                address,
+                register,
+                write,
+                elapsed,
+                ..
+            } => write!(
+                f,
+                "{} register 0x{:02X} of the sensor at address 0x{:02X} timed out after {}ms",
+                if *write { "writing" } else { "reading" },
+                register,
+                address,
+                elapsed.as_millis()
+            ),
+            Error::NotConnected { path, address, .. } => write!(
+                f,
+                "no device at address 0x{:02X} on {}",
+                address,
+                path.display()
+            ),
+            Error::UnexpectedAddress { address } => write!(
+                f,
+                "a BMP280 is at address 0x{:02X} or 0x{:02X}, not 0x{:02X}",
+                ADDRESSES[0], ADDRESSES[1], address
+            ),
+            Error::InvalidDevicePath { path, reason } => {
+                write!(f, "{} is not an I2C device: {}", path.display(), reason)
+            }
+            Error::IncompatibleSettings { reason } => {
+                write!(f, "incompatible settings: {}", reason)
+            }
+            Error::ChipIdMismatch { found } => write!(
+                f,
+                "wrong chip ID 0x{:02X} in register 0x{:02X}, expected 0x{:02X} for a BMP280",
+                found,
+                u8::from(&Register::ChipId),
+                CHIP_ID
+            ),
+            Error::InvalidCalibration { register, value } => write!(
+                f,
+                "invalid calibration word 0x{:04X} in register 0x{:02X}",
+                value, register
+            ),
+            Error::MeasurementTimeout { register, waited } => write!(
+                f,
+                "measurement still running after {}ms according to the status register 0x{:02X}",
+                waited.as_millis(),
+                register
+            ),
+            Error::NvmCopyTimeout { register, waited } => write!(
+                f,
+                "calibration data still being copied from NVM after {}ms according to the status \
+                 register 0x{:02X}",
+                waited.as_millis(),
+                register
+            ),
+            Error::PressureComputationOverflow { adc_p, t_fine } => write!(
+                f,
+                "pressure compensation out of range for adc_P={} and t_fine={}",
+                adc_p, t_fine
+            ),
+            Error::TemperatureComputationOverflow { adc_t } => write!(
+                f,
+                "temperature compensation out of range for adc_T={}",
+                adc_t
+            ),
+            Error::PressureDivisionByZero { t_fine } => write!(
+                f,
+                "pressure compensation divides by zero for t_fine={}",
+                t_fine
+            ),
+            Error::WriteVerificationFailed {
+                register,
+                wrote,
+                read,
+            } => write!(
+                f,
+                "register 0x{:02X} reads 0x{:02X} after writing 0x{:02X} to it",
+                register, read, wrote
+            ),
+            Error::NoReferencePressure => write!(
+                f,
+                "no reference pressure for the altitude, zero the sensor or set a ground pressure"
+            ),
+            Error::InconsistentReading { previous, current } => write!(
+                f,
+                "data registers changed between reads, adc_T={} then {} and adc_P={} then {}",
+                previous.adc_t, current.adc_t, previous.adc_p, current.adc_p
+            ),
+            Error::ImplausibleReading { reason } => write!(f, "implausible {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::I2cError(_) => "I2cError",
+            Error::IoError(_) => "IoError",
+            Error::Transfer { .. } => "Transfer",
+            Error::Timeout { .. } => "Timeout",
+            Error::NotConnected { .. } => "NotConnected",
+            Error::UnexpectedAddress { .. } => "UnexpectedAddress",
+            Error::InvalidDevicePath { .. } => "InvalidDevicePath",
+            Error::IncompatibleSettings { .. } => "IncompatibleSettings",
+            Error::ChipIdMismatch { .. } => "ChipIdMismatch",
+            Error::InvalidCalibration { .. } => "InvalidCalibration",
+            Error::MeasurementTimeout { .. } => "MeasurementTimeout",
+            Error::NvmCopyTimeout { .. } => "NvmCopyTimeout",
+            Error::PressureComputationOverflow { .. } => "PressureComputationOverflow",
+            Error::TemperatureComputationOverflow { .. } => "TemperatureComputationOverflow",
+            Error::PressureDivisionByZero { .. } => "PressureDivisionByZero",
+            Error::WriteVerificationFailed { .. } => "WriteVerificationFailed",
+            Error::NoReferencePressure => "NoReferencePressure",
+            Error::InconsistentReading { .. } => "InconsistentReading",
+            Error::ImplausibleReading { .. } => "ImplausibleReading",
+        }
+    }
+
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::I2cError(e) | Error::NotConnected { source: e, .. } => Some(e),
+            Error::IoError(e) => Some(e),
+            // Skip the I2cError or IoError wrapper, which has nothing to add.
+            Error::Transfer { source, .. } | Error::Timeout { source, .. } => match source.as_ref()
+            {
+                Error::I2cError(_) | Error::IoError(_) => source.source(),
+                source => Some(source),
+            },
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! Detecting climbs, descents and floor changes in a stream of altitudes, e.g. to track an
+//! elevator or to arm a drone once it has taken off.
+//!
+//! The altitudes are smoothed first, so the noise of single readings doesn't count as movement.
+//! An event is reported whenever the movement changes:
+//!
+//! ```
+//! use bmp280::events::{AltitudeEvent, EventDetector};
+//! use std::time::{Duration, Instant};
+//!
+//! let mut detector = EventDetector::default();
+//! detector.floor_height_m = Some(3.);
+//!
+//! // 10 readings a second: 5 seconds at rest, 6 seconds going up at 1 m/s, 5 seconds at rest.
+//! let start = Instant::now();
+//! let altitude = |t: f32| 100. + (t - 5.).clamp(0., 6.);
+//! let events: Vec<AltitudeEvent> = (0..160)
+//!     .filter_map(|i| {
+//!         let t = i as f32 / 10.;
+//!         detector.update(altitude(t), start + Duration::from_secs_f32(t))
+//!     })
+//!     .collect();
+//!
+//! assert_eq!(events, [AltitudeEvent::Climb, AltitudeEvent::FloorChange(2)]);
+//! ```
+
+use std::time::{Duration, Instant};
+
+/// A change of the vertical movement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AltitudeEvent {
+    /// Started going up
+    Climb,
+    /// Started going down
+    Descend,
+    /// Came to rest at about the altitude of the last rest, or anywhere if floors aren't counted
+    Stable,
+    /// Came to rest this many floors above (or below, if negative) the last rest
+    FloorChange(i32),
+}
+
+/// Turns altitudes into `AltitudeEvent`s. A climb or descent starts once the smoothed altitude has
+/// both moved faster than `rate_m_per_s` and by more than `height_m` from where it was at rest, and
+/// ends once it moves slower than half the rate.
+#[derive(Debug, Clone)]
+pub struct EventDetector {
+    pub rate_m_per_s: f32,
+    pub height_m: f32,
+    /// The height of a floor, to report coming to rest on another floor as `FloorChange`
+    pub floor_height_m: Option<f32>,
+    /// The time constant of the smoothing
+    pub smoothing: Duration,
+    /// When the last altitude was given, the smoothed altitude and its rate of change
+    last: Option<(Instant, f32, f32)>,
+    /// The current movement, `Stable` at rest
+    moving: AltitudeEvent,
+    /// The smoothed altitude while at rest
+    rest_m: f32,
+}
+
+impl Default for EventDetector {
+    /// Movements of 0.5 m/s over more than 1 m, smoothed over 1 second, without counting floors.
+    fn default() -> Self {
+        EventDetector {
+            rate_m_per_s: 0.5,
+            height_m: 1.,
+            floor_height_m: None,
+            smoothing: Duration::from_secs(1),
+            last: None,
+            moving: AltitudeEvent::Stable,
+            rest_m: 0.,
+        }
+    }
+}
+
+impl EventDetector {
+    /// Take the altitude read at `at`, returning an event if the movement changed. NaN altitudes,
+    /// like those of a sensor without a reference pressure, are skipped.
+    pub fn update(&mut self, altitude_m: f32, at: Instant) -> Option<AltitudeEvent> {
+        if altitude_m.is_nan() {
+            return None;
+        }
+
+        let (last_at, last_m, last_rate) = match self.last {
+            Some(last) => last,
+            None => {
+                self.last = Some((at, altitude_m, 0.));
+                self.rest_m = altitude_m;
+                return None;
+            }
+        };
+
+        let seconds = at.saturating_duration_since(last_at).as_secs_f32();
+        if seconds <= 0. {
+            return None;
+        }
+        let alpha = seconds / (self.smoothing.as_secs_f32() + seconds);
+        let smoothed_m = last_m + alpha * (altitude_m - last_m);
+        let rate = last_rate + alpha * ((smoothed_m - last_m) / seconds - last_rate);
+        self.last = Some((at, smoothed_m, rate));
+
+        let moved_m = smoothed_m - self.rest_m;
+        let moving = match self.moving {
+            AltitudeEvent::Stable if rate > self.rate_m_per_s && moved_m > self.height_m => {
+                AltitudeEvent::Climb
+            }
+            AltitudeEvent::Stable if rate < -self.rate_m_per_s && moved_m < -self.height_m => {
+                AltitudeEvent::Descend
+            }
+            AltitudeEvent::Stable => {
+                // Follows slow drifts, like those of the weather.
+                if rate.abs() < self.rate_m_per_s / 2. {
+                    self.rest_m = smoothed_m;
+                }
+                return None;
+            }
+            _ if rate.abs() < self.rate_m_per_s / 2. => AltitudeEvent::Stable,
+            _ => return None,
+        };
+        self.moving = moving;
+        if moving != AltitudeEvent::Stable {
+            return Some(moving);
+        }
+
+        self.rest_m = smoothed_m;
+        let floors = match self.floor_height_m {
+            Some(floor_height_m) => (moved_m / floor_height_m).round() as i32,
+            None => return Some(AltitudeEvent::Stable),
+        };
+        if floors == 0 {
+            Some(AltitudeEvent::Stable)
+        } else {
+            Some(AltitudeEvent::FloorChange(floors))
+        }
+    }
+
+    /// The current movement, `Stable` at rest.
+    pub fn movement(&self) -> AltitudeEvent {
+        self.moving
+    }
+}
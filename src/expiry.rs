@@ -0,0 +1,123 @@
+//! Expiry of the reference pressure that altitudes are computed from. The weather moves the sea
+//! level pressure by several hPa a day, tens of meters of altitude, so a reference that was right
+//! in the morning is not in the evening.
+//!
+//! With an `ExpiryPolicy`, the sensor takes a new reference once the old one is older than
+//! `max_age`, or flags its measurements with `Measurement::stale_reference` if it can't:
+//!
+//! ```
+//! use bmp280::emulator::Emulator;
+//! use bmp280::expiry::ExpiryPolicy;
+//! use bmp280::{Bmp280Builder, ReferencePressure};
+//! use std::time::Duration;
+//!
+//! let mut sensor = Bmp280Builder::new()
+//!     .ground_pressure(ReferencePressure::STANDARD_SEA_LEVEL)
+//!     .reference_expiry(ExpiryPolicy::flag(Duration::from_millis(10)))
+//!     .build_with(Emulator::new())
+//!     .expect("Could not build device");
+//!
+//! assert!(!sensor.measure().unwrap().stale_reference);
+//! std::thread::sleep(Duration::from_millis(20));
+//! assert!(sensor.measure().unwrap().stale_reference);
+//!
+//! // A new reference is good for another `max_age`.
+//! sensor.set_ground_pressure(ReferencePressure::from_hpa(1010.));
+//! assert!(!sensor.measure().unwrap().stale_reference);
+//! ```
+//!
+//! A `Refresh::Source` can fetch the reference from anywhere, e.g. from the altimeter setting of
+//! a METAR report with `metar::altimeter_pa()` of the `metar` feature:
+//!
+//! ```
+//! # #[cfg(feature = "metar")]
+//! # {
+//! use bmp280::emulator::Emulator;
+//! use bmp280::expiry::{ExpiryPolicy, Refresh};
+//! use bmp280::{metar, Bmp280Builder, ReferencePressure};
+//! use std::time::Duration;
+//!
+//! let policy = ExpiryPolicy::refresh(
+//!     Duration::from_millis(10),
+//!     Refresh::source(|| {
+//!         // In practice, the latest report of a station nearby
+//!         let report = "KSFO 121656Z 29012KT 10SM FEW008 17/12 A3002 RMK AO2";
+//!         metar::altimeter_pa(report).ok_or(bmp280::Error::NoReferencePressure)
+//!     }),
+//! );
+//! let mut sensor = Bmp280Builder::new()
+//!     .ground_pressure(ReferencePressure::from_hpa(900.))
+//!     .reference_expiry(policy)
+//!     .build_with(Emulator::new())
+//!     .expect("Could not build device");
+//! assert!(sensor.measure().unwrap().altitude_m < -900.);
+//!
+//! std::thread::sleep(Duration::from_millis(20));
+//! let measurement = sensor.measure().unwrap();
+//! assert!(!measurement.stale_reference);
+//! assert!((measurement.altitude_m - 84.).abs() < 1.);
+//! # }
+//! ```
+
+use crate::Result;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How to take a new reference pressure.
+#[derive(Clone)]
+pub enum Refresh {
+    /// Zero the sensor again on the average of this many readings, for sensors that are back at
+    /// the reference altitude whenever the reference expires, like a weather station or a drone
+    /// between flights
+    Average(u32),
+    /// Take the reference pressure in Pa from elsewhere, like a METAR report
+    Source(Arc<dyn Fn() -> Result<f32> + Send + Sync>),
+}
+
+impl Refresh {
+    pub fn source(source: impl Fn() -> Result<f32> + Send + Sync + 'static) -> Self {
+        Refresh::Source(Arc::new(source))
+    }
+}
+
+impl fmt::Debug for Refresh {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Refresh::Average(count) => f.debug_tuple("Average").field(count).finish(),
+            Refresh::Source(_) => f.write_str("Source(..)"),
+        }
+    }
+}
+
+/// When the reference pressure of a sensor expires and what to do then, set up with
+/// `Bmp280Builder::reference_expiry()`.
+#[derive(Debug, Clone)]
+pub struct ExpiryPolicy {
+    /// How long a reference pressure is good for
+    pub max_age: Duration,
+    /// How to take a new reference once it has expired. Without one, or while it fails,
+    /// measurements have `Measurement::stale_reference` set.
+    pub refresh: Option<Refresh>,
+    /// How long to wait after a failed refresh before trying again
+    pub retry_interval: Duration,
+}
+
+impl ExpiryPolicy {
+    /// Flag measurements once the reference is older than `max_age`.
+    pub fn flag(max_age: Duration) -> Self {
+        ExpiryPolicy {
+            max_age,
+            refresh: None,
+            retry_interval: Duration::from_secs(60),
+        }
+    }
+
+    /// Take a new reference once the old one is older than `max_age`.
+    pub fn refresh(max_age: Duration, refresh: Refresh) -> Self {
+        ExpiryPolicy {
+            refresh: Some(refresh),
+            ..ExpiryPolicy::flag(max_age)
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! A device wrapper that makes the bus misbehave on purpose, to test how retries, recoveries and
+//! the code using a sensor cope with it.
+//!
+//! ```
+//! use bmp280::emulator::Emulator;
+//! use bmp280::fault::{Faults, Faulty};
+//! use bmp280::retry::RetryPolicy;
+//! use bmp280::Bmp280Builder;
+//!
+//! let faults = Faults {
+//!     nak: 0.2,
+//!     ..Faults::default()
+//! };
+//! let mut sensor = Bmp280Builder::new()
+//!     .retry_policy(RetryPolicy::new(10))
+//!     .build_with(Faulty::new(Emulator::new(), faults))
+//!     .expect("Could not build device");
+//!
+//! for _ in 0..10 {
+//!     sensor.measure().unwrap();
+//! }
+//! assert!(sensor.stats().retries > 0);
+//! ```
+
+use crate::Error;
+use i2cdev::core::I2CDevice;
+use std::io;
+
+/// The probabilities, from 0 to 1, of faults in every read or write.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Faults {
+    /// The transfer fails as if nothing acknowledged the address
+    pub nak: f64,
+    /// The transfer fails with a timeout of the adapter
+    pub timeout: f64,
+    /// A random bit of the data read is flipped
+    pub bit_flip: f64,
+    /// The data read ends early, the missing bytes read as 0xFF like an idle bus
+    pub partial_read: f64,
+}
+
+/// The number of faults injected so far, by kind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Injected {
+    pub naks: u64,
+    pub timeouts: u64,
+    pub bit_flips: u64,
+    pub partial_reads: u64,
+}
+
+/// A device that injects faults into the transfers of another.
+pub struct Faulty<D> {
+    device: D,
+    faults: Faults,
+    rng: u64,
+    injected: Injected,
+}
+
+impl<D: I2CDevice> Faulty<D> {
+    pub fn new(device: D, faults: Faults) -> Self {
+        Faulty::with_seed(device, faults, 0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Like `Faulty::new()`, with a different pseudo-random sequence of faults for every seed.
+    pub fn with_seed(device: D, faults: Faults, seed: u64) -> Self {
+        Faulty {
+            device,
+            faults,
+            // xorshift gets stuck at zero
+            rng: seed.max(1),
+            injected: Injected::default(),
+        }
+    }
+
+    /// Change the fault probabilities, e.g. to let the bus settle.
+    pub fn set_faults(&mut self, faults: Faults) {
+        self.faults = faults;
+    }
+
+    pub fn injected(&self) -> Injected {
+        self.injected
+    }
+
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+
+    /// A xorshift64* random number.
+    fn next(&mut self) -> u64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        self.rng.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn happens(&mut self, probability: f64) -> bool {
+        // The top 53 bits as a uniform number in [0, 1)
+        let sample = (self.next() >> 11) as f64 / (1u64 << 53) as f64;
+        probability > 0. && sample < probability
+    }
+
+    /// The fault failing the transfer, if any.
+    fn failure(&mut self) -> Option<Error> {
+        if self.happens(self.faults.nak) {
+            self.injected.naks += 1;
+            return Some(io::Error::from_raw_os_error(libc::ENXIO).into());
+        }
+        if self.happens(self.faults.timeout) {
+            self.injected.timeouts += 1;
+            return Some(io::Error::from_raw_os_error(libc::ETIMEDOUT).into());
+        }
+
+        None
+    }
+
+    /// Corrupt the data of a read, if it is to be corrupted.
+    fn corrupt(&mut self, data: &mut [u8]) {
+        if !data.is_empty() && self.happens(self.faults.partial_read) {
+            self.injected.partial_reads += 1;
+            let len = self.next() as usize % data.len();
+            for byte in &mut data[len..] {
+                *byte = 0xFF;
+            }
+        }
+        if !data.is_empty() && self.happens(self.faults.bit_flip) {
+            self.injected.bit_flips += 1;
+            let bit = self.next() as usize % (data.len() * 8);
+            data[bit / 8] ^= 1 << (bit % 8);
+        }
+    }
+}
+
+impl<D> I2CDevice for Faulty<D>
+where
+    D: I2CDevice,
+    Error: From<D::Error>,
+{
+    type Error = Error;
+
+    fn read(&mut self, data: &mut [u8]) -> Result<(), Error> {
+        if let Some(error) = self.failure() {
+            return Err(error);
+        }
+
+        self.device.read(data)?;
+        self.corrupt(data);
+
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        if let Some(error) = self.failure() {
+            return Err(error);
+        }
+
+        Ok(self.device.write(data)?)
+    }
+
+    fn smbus_write_quick(&mut self, bit: bool) -> Result<(), Error> {
+        Ok(self.device.smbus_write_quick(bit)?)
+    }
+
+    fn smbus_read_block_data(&mut self, register: u8) -> Result<Vec<u8>, Error> {
+        Ok(self.device.smbus_read_block_data(register)?)
+    }
+
+    fn smbus_read_i2c_block_data(&mut self, register: u8, len: u8) -> Result<Vec<u8>, Error> {
+        if let Some(error) = self.failure() {
+            return Err(error);
+        }
+
+        let mut data = self.device.smbus_read_i2c_block_data(register, len)?;
+        self.corrupt(&mut data);
+
+        Ok(data)
+    }
+
+    fn smbus_write_block_data(&mut self, register: u8, values: &[u8]) -> Result<(), Error> {
+        Ok(self.device.smbus_write_block_data(register, values)?)
+    }
+
+    fn smbus_write_i2c_block_data(&mut self, register: u8, values: &[u8]) -> Result<(), Error> {
+        Ok(self.device.smbus_write_i2c_block_data(register, values)?)
+    }
+
+    fn smbus_process_block(&mut self, register: u8, values: &[u8]) -> Result<Vec<u8>, Error> {
+        Ok(self.device.smbus_process_block(register, values)?)
+    }
+}
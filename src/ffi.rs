@@ -0,0 +1,170 @@
+//! A C interface to the driver, for C and C++ programs that would otherwise talk to the sensor
+//! through libi2c themselves. Built with the `ffi` feature; the declarations are in
+//! `include/bmp280.h`, generated with `cbindgen --config cbindgen.toml --output include/bmp280.h`.
+//! The shared library isn't built by default, so crates depending on this one don't build it too;
+//! `cargo rustc --lib --release --crate-type cdylib --features ffi` builds
+//! `target/release/libbmp280.so`.
+//!
+//! ```c
+//! #include <bmp280.h>
+//!
+//! bmp280 *sensor = bmp280_open("/dev/i2c-1", 0x77);
+//! if (sensor == NULL) {
+//!     perror("bmp280_open");
+//!     return 1;
+//! }
+//!
+//! bmp280_measurement measurement;
+//! if (bmp280_zero(sensor) == 0 && bmp280_read(sensor, &measurement) == 0) {
+//!     printf("%.2f C, %.3f kPa\n", measurement.temperature_celsius, measurement.pressure_kpa);
+//! }
+//! bmp280_close(sensor);
+//! ```
+//!
+//! Functions returning an `int` return 0 on success and a negative errno value on failure:
+//! `-EINVAL` for a null pointer or an address a BMP280 can't have, `-ENODEV` if the path isn't an
+//! I2C device or the chip there isn't a BMP280, the error of the I2C device for bus errors and
+//! `-EIO` for the other errors of the driver.
+
+#![allow(unsafe_code, non_camel_case_types)]
+
+use crate::{Bmp280, Bmp280Builder, Error};
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+/// A sensor opened by `bmp280_open()`, only used through a pointer.
+pub struct bmp280 {
+    sensor: Bmp280,
+}
+
+/// A reading of `bmp280_read()`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct bmp280_measurement {
+    pub temperature_celsius: f32,
+    pub pressure_kpa: f32,
+    /// Relative to the pressure at `bmp280_zero()`, NaN before it was called
+    pub altitude_m: f32,
+}
+
+fn error_code(error: &Error) -> c_int {
+    let errno = match error.innermost() {
+        Error::InvalidDevicePath { .. } | Error::ChipIdMismatch { .. } => libc::ENODEV,
+        Error::UnexpectedAddress { .. } => libc::EINVAL,
+        error => error.raw_os_error().unwrap_or(libc::EIO),
+    };
+
+    -errno
+}
+
+fn set_errno(code: c_int) {
+    // SAFETY: errno is thread local, and its location valid for the life of the thread.
+    unsafe { *libc::__errno_location() = code };
+}
+
+/// Open and set up the sensor at `address` on the I2C device at `path`, like `Bmp280Builder`
+/// with its defaults otherwise.
+///
+/// Returns null and sets `errno` if there is no sensor there or it can't be set up.
+///
+/// ```
+/// use bmp280::ffi::bmp280_open;
+/// use std::ffi::CString;
+///
+/// let path = CString::new("/dev/i2c-does-not-exist").unwrap();
+/// let sensor = unsafe { bmp280_open(path.as_ptr(), 0x77) };
+/// assert!(sensor.is_null());
+/// assert_eq!(std::io::Error::last_os_error().raw_os_error(), Some(libc::ENODEV));
+/// ```
+///
+/// # Safety
+///
+/// `path` must be null or point to a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn bmp280_open(path: *const c_char, address: u16) -> *mut bmp280 {
+    if path.is_null() {
+        set_errno(libc::EINVAL);
+        return ptr::null_mut();
+    }
+    // SAFETY: checked for null above, and a NUL terminated string by the contract of the caller.
+    let path = match unsafe { CStr::from_ptr(path) }.to_str() {
+        Ok(path) => path,
+        Err(_) => {
+            set_errno(libc::EINVAL);
+            return ptr::null_mut();
+        }
+    };
+
+    match Bmp280Builder::new().path(path).address(address).build() {
+        Ok(sensor) => Box::into_raw(Box::new(bmp280 { sensor })),
+        Err(e) => {
+            set_errno(-error_code(&e));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Take a reading of the sensor into `measurement`.
+///
+/// # Safety
+///
+/// `sensor` must be null or returned by `bmp280_open()` and not closed yet, and `measurement`
+/// null or valid for writes. Neither may be used by another thread during the call.
+#[no_mangle]
+pub unsafe extern "C" fn bmp280_read(
+    sensor: *mut bmp280,
+    measurement: *mut bmp280_measurement,
+) -> c_int {
+    // SAFETY: null or valid and not shared, by the contract of the caller.
+    let (sensor, out) = match unsafe { (sensor.as_mut(), measurement.as_mut()) } {
+        (Some(sensor), Some(out)) => (sensor, out),
+        _ => return -libc::EINVAL,
+    };
+
+    match sensor.sensor.measure() {
+        Ok(measurement) => {
+            *out = bmp280_measurement {
+                temperature_celsius: measurement.temperature_celsius,
+                pressure_kpa: measurement.pressure_kpa,
+                altitude_m: measurement.altitude_m,
+            };
+            0
+        }
+        Err(e) => error_code(&e),
+    }
+}
+
+/// Take the current pressure as the reference of the altitudes of later readings.
+///
+/// # Safety
+///
+/// `sensor` must be null or returned by `bmp280_open()` and not closed yet, and not be used by
+/// another thread during the call.
+#[no_mangle]
+pub unsafe extern "C" fn bmp280_zero(sensor: *mut bmp280) -> c_int {
+    // SAFETY: null or valid and not shared, by the contract of the caller.
+    let sensor = match unsafe { sensor.as_mut() } {
+        Some(sensor) => sensor,
+        None => return -libc::EINVAL,
+    };
+
+    match sensor.sensor.zero() {
+        Ok(_) => 0,
+        Err(e) => error_code(&e),
+    }
+}
+
+/// Close the device of the sensor and free it. Does nothing for null.
+///
+/// # Safety
+///
+/// `sensor` must be null or returned by `bmp280_open()` and not closed yet. It can't be used
+/// afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn bmp280_close(sensor: *mut bmp280) {
+    if !sensor.is_null() {
+        // SAFETY: allocated by `bmp280_open()` and not freed yet, by the contract of the caller.
+        drop(unsafe { Box::from_raw(sensor) });
+    }
+}
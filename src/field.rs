@@ -0,0 +1,198 @@
+//! The registers of the sensor with the type of their values: how many bytes each one spans, in
+//! which byte order, and whether its value is signed. Reading a register through its field decodes
+//! it the one way it is stored, so a calibration word can't be read big endian or unsigned by
+//! mistake:
+//!
+//! ```
+//! use bmp280::field;
+//!
+//! // The calibration words are little endian, dig_T1 and dig_P1 unsigned and the others signed.
+//! assert_eq!(field::DIG_T1.decode([0x70, 0x6B]), 27504);
+//! assert_eq!(field::DIG_P2.decode([0x43, 0xD6]), -10685);
+//!
+//! // The ADC values are 20 bits in 3 bytes, most significant first.
+//! assert_eq!(field::PRESSURE_DATA.width(), 3);
+//! assert_eq!(field::PRESSURE_DATA.decode([0x65, 0x5A, 0xC0]), 415148);
+//! ```
+
+use crate::registers::{adc_value, Register};
+use crate::RawMeasurement;
+use std::fmt;
+
+/// A register holding a value of type `T` in `N` bytes.
+#[derive(Clone, Copy)]
+pub struct Field<T, const N: usize> {
+    register: Register,
+    decode: fn([u8; N]) -> T,
+}
+
+/// A register of a single byte. These are the only registers that can be written.
+pub type Byte = Field<u8, 1>;
+
+impl<T, const N: usize> Field<T, N> {
+    const fn new(register: Register, decode: fn([u8; N]) -> T) -> Self {
+        Field { register, decode }
+    }
+
+    /// The register of the first byte.
+    pub const fn register(&self) -> Register {
+        self.register
+    }
+
+    /// The number of bytes the value spans, from the first register on.
+    pub const fn width(&self) -> usize {
+        N
+    }
+
+    /// The value held by the bytes read from the register.
+    pub fn decode(&self, bytes: [u8; N]) -> T {
+        (self.decode)(bytes)
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for Field<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Field")
+            .field("register", &self.register)
+            .field("width", &N)
+            .finish()
+    }
+}
+
+fn byte([value]: [u8; 1]) -> u8 {
+    value
+}
+
+pub const DIG_T1: Field<u16, 2> = Field::new(Register::DigT1, u16::from_le_bytes);
+pub const DIG_T2: Field<i16, 2> = Field::new(Register::DigT2, i16::from_le_bytes);
+pub const DIG_T3: Field<i16, 2> = Field::new(Register::DigT3, i16::from_le_bytes);
+
+pub const DIG_P1: Field<u16, 2> = Field::new(Register::DigP1, u16::from_le_bytes);
+pub const DIG_P2: Field<i16, 2> = Field::new(Register::DigP2, i16::from_le_bytes);
+pub const DIG_P3: Field<i16, 2> = Field::new(Register::DigP3, i16::from_le_bytes);
+pub const DIG_P4: Field<i16, 2> = Field::new(Register::DigP4, i16::from_le_bytes);
+pub const DIG_P5: Field<i16, 2> = Field::new(Register::DigP5, i16::from_le_bytes);
+pub const DIG_P6: Field<i16, 2> = Field::new(Register::DigP6, i16::from_le_bytes);
+pub const DIG_P7: Field<i16, 2> = Field::new(Register::DigP7, i16::from_le_bytes);
+pub const DIG_P8: Field<i16, 2> = Field::new(Register::DigP8, i16::from_le_bytes);
+pub const DIG_P9: Field<i16, 2> = Field::new(Register::DigP9, i16::from_le_bytes);
+
+pub const CHIP_ID: Byte = Field::new(Register::ChipId, byte);
+pub const VERSION: Byte = Field::new(Register::Version, byte);
+pub const SOFT_RESET: Byte = Field::new(Register::SoftReset, byte);
+pub const STATUS: Byte = Field::new(Register::Status, byte);
+pub const CONTROL: Byte = Field::new(Register::Control, byte);
+pub const CONFIG: Byte = Field::new(Register::Config, byte);
+
+/// The 20 bit pressure ADC value, see `RawMeasurement::adc_p`
+pub const PRESSURE_DATA: Field<i32, 3> = Field::new(Register::PressureData, adc_value);
+/// The 20 bit temperature ADC value, see `RawMeasurement::adc_t`
+pub const TEMPERATURE_DATA: Field<i32, 3> = Field::new(Register::TemperatureData, adc_value);
+
+fn data_registers(data: [u8; 6]) -> RawMeasurement {
+    RawMeasurement::from_data_registers(&data)
+}
+
+/// Both ADC values, read in a single burst so they come from the same conversion
+pub const DATA: Field<RawMeasurement, 6> = Field::new(Register::PressureData, data_registers);
+
+#[cfg(test)]
+// The bits are grouped by the fields of the registers.
+#[allow(clippy::unusual_byte_groupings)]
+mod tests {
+    use super::*;
+    use crate::config::{Filter, Mode, Oversampling, Settings, Standby, Status};
+
+    fn check<T, const N: usize>(field: Field<T, N>, register: Register, width: usize) {
+        assert_eq!(field.register(), register);
+        assert_eq!(field.width(), width, "{}", register.name());
+    }
+
+    #[test]
+    fn fields_are_at_their_registers() {
+        check(DIG_T1, Register::DigT1, 2);
+        check(DIG_T2, Register::DigT2, 2);
+        check(DIG_T3, Register::DigT3, 2);
+        check(DIG_P1, Register::DigP1, 2);
+        check(DIG_P2, Register::DigP2, 2);
+        check(DIG_P3, Register::DigP3, 2);
+        check(DIG_P4, Register::DigP4, 2);
+        check(DIG_P5, Register::DigP5, 2);
+        check(DIG_P6, Register::DigP6, 2);
+        check(DIG_P7, Register::DigP7, 2);
+        check(DIG_P8, Register::DigP8, 2);
+        check(DIG_P9, Register::DigP9, 2);
+        check(CHIP_ID, Register::ChipId, 1);
+        check(VERSION, Register::Version, 1);
+        check(SOFT_RESET, Register::SoftReset, 1);
+        check(STATUS, Register::Status, 1);
+        check(CONTROL, Register::Control, 1);
+        check(CONFIG, Register::Config, 1);
+        check(PRESSURE_DATA, Register::PressureData, 3);
+        check(TEMPERATURE_DATA, Register::TemperatureData, 3);
+        check(DATA, Register::PressureData, 6);
+    }
+
+    #[test]
+    fn calibration_words_are_little_endian() {
+        // Table 17 of the datasheet: dig_T1 and dig_P1 are unsigned, the others signed.
+        assert_eq!(DIG_T1.decode([0xFF, 0xFF]), u16::MAX);
+        assert_eq!(DIG_P1.decode([0xFF, 0xFF]), u16::MAX);
+        for field in [
+            DIG_T2, DIG_T3, DIG_P2, DIG_P3, DIG_P4, DIG_P5, DIG_P6, DIG_P7, DIG_P8, DIG_P9,
+        ] {
+            assert_eq!(field.decode([0x34, 0x12]), 0x1234);
+            assert_eq!(field.decode([0xFF, 0xFF]), -1);
+            assert_eq!(field.decode([0x00, 0x80]), i16::MIN);
+        }
+    }
+
+    #[test]
+    fn data_is_pressure_then_temperature() {
+        let raw = DATA.decode([0x12, 0x34, 0x50, 0xAB, 0xCD, 0xE0]);
+        assert_eq!(raw.adc_p, 0x12345);
+        assert_eq!(raw.adc_t, 0xABCDE);
+        assert_eq!(PRESSURE_DATA.decode([0x12, 0x34, 0x50]), 0x12345);
+        assert_eq!(TEMPERATURE_DATA.decode([0xAB, 0xCD, 0xE0]), 0xABCDE);
+    }
+
+    #[test]
+    fn control_bits_match_the_datasheet() {
+        // osrs_t in bits 7..5, osrs_p in bits 4..2, mode in bits 1..0
+        let settings = Settings::from_bits(0, 0)
+            .oversampling(Oversampling::X2, Oversampling::X16)
+            .mode(Mode::Forced)
+            .control_bits();
+        assert_eq!(settings, 0b010_101_01);
+        assert_eq!(Mode::Normal.bits(), 0b11);
+
+        let decoded = Settings::from_bits(0b101_001_11, 0);
+        assert_eq!(decoded.temperature_oversampling, Oversampling::X16);
+        assert_eq!(decoded.pressure_oversampling, Oversampling::X1);
+        assert_eq!(decoded.mode, Mode::Normal);
+    }
+
+    #[test]
+    fn config_bits_match_the_datasheet() {
+        // t_sb in bits 7..5, filter in bits 4..2, spi3w_en in bit 0
+        let config = Settings::from_bits(0, 0)
+            .filter(Filter::X16)
+            .standby(Standby::Ms4000)
+            .config_bits();
+        assert_eq!(config, 0b111_100_00);
+
+        let decoded = Settings::from_bits(0, 0b101_010_00);
+        assert_eq!(decoded.standby, Standby::Ms1000);
+        assert_eq!(decoded.filter, Filter::X4);
+    }
+
+    #[test]
+    fn status_bits_match_the_datasheet() {
+        // measuring in bit 3, im_update in bit 0
+        let status = Status::from_bits(0b1000);
+        assert!(status.measuring && !status.im_update);
+        let status = Status::from_bits(0b0001);
+        assert!(!status.measuring && status.im_update);
+        assert_eq!(Status::from_bits(0b1111_0110), Status::from_bits(0));
+    }
+}
@@ -0,0 +1,242 @@
+//! Recorded readings with their timing, for `Emulator::play()` to reproduce a flight or a weather
+//! event.
+//!
+//! A fixture is a CSV file with a header row, or a file with one JSON object per line. The
+//! readings are either raw values in the `adc_t` and `adc_p` columns, or compensated values in
+//! `temperature_celsius` and `pressure_kpa`, which is what `bmp280 read --format csv` and
+//! `--format json` output. The optional `time_s` column holds the time of the reading in seconds
+//! since the first one. All other columns are ignored.
+//!
+//! ```
+//! use bmp280::emulator::{Emulator, Pacing};
+//! use bmp280::fixture::Fixture;
+//! use bmp280::Bmp280Builder;
+//!
+//! let fixture = Fixture::parse(
+//!     r#"
+//! {"time_s": 0, "temperature_celsius": 21.5, "pressure_kpa": 101.3}
+//! {"time_s": 1, "temperature_celsius": 21.0, "pressure_kpa": 95.0}
+//! "#
+//!     .as_bytes(),
+//! )
+//! .unwrap();
+//!
+//! let emulator = Emulator::new();
+//! emulator.play(fixture, Pacing::Step);
+//! let mut sensor = Bmp280Builder::new()
+//!     .build_with(emulator)
+//!     .expect("Could not build device");
+//!
+//! let first = sensor.measure().unwrap();
+//! let second = sensor.measure().unwrap();
+//! assert_eq!(first.temperature_celsius, 21.5);
+//! assert!((second.pressure_kpa - 95.0).abs() < 0.001);
+//! ```
+
+use crate::{Calibration, RawMeasurement, ADC_RANGE};
+use std::io::{self, BufRead};
+use std::time::Duration;
+
+/// A reading of a fixture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reading {
+    Raw(RawMeasurement),
+    Compensated {
+        temperature_celsius: f32,
+        pressure_kpa: f32,
+    },
+}
+
+impl Reading {
+    /// The raw values that `calibration` compensates to the reading, or the closest ones to it.
+    pub fn raw(&self, calibration: &Calibration) -> RawMeasurement {
+        match *self {
+            Reading::Raw(raw) => raw,
+            Reading::Compensated {
+                temperature_celsius,
+                pressure_kpa,
+            } => invert(calibration, temperature_celsius, pressure_kpa * 1000.),
+        }
+    }
+}
+
+/// A reading and its time since the first one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    pub at: Duration,
+    pub reading: Reading,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Fixture {
+    pub frames: Vec<Frame>,
+}
+
+fn invalid(line: usize, message: impl Into<String>) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line, message.into()),
+    )
+}
+
+impl Fixture {
+    /// Read a CSV or JSON lines fixture, telling them apart by the first character.
+    pub fn parse(input: impl BufRead) -> io::Result<Self> {
+        let mut fixture = Fixture::default();
+        let mut header: Option<Vec<String>> = None;
+
+        for (i, line) in input.lines().enumerate() {
+            let line = line?;
+            let number = i + 1;
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields = if line.starts_with('{') {
+                parse_json(number, line)?
+            } else {
+                let values = line.split(',').map(|v| v.trim().to_string());
+                match &header {
+                    Some(header) => header.iter().cloned().zip(values).collect(),
+                    None => {
+                        header = Some(values.collect());
+                        continue;
+                    }
+                }
+            };
+
+            fixture.frames.push(frame(number, &fields)?);
+        }
+
+        Ok(fixture)
+    }
+
+    /// The time of the last frame.
+    pub fn duration(&self) -> Duration {
+        self.frames.last().map_or(Duration::ZERO, |frame| frame.at)
+    }
+
+    /// The index of the last frame at or before `at`.
+    pub fn index_at(&self, at: Duration) -> usize {
+        self.frames
+            .iter()
+            .rposition(|frame| frame.at <= at)
+            .unwrap_or(0)
+    }
+}
+
+/// The frame from the named fields of a row.
+fn frame(line: usize, fields: &[(String, String)]) -> io::Result<Frame> {
+    let get = |name: &str| -> io::Result<Option<f64>> {
+        match fields.iter().find(|(n, _)| n == name) {
+            Some((_, value)) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| invalid(line, format!("invalid value for {}", name))),
+            None => Ok(None),
+        }
+    };
+
+    let at = match get("time_s")? {
+        Some(seconds) if seconds >= 0. && seconds.is_finite() => Duration::from_secs_f64(seconds),
+        Some(_) => return Err(invalid(line, "invalid value for time_s")),
+        None => Duration::ZERO,
+    };
+
+    let reading = match (get("adc_t")?, get("adc_p")?) {
+        (Some(adc_t), Some(adc_p)) => Reading::Raw(RawMeasurement {
+            adc_t: adc_t as i32,
+            adc_p: adc_p as i32,
+        }),
+        _ => match (get("temperature_celsius")?, get("pressure_kpa")?) {
+            (Some(temperature), Some(pressure)) => Reading::Compensated {
+                temperature_celsius: temperature as f32,
+                pressure_kpa: pressure as f32,
+            },
+            _ => {
+                return Err(invalid(
+                    line,
+                    "no adc_t and adc_p or temperature_celsius and pressure_kpa",
+                ))
+            }
+        },
+    };
+
+    Ok(Frame { at, reading })
+}
+
+/// The fields of a flat JSON object, with string values unquoted but not unescaped.
+fn parse_json(line: usize, s: &str) -> io::Result<Vec<(String, String)>> {
+    let body = s
+        .strip_prefix('{')
+        .and_then(|s| s.strip_suffix('}'))
+        .ok_or_else(|| invalid(line, "expected a JSON object"))?;
+
+    let mut fields = Vec::new();
+    let mut rest = body.trim();
+    while !rest.is_empty() {
+        let (key, after) = json_string(rest).ok_or_else(|| invalid(line, "expected a key"))?;
+        let after = after
+            .trim_start()
+            .strip_prefix(':')
+            .ok_or_else(|| invalid(line, "expected ':'"))?
+            .trim_start();
+
+        let (value, after) = if after.starts_with('"') {
+            json_string(after).ok_or_else(|| invalid(line, "unterminated string"))?
+        } else {
+            let end = after.find(',').unwrap_or(after.len());
+            (after[..end].trim(), &after[end..])
+        };
+        fields.push((key.to_string(), value.to_string()));
+
+        rest = after.trim_start();
+        rest = rest.strip_prefix(',').unwrap_or(rest).trim_start();
+    }
+
+    Ok(fields)
+}
+
+/// The contents of the JSON string `s` starts with, and what follows it.
+fn json_string(s: &str) -> Option<(&str, &str)> {
+    let s = s.strip_prefix('"')?;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some((&s[..i], &s[i + 1..])),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+/// The raw values closest to a temperature and a pressure in Pa. Temperature rises and pressure
+/// falls with the ADC values, so both can be found by bisection.
+fn invert(calibration: &Calibration, temperature: f32, pressure: f32) -> RawMeasurement {
+    let adc_t = bisect(|adc_t| calibration.compensate_temperature(adc_t).0 >= temperature);
+    let (_, fine) = calibration.compensate_temperature(adc_t);
+    let adc_p = bisect(|adc_p| {
+        calibration
+            .compensate_pressure(adc_p, fine)
+            .map_or(true, |kpa| kpa * 1000. <= pressure)
+    });
+
+    RawMeasurement { adc_t, adc_p }
+}
+
+/// The lowest ADC value that `reached` is true for, or the highest one if there is none.
+fn bisect(reached: impl Fn(i32) -> bool) -> i32 {
+    let (mut low, mut high) = (*ADC_RANGE.start(), *ADC_RANGE.end());
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if reached(mid) {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    low
+}
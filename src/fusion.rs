@@ -0,0 +1,114 @@
+//! Barometric altitudes corrected with GPS fixes.
+//!
+//! The barometric altitude is precise from one reading to the next, but drifts by meters an hour as
+//! the weather moves the pressure at sea level. The altitude of a GPS fix doesn't drift, but jumps
+//! around by several meters. `AltitudeEstimator` takes both: the difference between them is
+//! smoothed over a long time into the bias of the barometric altitude, which is taken off every
+//! barometric altitude. The blended altitude keeps the short term precision of the barometer with
+//! the long term accuracy of GPS:
+//!
+//! ```
+//! use bmp280::fusion::AltitudeEstimator;
+//! use std::time::{Duration, Instant};
+//!
+//! let mut estimator = AltitudeEstimator::default();
+//!
+//! // Standing at 100 m for an hour while the barometric altitude drifts up by 10 m, with a GPS fix
+//! // every second that is 4 m off either way.
+//! let start = Instant::now();
+//! for second in 0..3600 {
+//!     let baro = 100. + second as f32 / 360.;
+//!     let gps = if second % 2 == 0 { 104. } else { 96. };
+//!
+//!     estimator.update(baro);
+//!     estimator.gps_fix(gps, start + Duration::from_secs(second));
+//! }
+//!
+//! assert!((estimator.altitude_m().unwrap() - 100.).abs() < 1.);
+//! assert!((estimator.bias_m().unwrap() - 10.).abs() < 1.);
+//! ```
+//!
+//! Both altitudes should be above the same datum: the barometric one above sea level, see
+//! `Bmp280::set_ground_pressure()`, and the GPS one above mean sea level, like the altitude of the
+//! NMEA `GGA` sentence, rather than above the WGS84 ellipsoid.
+
+use std::time::{Duration, Instant};
+
+/// Blends barometric altitudes with GPS fixes.
+#[derive(Debug, Clone)]
+pub struct AltitudeEstimator {
+    /// The time constant of the smoothing of the bias. Longer ones follow the drift more slowly,
+    /// but pass on less of the noise of the fixes.
+    pub time_constant: Duration,
+    /// The last barometric altitude given
+    baro: Option<f32>,
+    bias: Option<f32>,
+    /// When the last GPS fix was given
+    last_fix: Option<Instant>,
+}
+
+impl Default for AltitudeEstimator {
+    /// The bias smoothed over 100 seconds.
+    fn default() -> Self {
+        AltitudeEstimator {
+            time_constant: Duration::from_secs(100),
+            baro: None,
+            bias: None,
+            last_fix: None,
+        }
+    }
+}
+
+impl AltitudeEstimator {
+    /// Take a barometric altitude in m, returning the blended altitude. Until the first GPS fix,
+    /// that is the barometric altitude itself. NaN altitudes are skipped.
+    pub fn update(&mut self, baro_altitude_m: f32) -> Option<f32> {
+        if !baro_altitude_m.is_nan() {
+            self.baro = Some(baro_altitude_m);
+        }
+        self.altitude_m()
+    }
+
+    /// Take the altitude in m of a GPS fix made at `at`, updating the bias against the last
+    /// barometric altitude. Fixes before the first barometric altitude, and NaN altitudes, are
+    /// skipped, as are fixes without a vertical solution, which should not be given.
+    pub fn gps_fix(&mut self, altitude_m: f32, at: Instant) {
+        let baro = match self.baro {
+            Some(baro) if !altitude_m.is_nan() => baro,
+            _ => return,
+        };
+        let error = baro - altitude_m;
+
+        self.bias = Some(match (self.bias, self.last_fix) {
+            (Some(bias), Some(last_fix)) => {
+                let seconds = at.saturating_duration_since(last_fix).as_secs_f32();
+                let alpha = seconds / (self.time_constant.as_secs_f32() + seconds);
+                bias + alpha * (error - bias)
+            }
+            _ => error,
+        });
+        self.last_fix = Some(at);
+    }
+
+    /// The last barometric altitude less the bias, `None` until a barometric altitude was given.
+    pub fn altitude_m(&self) -> Option<f32> {
+        self.baro.map(|baro| baro - self.bias.unwrap_or(0.))
+    }
+
+    /// How far the barometric altitude is estimated to be above the true one, in m. `None` until
+    /// the first GPS fix.
+    pub fn bias_m(&self) -> Option<f32> {
+        self.bias
+    }
+
+    /// When the last GPS fix was given, so callers can tell when the bias is getting old.
+    pub fn last_fix(&self) -> Option<Instant> {
+        self.last_fix
+    }
+
+    /// Forget the bias, e.g. after the ground pressure was set again.
+    pub fn reset(&mut self) {
+        self.bias = None;
+        self.last_fix = None;
+    }
+}
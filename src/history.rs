@@ -0,0 +1,263 @@
+//! The pressure and temperature of the last hours, kept in a file so the history survives restarts
+//! of the program recording it. The pressure tendency of the last 3 hours, which weather forecasts
+//! go by, is there as soon as the program is running again instead of 3 hours later.
+//!
+//! The file is a ring buffer of a fixed number of samples, so it never grows and every sample is
+//! a single small write:
+//!
+//! ```
+//! use bmp280::history::{History, Sample};
+//! use std::time::{Duration, SystemTime};
+//!
+//! let path = std::env::temp_dir().join(format!("bmp280-history-{}", std::process::id()));
+//! let start = SystemTime::now() - Duration::from_secs(4 * 3600);
+//!
+//! let mut history = History::open(&path, 4).unwrap();
+//! for hour in 0..5 {
+//!     history
+//!         .push(Sample {
+//!             at: start + Duration::from_secs(hour * 3600),
+//!             pressure_pa: 101000. - hour as f32 * 100.,
+//!             temperature_celsius: 20.,
+//!         })
+//!         .unwrap();
+//! }
+//! drop(history);
+//!
+//! // After a restart
+//! let history = History::open(&path, 4).unwrap();
+//! assert_eq!(history.len(), 4);
+//! assert_eq!(history.tendency_pa(bmp280::history::TENDENCY_PERIOD), Some(-300.));
+//!
+//! std::fs::remove_file(&path).unwrap();
+//! ```
+
+use crate::{Measurement, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The period of the pressure tendency in weather reports.
+pub const TENDENCY_PERIOD: Duration = Duration::from_secs(3 * 3600);
+
+/// The start of a history file, with the version of the format.
+const MAGIC: &[u8; 8] = b"bmp280h1";
+
+/// The magic, the capacity and the index of the slot written next.
+const HEADER_SIZE: u64 = 16;
+
+/// The time in milliseconds since the Unix epoch, 0 for an empty slot, the pressure and the
+/// temperature.
+pub(crate) const SLOT_SIZE: u64 = 16;
+
+/// A reading in the history.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub at: SystemTime,
+    pub pressure_pa: f32,
+    pub temperature_celsius: f32,
+}
+
+impl Sample {
+    /// The sample of a measurement taken just now.
+    pub fn now(measurement: &Measurement) -> Self {
+        Sample {
+            at: SystemTime::now(),
+            pressure_pa: measurement.pressure_kpa * 1000.,
+            temperature_celsius: measurement.temperature_celsius,
+        }
+    }
+
+    pub(crate) fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        let millis = self
+            .at
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis() as u64);
+
+        // A sample from exactly the epoch would read back as an empty slot.
+        writer.write_u64::<LittleEndian>(millis.max(1))?;
+        writer.write_f32::<LittleEndian>(self.pressure_pa)?;
+        writer.write_f32::<LittleEndian>(self.temperature_celsius)
+    }
+
+    pub(crate) fn read(mut reader: impl Read) -> io::Result<Option<Self>> {
+        let millis = reader.read_u64::<LittleEndian>()?;
+        let pressure_pa = reader.read_f32::<LittleEndian>()?;
+        let temperature_celsius = reader.read_f32::<LittleEndian>()?;
+
+        if millis == 0 {
+            return Ok(None);
+        }
+        Ok(Some(Sample {
+            at: UNIX_EPOCH + Duration::from_millis(millis),
+            pressure_pa,
+            temperature_celsius,
+        }))
+    }
+}
+
+/// The last samples, oldest first, and the file they are kept in.
+pub struct History {
+    file: File,
+    capacity: usize,
+    /// The slot the next sample is written to
+    next: usize,
+    samples: VecDeque<Sample>,
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl History {
+    /// Open the history file at `path` holding up to `capacity` samples, creating it if it doesn't
+    /// exist. A file written with another capacity is rewritten with as many of its latest
+    /// samples as fit.
+    pub fn open(path: impl AsRef<Path>, capacity: usize) -> Result<Self> {
+        let capacity = capacity.max(1);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+
+        let mut history = History {
+            file,
+            capacity,
+            next: 0,
+            samples: VecDeque::new(),
+        };
+        if contents.is_empty() {
+            history.rewrite()?;
+            return Ok(history);
+        }
+
+        let (slots, next) = parse(&contents)?;
+        let mut samples = slots;
+        samples.sort_by_key(|sample| sample.at);
+        let kept = samples.len().saturating_sub(capacity);
+        history.samples = samples.into_iter().skip(kept).collect();
+
+        if slot_count(&contents) == capacity {
+            history.next = next % capacity;
+        } else {
+            history.rewrite()?;
+        }
+
+        Ok(history)
+    }
+
+    /// Write the whole file from the samples in memory, oldest first.
+    fn rewrite(&mut self) -> Result<()> {
+        self.next = self.samples.len() % self.capacity;
+
+        let size = (HEADER_SIZE + SLOT_SIZE * self.capacity as u64) as usize;
+        let mut contents = Vec::with_capacity(size);
+        contents.extend_from_slice(MAGIC);
+        contents.write_u32::<LittleEndian>(self.capacity as u32)?;
+        contents.write_u32::<LittleEndian>(self.next as u32)?;
+        for sample in &self.samples {
+            sample.write(&mut contents)?;
+        }
+        contents.resize(size, 0);
+
+        self.file.set_len(0)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&contents)?;
+
+        Ok(())
+    }
+
+    /// Add a sample, replacing the oldest one once the history is full. Samples are expected in
+    /// the order they were taken.
+    pub fn push(&mut self, sample: Sample) -> Result<()> {
+        let mut slot = Vec::with_capacity(SLOT_SIZE as usize);
+        sample.write(&mut slot)?;
+
+        self.file
+            .seek(SeekFrom::Start(HEADER_SIZE + SLOT_SIZE * self.next as u64))?;
+        self.file.write_all(&slot)?;
+
+        self.next = (self.next + 1) % self.capacity;
+        self.file.seek(SeekFrom::Start(MAGIC.len() as u64 + 4))?;
+        self.file.write_u32::<LittleEndian>(self.next as u32)?;
+
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+
+        Ok(())
+    }
+
+    /// The samples, oldest first.
+    pub fn samples(&self) -> impl Iterator<Item = &Sample> {
+        self.samples.iter()
+    }
+
+    pub fn latest(&self) -> Option<&Sample> {
+        self.samples.back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The change of the pressure in Pa from the latest sample at least `over` before the latest
+    /// one, up to the latest one. `None` if the history doesn't go back that far.
+    pub fn tendency_pa(&self, over: Duration) -> Option<f32> {
+        let latest = self.latest()?;
+        let since = latest.at.checked_sub(over)?;
+        let earlier = self
+            .samples
+            .iter()
+            .rev()
+            .find(|sample| sample.at <= since)?;
+
+        Some(latest.pressure_pa - earlier.pressure_pa)
+    }
+}
+
+/// The number of slots of a history file.
+fn slot_count(contents: &[u8]) -> usize {
+    ((contents.len() as u64).saturating_sub(HEADER_SIZE) / SLOT_SIZE) as usize
+}
+
+/// The samples in the slots of a history file and the index of the slot written next.
+fn parse(contents: &[u8]) -> io::Result<(Vec<Sample>, usize)> {
+    if contents.len() < HEADER_SIZE as usize || &contents[..MAGIC.len()] != MAGIC {
+        return Err(invalid("not a history file"));
+    }
+
+    let mut reader = Cursor::new(&contents[MAGIC.len()..]);
+    let capacity = reader.read_u32::<LittleEndian>()? as usize;
+    let next = reader.read_u32::<LittleEndian>()? as usize;
+    if slot_count(contents) != capacity {
+        return Err(invalid("truncated history file"));
+    }
+
+    let mut reader = Cursor::new(&contents[HEADER_SIZE as usize..]);
+    let mut samples = Vec::new();
+    for _ in 0..capacity {
+        if let Some(sample) = Sample::read(&mut reader)? {
+            samples.push(sample);
+        }
+    }
+
+    Ok((samples, next))
+}
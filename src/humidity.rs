@@ -0,0 +1,71 @@
+//! Quantities derived from the temperature and the relative humidity, for weather stations and HVAC
+//! controllers. The BMP280 doesn't measure humidity, so it comes from a hygrometer next to it (or
+//! the humidity sensor of a BME280); temperatures are in degrees celsius and relative humidities
+//! in percent.
+//!
+//! ```
+//! use bmp280::humidity::{absolute_humidity_g_m3, dew_point_celsius, heat_index_celsius};
+//!
+//! assert!((dew_point_celsius(25., 60.) - 16.69).abs() < 0.01);
+//! assert!((absolute_humidity_g_m3(25., 60.) - 13.78).abs() < 0.01);
+//! assert!((heat_index_celsius(32., 70.) - 40.41).abs() < 0.01);
+//! ```
+
+/// The coefficients of the Magnus formula for the saturation vapor pressure over water, valid from
+/// -45 to 60 degrees celsius. Both the dew point and the absolute humidity use them, so the two
+/// agree.
+const MAGNUS_A: f32 = 17.62;
+const MAGNUS_B: f32 = 243.12;
+/// The saturation vapor pressure at 0 degrees celsius, in hPa.
+const MAGNUS_HPA: f32 = 6.112;
+
+/// The temperature the air would have to be cooled to for its water vapor to condense, by the
+/// Magnus formula. NaN for a relative humidity of 0.
+pub fn dew_point_celsius(temperature_celsius: f32, relative_humidity: f32) -> f32 {
+    let gamma = (relative_humidity / 100.).ln()
+        + MAGNUS_A * temperature_celsius / (MAGNUS_B + temperature_celsius);
+
+    MAGNUS_B * gamma / (MAGNUS_A - gamma)
+}
+
+/// The mass of water vapor per volume of air, in grams per cubic meter.
+pub fn absolute_humidity_g_m3(temperature_celsius: f32, relative_humidity: f32) -> f32 {
+    // The saturation vapor pressure in hPa, by the Magnus formula
+    let saturation_hpa =
+        MAGNUS_HPA * (MAGNUS_A * temperature_celsius / (MAGNUS_B + temperature_celsius)).exp();
+    // 1 / the specific gas constant of water vapor, in g K / J, times 100 Pa/hPa / 100 %
+    const GRAMS_KELVIN_PER_JOULE: f32 = 2.1674;
+
+    saturation_hpa * relative_humidity * GRAMS_KELVIN_PER_JOULE / (temperature_celsius + 273.15)
+}
+
+/// The temperature it feels like to a person in the shade, by the heat index formula of the US
+/// National Weather Service. Below about 27 degrees celsius this is close to the temperature itself.
+pub fn heat_index_celsius(temperature_celsius: f32, relative_humidity: f32) -> f32 {
+    let t = temperature_celsius * 9. / 5. + 32.;
+    let rh = relative_humidity;
+
+    // The formulas are in degrees fahrenheit. The simple one is good enough for low heat indices,
+    // where the regression of Rothfusz doesn't hold.
+    let simple = 0.5 * (t + 61. + (t - 68.) * 1.2 + rh * 0.094);
+    let fahrenheit = if (simple + t) / 2. < 80. {
+        simple
+    } else {
+        let mut hi = -42.379 + 2.049_015_2 * t + 10.143_331 * rh
+            - 0.224_755_42 * t * rh
+            - 0.006_837_83 * t * t
+            - 0.054_817_17 * rh * rh
+            + 0.001_228_74 * t * t * rh
+            + 0.000_852_82 * t * rh * rh
+            - 0.000_001_99 * t * t * rh * rh;
+
+        if rh < 13. && (80.0..=112.).contains(&t) {
+            hi -= (13. - rh) / 4. * ((17. - (t - 95.).abs()) / 17.).sqrt();
+        } else if rh > 85. && (80.0..=87.).contains(&t) {
+            hi += (rh - 85.) / 10. * ((87. - t) / 5.);
+        }
+        hi
+    };
+
+    (fahrenheit - 32.) * 5. / 9.
+}
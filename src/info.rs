@@ -0,0 +1,170 @@
+//! A summary of what a sensor is and where it is connected, for inventories and diagnostics.
+//!
+//! ```ignore
+//! use bmp280::Bmp280Builder;
+//!
+//! let mut sensor = Bmp280Builder::new().build().expect("Could not build device");
+//! let info = sensor.info().expect("Could not read chip ID");
+//!
+//! println!("{}", info);
+//! println!("{}", info.to_json());
+//! ```
+
+use crate::field;
+use crate::{Bmp280, Calibration, Error, Result};
+use i2cdev::core::I2CDevice;
+use std::fmt;
+use std::path::PathBuf;
+
+/// The kind of sensor, by its chip ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Bmp280,
+    /// A BMP280 engineering sample
+    Bmp280Sample,
+    /// A BME280, which measures humidity as well
+    Bme280,
+    Unknown,
+}
+
+impl Variant {
+    pub fn from_chip_id(chip_id: u8) -> Self {
+        match chip_id {
+            0x58 => Variant::Bmp280,
+            0x56 | 0x57 => Variant::Bmp280Sample,
+            0x60 => Variant::Bme280,
+            _ => Variant::Unknown,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Bmp280 => "BMP280",
+            Variant::Bmp280Sample => "BMP280 sample",
+            Variant::Bme280 => "BME280",
+            Variant::Unknown => "unknown",
+        }
+    }
+}
+
+/// What a sensor is and where it is connected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SensorInfo {
+    /// The label set by `Bmp280Builder::label()`
+    pub label: Option<String>,
+    pub chip_id: u8,
+    pub version: u8,
+    /// The I2C device node, unknown for sensors from `Bmp280Builder::build_with()`
+    pub i2c_path: Option<PathBuf>,
+    pub address: u16,
+    pub variant: Variant,
+    /// A hash of the calibration words, which tells individual sensors apart
+    pub calibration_fingerprint: u32,
+}
+
+impl SensorInfo {
+    /// The summary as a JSON object.
+    pub fn to_json(&self) -> String {
+        let path = match &self.i2c_path {
+            Some(path) => json_string(&path.display().to_string()),
+            None => "null".into(),
+        };
+
+        let label = match &self.label {
+            Some(label) => json_string(label),
+            None => "null".into(),
+        };
+
+        format!(
+            "{{\"label\":{},\"chip_id\":{},\"version\":{},\"i2c_path\":{},\"address\":{},\
+             \"variant\":{},\"calibration_fingerprint\":\"{:08x}\"}}",
+            label,
+            self.chip_id,
+            self.version,
+            path,
+            self.address,
+            json_string(self.variant.name()),
+            self.calibration_fingerprint
+        )
+    }
+}
+
+impl fmt::Display for SensorInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(label) = &self.label {
+            write!(f, "{}: ", label)?;
+        }
+        write!(
+            f,
+            "{} (chip ID 0x{:02X}, version 0x{:02X}) at address 0x{:02X}",
+            self.variant.name(),
+            self.chip_id,
+            self.version,
+            self.address
+        )?;
+        if let Some(path) = &self.i2c_path {
+            write!(f, " on {}", path.display())?;
+        }
+        write!(f, ", calibration {:08x}", self.calibration_fingerprint)
+    }
+}
+
+/// Quote and escape a string for JSON, as in `SensorInfo::to_json()`.
+///
+/// ```
+/// use bmp280::info::json_string;
+///
+/// assert_eq!(json_string("roof \"north\"\n"), r#""roof \"north\"\n""#);
+/// ```
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl Calibration {
+    /// A 32 bit FNV-1a hash of the calibration words in register order, stable across versions of
+    /// this crate.
+    pub fn fingerprint(&self) -> u32 {
+        let mut hash: u32 = 0x811c_9dc5;
+        for word in self.words().iter() {
+            for byte in word.to_le_bytes().iter() {
+                hash ^= u32::from(*byte);
+                hash = hash.wrapping_mul(0x0100_0193);
+            }
+        }
+        hash
+    }
+}
+
+impl<D> Bmp280<D>
+where
+    D: I2CDevice,
+    Error: From<D::Error>,
+{
+    /// Reads the chip ID and version registers and sums up what the sensor is.
+    pub fn info(&mut self) -> Result<SensorInfo> {
+        let chip_id = self.read(field::CHIP_ID)?;
+        let version = self.read(field::VERSION)?;
+
+        Ok(SensorInfo {
+            label: self.label.as_deref().map(String::from),
+            chip_id,
+            version,
+            i2c_path: self.path.clone(),
+            address: self.address,
+            variant: Variant::from_chip_id(chip_id),
+            calibration_fingerprint: self.calibration.fingerprint(),
+        })
+    }
+}
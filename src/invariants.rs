@@ -0,0 +1,266 @@
+//! Properties the compensation formulas have for any sensible calibration, to check changes to
+//! them against beyond the fixed values of `vectors`.
+//!
+//! ```
+//! use bmp280::invariants;
+//! use bmp280::vectors::DATASHEET_CALIBRATION;
+//!
+//! assert_eq!(invariants::sweep(&DATASHEET_CALIBRATION, 64), Ok(()));
+//! ```
+//!
+//! `check()` takes any raw values, so it can also be driven by a property testing framework:
+//!
+//! ```ignore
+//! proptest! {
+//!     #[test]
+//!     fn compensation(adc_t in 0..0xFFFFF, adc_p in 0..0xFFFFF) {
+//!         let raw = RawMeasurement { adc_t, adc_p };
+//!         prop_assert_eq!(invariants::check(&DATASHEET_CALIBRATION, raw), Ok(()));
+//!     }
+//! }
+//! ```
+
+use crate::{Calibration, RawMeasurement, ADC_RANGE};
+use std::fmt;
+
+/// The most the pressure may change for one step of `adc_p`. The resolution of the sensor is
+/// 0.16 Pa at the highest oversampling.
+pub const MAX_PRESSURE_STEP_PA: f32 = 1.;
+
+/// The lowest temperature there is.
+const ABSOLUTE_ZERO_CELSIUS: f32 = -273.15;
+
+/// A place where the compensation breaks one of the invariants.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Violation {
+    /// The temperature is below absolute zero or not a number
+    Temperature { adc_t: i32, celsius: f32 },
+    /// The pressure in Pa is not finite
+    Pressure { raw: RawMeasurement, pa: f32 },
+    /// The temperature is lower for `adc_t + 1`
+    TemperatureDecreasing { adc_t: i32 },
+    /// The pressure is higher for `adc_p + 1`, at the same temperature
+    PressureIncreasing { raw: RawMeasurement },
+    /// The pressure changes by more than `MAX_PRESSURE_STEP_PA` from `adc_p` to `adc_p + 1`
+    PressureStep { raw: RawMeasurement, step_pa: f32 },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::Temperature { adc_t, celsius } => {
+                write!(f, "adc_t {} gives a temperature of {} C", adc_t, celsius)
+            }
+            Violation::Pressure { raw, pa } => write!(
+                f,
+                "adc_t {}, adc_p {} give a pressure of {} Pa",
+                raw.adc_t, raw.adc_p, pa
+            ),
+            Violation::TemperatureDecreasing { adc_t } => {
+                write!(f, "the temperature decreases after adc_t {}", adc_t)
+            }
+            Violation::PressureIncreasing { raw } => write!(
+                f,
+                "the pressure increases after adc_p {} at adc_t {}",
+                raw.adc_p, raw.adc_t
+            ),
+            Violation::PressureStep { raw, step_pa } => write!(
+                f,
+                "the pressure changes by {} Pa after adc_p {} at adc_t {}",
+                step_pa, raw.adc_p, raw.adc_t
+            ),
+        }
+    }
+}
+
+/// Check the invariants at `raw` and at the next step of each ADC value. Values that the
+/// compensation returns an error for, like those outside the 20 bit range, are not checked.
+pub fn check(calibration: &Calibration, raw: RawMeasurement) -> Result<(), Violation> {
+    let RawMeasurement { adc_t, adc_p } = raw;
+
+    let (celsius, fine) = match calibration.checked_compensate_temperature(adc_t) {
+        Ok(compensated) => compensated,
+        Err(_) => return Ok(()),
+    };
+    if celsius.is_nan() || celsius < ABSOLUTE_ZERO_CELSIUS {
+        return Err(Violation::Temperature { adc_t, celsius });
+    }
+    if ADC_RANGE.contains(&(adc_t + 1)) {
+        if let Ok((next, _)) = calibration.checked_compensate_temperature(adc_t + 1) {
+            if next < celsius {
+                return Err(Violation::TemperatureDecreasing { adc_t });
+            }
+        }
+    }
+
+    let pressure = match calibration.pressure_q24_8(adc_p, fine) {
+        Ok(pressure) => pressure,
+        Err(_) => return Ok(()),
+    };
+    let pa = pressure as f32 / 256.;
+    if !pa.is_finite() {
+        return Err(Violation::Pressure { raw, pa });
+    }
+    if ADC_RANGE.contains(&(adc_p + 1)) {
+        if let Ok(next) = calibration.pressure_q24_8(adc_p + 1, fine) {
+            if next > pressure {
+                return Err(Violation::PressureIncreasing { raw });
+            }
+
+            let step_pa = (pressure - next) as f32 / 256.;
+            if step_pa > MAX_PRESSURE_STEP_PA {
+                return Err(Violation::PressureStep { raw, step_pa });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check the invariants on a grid of `steps` by `steps` raw values spread over the ADC range,
+/// including both ends of it.
+pub fn sweep(calibration: &Calibration, steps: u32) -> Result<(), Violation> {
+    let steps = steps.max(2) as i64;
+    let end = *ADC_RANGE.end() as i64;
+    let at = |i: i64| (i * end / (steps - 1)) as i32;
+
+    for i in 0..steps {
+        for j in 0..steps {
+            let raw = RawMeasurement {
+                adc_t: at(i),
+                adc_p: at(j),
+            };
+            check(calibration, raw)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectors::DATASHEET_CALIBRATION;
+    use std::ops::RangeInclusive;
+
+    /// The number of random cases of each property.
+    const CASES: u32 = 2000;
+
+    /// A xorshift64* generator with a fixed seed, so a failing case fails every time.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+        }
+
+        fn in_range(&mut self, range: RangeInclusive<i32>) -> i32 {
+            let span = (*range.end() as i64 - *range.start() as i64 + 1) as u64;
+            (*range.start() as i64 + (self.next() % span) as i64) as i32
+        }
+
+        fn adc(&mut self) -> i32 {
+            self.in_range(ADC_RANGE)
+        }
+
+        /// The word changed by up to `percent` percent of it, at least by 1.
+        fn vary(&mut self, word: i32, percent: i32) -> i32 {
+            let spread = (word.abs() * percent / 100).max(1);
+            word + self.in_range(-spread..=spread)
+        }
+
+        /// A calibration like that of another part, each word within a few percent of the
+        /// datasheet example.
+        fn calibration(&mut self) -> Calibration {
+            let c = DATASHEET_CALIBRATION;
+            let mut signed = |word: i16| self.vary(word as i32, 5) as i16;
+            Calibration {
+                dig_t2: signed(c.dig_t2),
+                dig_t3: signed(c.dig_t3),
+                dig_p2: signed(c.dig_p2),
+                dig_p3: signed(c.dig_p3),
+                dig_p4: signed(c.dig_p4),
+                dig_p5: signed(c.dig_p5),
+                dig_p6: signed(c.dig_p6),
+                dig_p7: signed(c.dig_p7),
+                dig_p8: signed(c.dig_p8),
+                dig_p9: signed(c.dig_p9),
+                dig_t1: self.vary(c.dig_t1 as i32, 5) as u16,
+                dig_p1: self.vary(c.dig_p1 as i32, 5) as u16,
+                ..c
+            }
+        }
+    }
+
+    #[test]
+    fn pressure_falls_as_adc_p_rises() {
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+        let calibration = DATASHEET_CALIBRATION;
+
+        for _ in 0..CASES {
+            let adc_t = rng.adc();
+            let (low, high) = (rng.adc(), rng.adc());
+            let (low, high) = (low.min(high), low.max(high));
+            let fine = calibration.fine_temperature(adc_t).unwrap();
+
+            let at_low = calibration.pressure_q24_8(low, fine).unwrap();
+            let at_high = calibration.pressure_q24_8(high, fine).unwrap();
+            assert!(
+                at_high <= at_low,
+                "adc_t {}: {} Pa at adc_p {}, {} Pa at adc_p {}",
+                adc_t,
+                at_low as f32 / 256.,
+                low,
+                at_high as f32 / 256.,
+                high
+            );
+        }
+    }
+
+    #[test]
+    fn random_calibrations_keep_the_invariants() {
+        let mut rng = Rng(0xD1B5_4A32_D192_ED03);
+
+        for _ in 0..CASES {
+            let calibration = rng.calibration();
+            let raw = RawMeasurement {
+                adc_t: rng.adc(),
+                adc_p: rng.adc(),
+            };
+            assert_eq!(check(&calibration, raw), Ok(()), "{:?}", calibration);
+        }
+    }
+
+    #[test]
+    fn random_calibrations_read_the_datasheet_example_in_the_operating_range() {
+        let mut rng = Rng(0x6A09_E667_F3BC_C908);
+        // The raw values of the example in the datasheet, 25.08 C and 100653 Pa.
+        let raw = RawMeasurement {
+            adc_t: 519888,
+            adc_p: 415148,
+        };
+
+        for _ in 0..CASES {
+            let calibration = rng.calibration();
+            let (celsius, fine) = calibration
+                .checked_compensate_temperature(raw.adc_t)
+                .unwrap();
+            let pa = calibration.pressure_q24_8(raw.adc_p, fine).unwrap() as f32 / 256.;
+            assert!(
+                (-40. ..=85.).contains(&celsius),
+                "{} C with {:?}",
+                celsius,
+                calibration
+            );
+            assert!(
+                (30000. ..=110000.).contains(&pa),
+                "{} Pa with {:?}",
+                pa,
+                calibration
+            );
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! Latency histograms of the bus transactions and conversions of a sensor, to tell whether a slow
+//! sample rate comes from the I2C bus or from the sensor.
+//!
+//! ```
+//! use bmp280::latency::Latency;
+//! use std::time::Duration;
+//!
+//! let mut latency = Latency::default();
+//! for micros in 1..=100 {
+//!     latency.record(Duration::from_micros(micros * 10));
+//! }
+//!
+//! assert_eq!(latency.min(), Some(Duration::from_micros(10)));
+//! assert_eq!(latency.mean(), Some(Duration::from_micros(505)));
+//! // 990us is in the bucket up to 1024us, but nothing took longer than 1ms.
+//! assert_eq!(latency.p99(), Some(Duration::from_micros(1000)));
+//! ```
+
+use std::time::Duration;
+
+/// The number of histogram buckets. Bucket `i` counts durations below 2^i microseconds, the last
+/// one everything longer.
+const BUCKETS: usize = 32;
+
+/// A histogram of durations with power of two buckets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Latency {
+    count: u64,
+    total: Duration,
+    min: Option<Duration>,
+    max: Duration,
+    buckets: [u64; BUCKETS],
+}
+
+impl Latency {
+    pub fn record(&mut self, duration: Duration) {
+        self.count += 1;
+        self.total = self.total.saturating_add(duration);
+        self.min = Some(self.min.map_or(duration, |min| min.min(duration)));
+        self.max = self.max.max(duration);
+
+        let micros = duration.as_micros();
+        let bucket = (u128::BITS - micros.leading_zeros()) as usize;
+        self.buckets[bucket.min(BUCKETS - 1)] += 1;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min(&self) -> Option<Duration> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.min.map(|_| self.max)
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        match self.count {
+            0 => None,
+            count => Some(self.total.div_f64(count as f64)),
+        }
+    }
+
+    /// An upper bound of the duration that `fraction` (e.g. 0.99) of all durations are below,
+    /// exact to a factor of two.
+    pub fn percentile(&self, fraction: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let rank = (fraction.clamp(0., 1.) * self.count as f64).ceil() as u64;
+        let mut seen = 0;
+        for (i, &n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= rank.max(1) {
+                let bound = Duration::from_micros(1 << i);
+                return Some(bound.min(self.max));
+            }
+        }
+
+        Some(self.max)
+    }
+
+    pub fn p99(&self) -> Option<Duration> {
+        self.percentile(0.99)
+    }
+}
+
+/// The latencies of a sensor, see `Bmp280::bus_stats()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BusStats {
+    /// Register reads and writes, including retries
+    pub transactions: Latency,
+    /// The time from triggering a forced measurement until the sensor was done with it
+    pub conversions: Latency,
+}
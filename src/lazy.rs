@@ -0,0 +1,209 @@
+//! Devices that are opened on first use, for sensors built with `Bmp280Builder::build_lazy()`,
+//! and a pool to share them between sensors and threads.
+//!
+//! Opening is tried again on every use until it works, so a service can start before the I2C
+//! device node appears, e.g. before a device tree overlay is loaded:
+//!
+//! ```
+//! use bmp280::emulator::Emulator;
+//! use bmp280::lazy::LazyDevice;
+//! use bmp280::Bmp280Builder;
+//! use std::io;
+//! use std::sync::atomic::{AtomicBool, Ordering};
+//! use std::sync::Arc;
+//!
+//! let loaded = Arc::new(AtomicBool::new(false));
+//! let device = {
+//!     let loaded = loaded.clone();
+//!     let emulator = Emulator::new();
+//!     LazyDevice::with_opener(move || {
+//!         if !loaded.load(Ordering::SeqCst) {
+//!             return Err(io::Error::new(io::ErrorKind::NotFound, "no /dev/i2c-1").into());
+//!         }
+//!         Ok(emulator.clone())
+//!     })
+//! };
+//!
+//! let mut sensor = Bmp280Builder::new().build_lazy_with(device);
+//! assert!(sensor.measure().is_err());
+//!
+//! loaded.store(true, Ordering::SeqCst);
+//! assert_eq!(sensor.measure().unwrap().temperature_celsius, 25.08);
+//! ```
+
+use crate::bus::{self, BusSettings};
+use crate::{Error, Result};
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::LinuxI2CDevice;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+type Opener<D> = Box<dyn Fn() -> Result<D> + Send + Sync>;
+
+struct Shared<D> {
+    open: Opener<D>,
+    device: Mutex<Option<D>>,
+}
+
+/// A device that is opened on its first transaction. Clones share the same device, and the
+/// transactions of each clone are not interleaved with those of the others.
+///
+/// When a transaction fails because the device node went away, the device is closed, to be opened
+/// again by the next transaction.
+pub struct LazyDevice<D = LinuxI2CDevice> {
+    shared: Arc<Shared<D>>,
+}
+
+impl<D> Clone for LazyDevice<D> {
+    fn clone(&self) -> Self {
+        LazyDevice {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl LazyDevice {
+    /// The Linux I2C device at `path` and `address`.
+    pub fn new(path: impl AsRef<Path>, address: u16) -> Self {
+        let path = path.as_ref().to_owned();
+
+        LazyDevice::with_opener(move || bus::open(&path, address, BusSettings::default()))
+    }
+}
+
+impl<D> LazyDevice<D> {
+    /// A device opened by `open`.
+    pub fn with_opener(open: impl Fn() -> Result<D> + Send + Sync + 'static) -> Self {
+        LazyDevice {
+            shared: Arc::new(Shared {
+                open: Box::new(open),
+                device: Mutex::new(None),
+            }),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.device().is_some()
+    }
+
+    fn device(&self) -> MutexGuard<'_, Option<D>> {
+        // A panic in another user of the device doesn't leave it in a state that matters here.
+        self.shared
+            .device
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<D> LazyDevice<D>
+where
+    D: I2CDevice,
+    Error: From<D::Error>,
+{
+    /// Run a transaction on the device, opening it first if needed.
+    fn with<T>(&self, f: impl FnOnce(&mut D) -> std::result::Result<T, D::Error>) -> Result<T> {
+        let mut device = self.device();
+
+        let open = match &mut *device {
+            Some(open) => open,
+            None => device.insert((self.shared.open)()?),
+        };
+
+        let result = f(open).map_err(Error::from);
+        if let Err(e) = &result {
+            if matches!(e.raw_os_error(), Some(libc::ENODEV) | Some(libc::EBADF)) {
+                *device = None;
+            }
+        }
+
+        result
+    }
+}
+
+impl<D> I2CDevice for LazyDevice<D>
+where
+    D: I2CDevice,
+    Error: From<D::Error>,
+{
+    type Error = Error;
+
+    fn read(&mut self, data: &mut [u8]) -> Result<()> {
+        self.with(|device| device.read(data))
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.with(|device| device.write(data))
+    }
+
+    fn smbus_write_quick(&mut self, bit: bool) -> Result<()> {
+        self.with(|device| device.smbus_write_quick(bit))
+    }
+
+    fn smbus_read_block_data(&mut self, register: u8) -> Result<Vec<u8>> {
+        self.with(|device| device.smbus_read_block_data(register))
+    }
+
+    fn smbus_read_i2c_block_data(&mut self, register: u8, len: u8) -> Result<Vec<u8>> {
+        self.with(|device| device.smbus_read_i2c_block_data(register, len))
+    }
+
+    fn smbus_write_block_data(&mut self, register: u8, values: &[u8]) -> Result<()> {
+        self.with(|device| device.smbus_write_block_data(register, values))
+    }
+
+    fn smbus_write_i2c_block_data(&mut self, register: u8, values: &[u8]) -> Result<()> {
+        self.with(|device| device.smbus_write_i2c_block_data(register, values))
+    }
+
+    fn smbus_process_block(&mut self, register: u8, values: &[u8]) -> Result<Vec<u8>> {
+        self.with(|device| device.smbus_process_block(register, values))
+    }
+}
+
+/// Lazily opened Linux I2C devices, one per path and address, handed out to every sensor on it.
+///
+/// ```no_run
+/// use bmp280::lazy::DevicePool;
+/// use bmp280::Bmp280Builder;
+///
+/// let pool = DevicePool::new();
+///
+/// // Both share one file descriptor for /dev/i2c-1 at address 0x77.
+/// let mut sensor = Bmp280Builder::new().build_lazy_with(pool.device("/dev/i2c-1", 0x77));
+/// let mut same = Bmp280Builder::new().build_lazy_with(pool.device("/dev/i2c-1", 0x77));
+/// ```
+#[derive(Default)]
+pub struct DevicePool {
+    devices: Mutex<HashMap<(PathBuf, u16), LazyDevice>>,
+}
+
+impl DevicePool {
+    pub fn new() -> Self {
+        DevicePool::default()
+    }
+
+    /// The device at `path` and `address`, which is opened on first use by any of the sensors it
+    /// is handed out to.
+    pub fn device(&self, path: impl AsRef<Path>, address: u16) -> LazyDevice {
+        let path = path.as_ref().to_owned();
+        let mut devices = self
+            .devices
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        devices
+            .entry((path.clone(), address))
+            .or_insert_with(|| LazyDevice::new(path, address))
+            .clone()
+    }
+
+    /// The number of distinct devices handed out.
+    pub fn len(&self) -> usize {
+        self.devices.lock().map_or(0, |devices| devices.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,264 @@
+//! MAVLink output for feeding ground stations and flight controllers.
+//!
+//! Frames are encoded as MAVLink 2 `SCALED_PRESSURE` (#29) and `ALTITUDE` (#141) messages and can
+//! be written to anything implementing `std::io::Write`, such as a serial port opened as a file.
+//! `MavlinkOutput::udp()` sends one datagram per frame, which is what ground station software
+//! listening on UDP port 14550 expects.
+//!
+//! ```ignore
+//! use bmp280::{Bmp280Builder, mavlink::MavlinkOutput};
+//! let mut sensor = Bmp280Builder::new().build().expect("Could not build device");
+//! let mut output = MavlinkOutput::udp("127.0.0.1:14550").expect("Could not open socket");
+//!
+//! output.send_sensor(&mut sensor).expect("Could not send");
+//! ```
+
+use crate::sink::Sink;
+use crate::{altitude_from_pressure, Bmp280, Error, Measurement, Result, STANDARD_SEA_LEVEL_PA};
+use i2cdev::core::I2CDevice;
+use std::io::Write;
+use std::net::ToSocketAddrs;
+use std::time::Instant;
+
+pub use crate::sink::UdpWriter;
+
+const STX_V2: u8 = 0xFD;
+
+const SCALED_PRESSURE_ID: u32 = 29;
+const SCALED_PRESSURE_CRC_EXTRA: u8 = 115;
+
+const ALTITUDE_ID: u32 = 141;
+const ALTITUDE_CRC_EXTRA: u8 = 47;
+
+/// The `SCALED_PRESSURE` message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScaledPressure {
+    /// Time since system boot in milliseconds
+    pub time_boot_ms: u32,
+    /// Absolute pressure in hPa
+    pub press_abs: f32,
+    /// Differential pressure in hPa
+    pub press_diff: f32,
+    /// Temperature in centidegrees celsius
+    pub temperature: i16,
+}
+
+/// The `ALTITUDE` message. Fields that are unknown to a barometer should be set to NaN.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Altitude {
+    /// Timestamp in microseconds
+    pub time_usec: u64,
+    /// Altitude in meters that is only guaranteed to be monotonic, initialized to AMSL
+    pub altitude_monotonic: f32,
+    /// Altitude in meters above mean sea level
+    pub altitude_amsl: f32,
+    /// Altitude in meters in the local frame
+    pub altitude_local: f32,
+    /// Altitude in meters above the home position
+    pub altitude_relative: f32,
+    /// Altitude in meters above terrain
+    pub altitude_terrain: f32,
+    /// Distance in meters to the nearest object below
+    pub bottom_clearance: f32,
+}
+
+impl ScaledPressure {
+    fn payload(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(14);
+        buf.extend_from_slice(&self.time_boot_ms.to_le_bytes());
+        buf.extend_from_slice(&self.press_abs.to_le_bytes());
+        buf.extend_from_slice(&self.press_diff.to_le_bytes());
+        buf.extend_from_slice(&self.temperature.to_le_bytes());
+        buf
+    }
+}
+
+impl Altitude {
+    fn payload(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(32);
+        buf.extend_from_slice(&self.time_usec.to_le_bytes());
+        for field in &[
+            self.altitude_monotonic,
+            self.altitude_amsl,
+            self.altitude_local,
+            self.altitude_relative,
+            self.altitude_terrain,
+            self.bottom_clearance,
+        ] {
+            buf.extend_from_slice(&field.to_le_bytes());
+        }
+        buf
+    }
+}
+
+/// Encodes MAVLink 2 frames, keeping track of the packet sequence number.
+pub struct MavlinkEncoder {
+    system_id: u8,
+    component_id: u8,
+    sequence: u8,
+}
+
+impl MavlinkEncoder {
+    pub fn new(system_id: u8, component_id: u8) -> Self {
+        MavlinkEncoder {
+            system_id,
+            component_id,
+            sequence: 0,
+        }
+    }
+
+    /// Encode a `SCALED_PRESSURE` message into a complete frame.
+    pub fn scaled_pressure(&mut self, msg: &ScaledPressure) -> Vec<u8> {
+        self.frame(SCALED_PRESSURE_ID, SCALED_PRESSURE_CRC_EXTRA, msg.payload())
+    }
+
+    /// Encode an `ALTITUDE` message into a complete frame.
+    pub fn altitude(&mut self, msg: &Altitude) -> Vec<u8> {
+        self.frame(ALTITUDE_ID, ALTITUDE_CRC_EXTRA, msg.payload())
+    }
+
+    fn frame(&mut self, msg_id: u32, crc_extra: u8, mut payload: Vec<u8>) -> Vec<u8> {
+        // MAVLink 2 truncates trailing zero bytes, but always sends at least one payload byte.
+        while payload.len() > 1 && payload.last() == Some(&0) {
+            payload.pop();
+        }
+
+        let id = msg_id.to_le_bytes();
+        let mut frame = vec![
+            STX_V2,
+            payload.len() as u8,
+            0, // incompat_flags
+            0, // compat_flags
+            self.sequence,
+            self.system_id,
+            self.component_id,
+            id[0],
+            id[1],
+            id[2],
+        ];
+        frame.extend(payload);
+
+        let mut crc = X25::new();
+        crc.accumulate(&frame[1..]);
+        crc.accumulate(&[crc_extra]);
+        frame.extend_from_slice(&crc.0.to_le_bytes());
+
+        self.sequence = self.sequence.wrapping_add(1);
+
+        frame
+    }
+}
+
+/// The CRC-16/MCRF4XX checksum used by MAVLink.
+struct X25(u16);
+
+impl X25 {
+    fn new() -> Self {
+        X25(0xFFFF)
+    }
+
+    fn accumulate(&mut self, data: &[u8]) {
+        for byte in data {
+            let mut tmp = byte ^ (self.0 & 0xFF) as u8;
+            tmp ^= tmp << 4;
+            let tmp = tmp as u16;
+            self.0 = (self.0 >> 8) ^ (tmp << 8) ^ (tmp << 3) ^ (tmp >> 4);
+        }
+    }
+}
+
+/// Sends sensor readings as MAVLink messages to a writer.
+pub struct MavlinkOutput<W: Write> {
+    writer: W,
+    encoder: MavlinkEncoder,
+    boot: Instant,
+}
+
+impl MavlinkOutput<UdpWriter> {
+    /// Send frames over UDP to the given address, e.g. `"127.0.0.1:14550"`.
+    pub fn udp(addr: impl ToSocketAddrs) -> Result<Self> {
+        Ok(MavlinkOutput::new(UdpWriter::connect(addr)?))
+    }
+}
+
+impl<W: Write> MavlinkOutput<W> {
+    /// Create an output using system ID 1 and the MAV_COMP_ID_PERIPHERAL component ID.
+    pub fn new(writer: W) -> Self {
+        MavlinkOutput::with_encoder(writer, MavlinkEncoder::new(1, 158))
+    }
+
+    pub fn with_encoder(writer: W, encoder: MavlinkEncoder) -> Self {
+        MavlinkOutput {
+            writer,
+            encoder,
+            boot: Instant::now(),
+        }
+    }
+
+    /// Send a `SCALED_PRESSURE` message.
+    pub fn send_scaled_pressure(&mut self, msg: &ScaledPressure) -> Result<()> {
+        let frame = self.encoder.scaled_pressure(msg);
+        self.writer.write_all(&frame)?;
+        Ok(())
+    }
+
+    /// Send an `ALTITUDE` message.
+    pub fn send_altitude(&mut self, msg: &Altitude) -> Result<()> {
+        let frame = self.encoder.altitude(msg);
+        self.writer.write_all(&frame)?;
+        Ok(())
+    }
+
+    /// Read the sensor and send both a `SCALED_PRESSURE` and an `ALTITUDE` message. The relative
+    /// altitude is based on the ground pressure of the sensor, the AMSL altitude on the standard
+    /// sea level pressure.
+    pub fn send_sensor<D>(&mut self, sensor: &mut Bmp280<D>) -> Result<()>
+    where
+        D: I2CDevice,
+        Error: From<D::Error>,
+    {
+        let measurement = sensor.measure()?;
+        self.send_measurement(&measurement)
+    }
+
+    /// Send both a `SCALED_PRESSURE` and an `ALTITUDE` message for a measurement, as
+    /// `.send_sensor()` does.
+    pub fn send_measurement(&mut self, measurement: &Measurement) -> Result<()> {
+        let elapsed = self.boot.elapsed();
+        let pressure_pa = measurement.pressure_kpa * 1000.;
+        let altitude_amsl = altitude_from_pressure(pressure_pa, STANDARD_SEA_LEVEL_PA);
+        let altitude_relative = measurement.altitude_m;
+
+        self.send_scaled_pressure(&ScaledPressure {
+            time_boot_ms: elapsed.as_millis() as u32,
+            press_abs: pressure_pa / 100.,
+            press_diff: 0.,
+            temperature: (measurement.temperature_celsius * 100.) as i16,
+        })?;
+
+        self.send_altitude(&Altitude {
+            time_usec: elapsed.as_micros() as u64,
+            altitude_monotonic: altitude_amsl,
+            altitude_amsl,
+            altitude_local: altitude_relative,
+            altitude_relative,
+            altitude_terrain: f32::NAN,
+            bottom_clearance: f32::NAN,
+        })?;
+
+        self.writer.flush()?;
+
+        Ok(())
+    }
+
+    /// Recover the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> Sink for MavlinkOutput<W> {
+    fn write(&mut self, measurement: &Measurement) -> Result<()> {
+        self.send_measurement(measurement)
+    }
+}
@@ -0,0 +1,35 @@
+//! Extracting the altimeter setting (QNH) from METAR weather reports, to use it as the sea level
+//! reference pressure. Built with the `metar` feature.
+//!
+//! ```
+//! let metar = "KSFO 121656Z 29012KT 10SM FEW008 17/12 A3002 RMK AO2";
+//! let qnh = bmp280::metar::altimeter_pa(metar).unwrap();
+//! assert!((qnh - 101659.).abs() < 1.);
+//! ```
+
+/// Pa per inch of mercury
+const PA_PER_INHG: f32 = 3386.389;
+
+/// The altimeter setting of a METAR report in Pa, from either an `A` group (inches of mercury, as
+/// used in North America) or a `Q` group (hPa).
+pub fn altimeter_pa(metar: &str) -> Option<f32> {
+    metar.split_whitespace().find_map(|group| {
+        let (unit, digits) = group.split_at(group.chars().next()?.len_utf8());
+
+        if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let value: f32 = digits.parse().ok()?;
+
+        match unit {
+            "A" => Some(value / 100. * PA_PER_INHG),
+            "Q" => Some(value * 100.),
+            _ => None,
+        }
+    })
+}
+
+/// Whether `station` looks like an ICAO station identifier such as `KSFO`.
+pub fn is_station_id(station: &str) -> bool {
+    station.len() == 4 && station.bytes().all(|b| b.is_ascii_alphanumeric())
+}
@@ -0,0 +1,296 @@
+//! Hooks into the bus traffic and measurements of sensors, e.g. to open `tracing` spans for them or
+//! to correlate sensor latency with system load.
+//!
+//! ```ignore
+//! use bmp280::observe::{Observer, Transaction};
+//! use bmp280::Bmp280Builder;
+//! use std::sync::Arc;
+//!
+//! struct SlowTransactions;
+//!
+//! impl Observer for SlowTransactions {
+//!     fn transaction(&self, transaction: &Transaction) {
+//!         if transaction.duration.as_millis() > 5 {
+//!             eprintln!("slow transaction: {:?}", transaction);
+//!         }
+//!     }
+//! }
+//!
+//! let mut sensor = Bmp280Builder::new()
+//!     .observer(Arc::new(SlowTransactions))
+//!     .build()
+//!     .expect("Could not build device");
+//! ```
+//!
+//! `Logger` is an observer that writes what is going on to stderr, for debugging without a
+//! logging framework:
+//!
+//! ```ignore
+//! use bmp280::observe::{Level, Logger};
+//!
+//! let mut sensor = Bmp280Builder::new()
+//!     .observer(Arc::new(Logger::new(Level::Debug)))
+//!     .build()
+//!     .expect("Could not build device");
+//! ```
+
+use crate::{Calibration, Error, Measurement};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A register read or write, including all its retries.
+#[derive(Debug)]
+pub struct Transaction<'a> {
+    /// The I2C address of the sensor
+    pub address: u16,
+    pub register: u8,
+    pub write: bool,
+    /// The number of tries, 1 if the transaction was not retried
+    pub attempts: u32,
+    pub duration: Duration,
+    /// Why the transaction failed, if it did
+    pub error: Option<&'a Error>,
+}
+
+/// Receives the events of the sensors it is set up for with `Bmp280Builder::observer()`. The
+/// methods are called on the thread using the sensor, and everything they do adds to the time the
+/// call on the sensor takes.
+pub trait Observer: Send + Sync {
+    /// Called once the sensor has been set up, by `Bmp280Builder::build()` or a recovery.
+    fn initialized(&self, _address: u16, _calibration: &Calibration) {}
+
+    /// Called after every register read or write.
+    fn transaction(&self, _transaction: &Transaction) {}
+
+    /// Called before a failed attempt at a register read or write is retried.
+    fn retrying(&self, _address: u16, _register: u8, _write: bool, _attempt: u32, _error: &Error) {}
+
+    /// Called before the sensor is reset and set up again because a measurement failed.
+    fn recovering(&self, _address: u16, _error: &Error) {}
+
+    /// Called after trying to take a new ground pressure in Pa because the old one expired at
+    /// `age`, see `expiry`.
+    fn reference_expired(
+        &self,
+        _address: u16,
+        _age: Duration,
+        _refreshed: std::result::Result<f32, &Error>,
+    ) {
+    }
+
+    /// Called after every `Bmp280::measure()`, with the time it took.
+    fn measurement(
+        &self,
+        _address: u16,
+        _duration: Duration,
+        _result: std::result::Result<&Measurement, &Error>,
+    ) {
+    }
+}
+
+/// How much `Logger` writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Setting up the sensor, recoveries and failed transactions
+    Info,
+    /// Also retries, register writes and measurements
+    Debug,
+    /// Also every register read
+    Trace,
+}
+
+/// An observer writing one line per event up to its level, to stderr by default.
+pub struct Logger<W = io::Stderr> {
+    level: Level,
+    writer: Mutex<W>,
+    /// The calibration fingerprint of every sensor address that was set up
+    fingerprints: Mutex<HashMap<u16, u32>>,
+}
+
+impl Logger {
+    pub fn new(level: Level) -> Self {
+        Logger::with_writer(level, io::stderr())
+    }
+}
+
+impl<W: Write> Logger<W> {
+    pub fn with_writer(level: Level, writer: W) -> Self {
+        Logger {
+            level,
+            writer: Mutex::new(writer),
+            fingerprints: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn log(&self, level: Level, address: u16, message: fmt::Arguments) {
+        if level > self.level {
+            return;
+        }
+
+        // A logger that can't write has nowhere to report that either.
+        if let Ok(mut writer) = self.writer.lock() {
+            let _ = writeln!(writer, "bmp280 0x{:02X}: {}", address, message);
+        }
+    }
+}
+
+impl<W: Write + Send> Observer for Logger<W> {
+    fn initialized(&self, address: u16, calibration: &Calibration) {
+        let fingerprint = calibration.fingerprint();
+        let previous = match self.fingerprints.lock() {
+            Ok(mut fingerprints) => fingerprints.insert(address, fingerprint),
+            Err(_) => None,
+        };
+
+        match previous {
+            // A different calibration after a recovery means another sensor took the place of
+            // this one, or its NVM changed.
+            Some(previous) if previous != fingerprint => self.log(
+                Level::Info,
+                address,
+                format_args!(
+                    "set up with calibration {:08x}, which changed from {:08x}",
+                    fingerprint, previous
+                ),
+            ),
+            _ => self.log(
+                Level::Info,
+                address,
+                format_args!("set up with calibration {:08x}", fingerprint),
+            ),
+        }
+    }
+
+    fn transaction(&self, transaction: &Transaction) {
+        let action = if transaction.write { "write" } else { "read" };
+        let tries = match transaction.attempts {
+            1 => String::new(),
+            attempts => format!(" after {} tries", attempts),
+        };
+
+        match transaction.error {
+            Some(error) => self.log(
+                Level::Info,
+                transaction.address,
+                format_args!(
+                    "{} 0x{:02X} failed{} in {}us: {}",
+                    action,
+                    transaction.register,
+                    tries,
+                    transaction.duration.as_micros(),
+                    Chain(error.innermost())
+                ),
+            ),
+            None => self.log(
+                if transaction.write {
+                    Level::Debug
+                } else {
+                    Level::Trace
+                },
+                transaction.address,
+                format_args!(
+                    "{} 0x{:02X} done{} in {}us",
+                    action,
+                    transaction.register,
+                    tries,
+                    transaction.duration.as_micros()
+                ),
+            ),
+        }
+    }
+
+    fn retrying(&self, address: u16, register: u8, write: bool, attempt: u32, error: &Error) {
+        self.log(
+            Level::Debug,
+            address,
+            format_args!(
+                "retrying {} 0x{:02X} after try {} failed: {}",
+                if write { "write" } else { "read" },
+                register,
+                attempt,
+                Chain(error.innermost())
+            ),
+        );
+    }
+
+    fn recovering(&self, address: u16, error: &Error) {
+        self.log(
+            Level::Info,
+            address,
+            format_args!("resetting after bus failure: {}", Chain(error)),
+        );
+    }
+
+    fn reference_expired(
+        &self,
+        address: u16,
+        age: Duration,
+        refreshed: std::result::Result<f32, &Error>,
+    ) {
+        match refreshed {
+            Ok(pressure) => self.log(
+                Level::Info,
+                address,
+                format_args!(
+                    "reference pressure expired after {}s, now {:.2} hPa",
+                    age.as_secs(),
+                    pressure / 100.
+                ),
+            ),
+            Err(error) => self.log(
+                Level::Info,
+                address,
+                format_args!(
+                    "reference pressure expired after {}s, keeping it: {}",
+                    age.as_secs(),
+                    Chain(error)
+                ),
+            ),
+        }
+    }
+
+    fn measurement(
+        &self,
+        address: u16,
+        duration: Duration,
+        result: std::result::Result<&Measurement, &Error>,
+    ) {
+        match result {
+            Ok(measurement) => self.log(
+                Level::Debug,
+                address,
+                format_args!(
+                    "measured {} kPa, {} C in {}us",
+                    measurement.pressure_kpa,
+                    measurement.temperature_celsius,
+                    duration.as_micros()
+                ),
+            ),
+            Err(error) => self.log(
+                Level::Info,
+                address,
+                format_args!("measurement failed: {}", Chain(error)),
+            ),
+        }
+    }
+}
+
+/// An error followed by all its sources, separated by colons.
+struct Chain<'a>(&'a Error);
+
+impl fmt::Display for Chain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)?;
+
+        let mut source = std::error::Error::source(self.0);
+        while let Some(e) = source {
+            write!(f, ": {}", e)?;
+            source = e.source();
+        }
+
+        Ok(())
+    }
+}
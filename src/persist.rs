@@ -0,0 +1,108 @@
+//! Calibration data in files, to set up sensors whose calibration NVM is slow or unreliable to
+//! read, and to archive the coefficients of every unit.
+//!
+//! The files are TOML with one key per calibration word, named as in the datasheet:
+//!
+//! ```text
+//! dig_T1 = 27504
+//! dig_T2 = 26435
+//! dig_T3 = -1000
+//! dig_P1 = 36477
+//! ...
+//! ```
+//!
+//! ```
+//! use bmp280::vectors::DATASHEET_CALIBRATION;
+//! use bmp280::Calibration;
+//!
+//! let toml = DATASHEET_CALIBRATION.to_toml();
+//! assert_eq!(Calibration::from_toml(&toml).unwrap(), DATASHEET_CALIBRATION);
+//! ```
+//!
+//! With `Bmp280Builder::calibration_file()`, the first setup saves the calibration and later ones
+//! use the saved one:
+//!
+//! ```
+//! use bmp280::emulator::Emulator;
+//! use bmp280::Bmp280Builder;
+//!
+//! let path = std::env::temp_dir().join(format!("bmp280-{}.toml", std::process::id()));
+//! let builder = Bmp280Builder::new().calibration_file(&path);
+//!
+//! builder.build_with(Emulator::new()).unwrap();
+//!
+//! // Unreadable NVM doesn't matter anymore.
+//! let emulator = Emulator::new();
+//! emulator.set_registers(0x88, &[0xFF; 24]);
+//! assert!(builder.build_with(emulator).is_ok());
+//!
+//! std::fs::remove_file(&path).unwrap();
+//! ```
+
+use crate::csv::{self, NAMES};
+use crate::{Calibration, Result};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+fn invalid(line: usize, message: impl Into<String>) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line, message.into()),
+    )
+}
+
+impl Calibration {
+    /// The calibration as a TOML document.
+    pub fn to_toml(&self) -> String {
+        let mut toml = String::new();
+        for (name, value) in NAMES.iter().zip(csv::values(self).iter()) {
+            // Writing to a String can't fail.
+            let _ = writeln!(toml, "{} = {}", name, value);
+        }
+        toml
+    }
+
+    /// Read a calibration written by `.to_toml()`. Comments and unknown keys are ignored.
+    pub fn from_toml(toml: &str) -> io::Result<Self> {
+        let mut values = [None; 12];
+
+        for (i, line) in toml.lines().enumerate() {
+            let number = i + 1;
+            let line = match line.split_once('#') {
+                Some((line, _)) => line.trim(),
+                None => line.trim(),
+            };
+            if line.is_empty() || line.starts_with('[') {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| invalid(number, "expected key = value"))?;
+            let key = key.trim();
+
+            if let Some(index) = NAMES.iter().position(|&name| name == key) {
+                let value = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid(number, format!("invalid value for {}", key)))?;
+                values[index] = Some(value);
+            }
+        }
+
+        csv::from_values(&values)
+            .map_err(|message| io::Error::new(io::ErrorKind::InvalidData, message))
+    }
+
+    /// Write the calibration to a TOML file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        Ok(fs::write(path, self.to_toml())?)
+    }
+
+    /// Read a calibration from a file written by `.save()`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Calibration::from_toml(&fs::read_to_string(path)?)?)
+    }
+}
@@ -0,0 +1,29 @@
+//! The types and traits most programs using the sensor need, for a single import:
+//!
+//! ```
+//! use bmp280::emulator::Emulator;
+//! use bmp280::prelude::*;
+//!
+//! let mut sensor = Bmp280Builder::new()
+//!     .oversampling(Oversampling::X2, Oversampling::X16)
+//!     .filter(Filter::X4)
+//!     .build_with(Emulator::new())
+//!     .expect("Could not build device");
+//!
+//! let measurement: Measurement = sensor.measure().unwrap();
+//! assert_eq!(measurement.temperature_celsius, 25.08);
+//! ```
+//!
+//! The crate's `Result` is left out, so it doesn't shadow the one of the standard library.
+
+pub use crate::config::{Config, Filter, Mode, Oversampling, Standby};
+pub use crate::observe::Observer;
+pub use crate::retry::RetryPolicy;
+pub use crate::sampler::{Readings, Sampler, Source};
+#[cfg(feature = "sensor-traits")]
+pub use crate::traits::{PressureSensor, TemperatureSensor};
+pub use crate::validate::Validation;
+pub use crate::{
+    Bmp280, Bmp280Builder, Calibration, Error, Measurement, RawMeasurement, ReferencePressure,
+};
+pub use i2cdev::core::I2CDevice;
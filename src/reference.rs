@@ -0,0 +1,68 @@
+//! The pressure altitudes are relative to, in whatever unit it comes in.
+
+use crate::STANDARD_SEA_LEVEL_PA;
+use std::fmt;
+
+/// Pa per inch of mercury, the unit of the altimeter setting in the US.
+const PA_PER_INHG: f32 = 3386.389;
+
+/// A reference pressure for altitudes, e.g. the pressure on the ground from `Bmp280::zero()` or
+/// the altimeter setting of the nearest airport. It is made from and read back in an explicit unit,
+/// so a pressure in kPa can't be taken for one in Pa.
+///
+/// ```
+/// use bmp280::ReferencePressure;
+///
+/// let qnh = ReferencePressure::from_inhg(29.92);
+/// assert!((qnh.hpa() - 1013.2).abs() < 0.1);
+/// assert_eq!(ReferencePressure::from_kpa(101.325), ReferencePressure::STANDARD_SEA_LEVEL);
+/// assert_eq!(ReferencePressure::from_hpa(1013.25).to_string(), "1013.25 hPa");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct ReferencePressure {
+    pa: f32,
+}
+
+impl ReferencePressure {
+    /// The standard atmosphere pressure at sea level, for altitudes above sea level.
+    pub const STANDARD_SEA_LEVEL: ReferencePressure =
+        ReferencePressure::from_pa(STANDARD_SEA_LEVEL_PA);
+
+    pub const fn from_pa(pa: f32) -> Self {
+        ReferencePressure { pa }
+    }
+
+    pub fn from_hpa(hpa: f32) -> Self {
+        ReferencePressure::from_pa(hpa * 100.)
+    }
+
+    pub fn from_kpa(kpa: f32) -> Self {
+        ReferencePressure::from_pa(kpa * 1000.)
+    }
+
+    pub fn from_inhg(inhg: f32) -> Self {
+        ReferencePressure::from_pa(inhg * PA_PER_INHG)
+    }
+
+    pub const fn pa(self) -> f32 {
+        self.pa
+    }
+
+    pub fn hpa(self) -> f32 {
+        self.pa / 100.
+    }
+
+    pub fn kpa(self) -> f32 {
+        self.pa / 1000.
+    }
+
+    pub fn inhg(self) -> f32 {
+        self.pa / PA_PER_INHG
+    }
+}
+
+impl fmt::Display for ReferencePressure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.2} hPa", self.hpa())
+    }
+}
@@ -0,0 +1,254 @@
+//! The register map of the sensor, and decoding the values read from its data registers, for
+//! diagnostics tools that talk to the sensor directly.
+//!
+//! The addresses are those of the memory map in the datasheet:
+//!
+//! ```
+//! use bmp280::registers::Register;
+//!
+//! let map: Vec<(&str, u8)> = Register::ALL.iter().map(|r| (r.name(), r.address())).collect();
+//! assert_eq!(
+//!     map,
+//!     [
+//!         ("dig_T1", 0x88), ("dig_T2", 0x8A), ("dig_T3", 0x8C),
+//!         ("dig_P1", 0x8E), ("dig_P2", 0x90), ("dig_P3", 0x92), ("dig_P4", 0x94),
+//!         ("dig_P5", 0x96), ("dig_P6", 0x98), ("dig_P7", 0x9A), ("dig_P8", 0x9C),
+//!         ("dig_P9", 0x9E),
+//!         ("id", 0xD0), ("version", 0xD1), ("reset", 0xE0), ("calib26", 0xE1),
+//!         ("status", 0xF3), ("ctrl_meas", 0xF4), ("config", 0xF5),
+//!         ("press_msb", 0xF7), ("temp_msb", 0xFA),
+//!     ]
+//! );
+//!
+//! // The calibration words are consecutive, 2 bytes each.
+//! assert!(Register::ALL[..12].windows(2).all(|w| w[1].address() == w[0].address() + 2));
+//! ```
+
+use std::ops::RangeInclusive;
+
+/// The value of the chip ID register of a BMP280
+pub const CHIP_ID: u8 = 0x58;
+
+/// The values the 20 bit ADC can produce.
+pub(crate) const ADC_RANGE: RangeInclusive<i32> = 0..=0xF_FFFF;
+
+/// The value written to the reset register to reset the sensor.
+pub const SOFT_RESET: u8 = 0xB6;
+
+/// All of the registers for the BMP280, by the address of their first byte.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Register {
+    DigT1 = 0x88,
+    DigT2 = 0x8A,
+    DigT3 = 0x8C,
+
+    DigP1 = 0x8E,
+    DigP2 = 0x90,
+    DigP3 = 0x92,
+    DigP4 = 0x94,
+    DigP5 = 0x96,
+    DigP6 = 0x98,
+    DigP7 = 0x9A,
+    DigP8 = 0x9C,
+    DigP9 = 0x9E,
+
+    ChipId = 0xD0,
+    Version = 0xD1,
+    SoftReset = 0xE0,
+
+    /// R calibration stored in 0xE1-0xF0
+    Cal26 = 0xE1,
+
+    Status = 0xF3,
+    Control = 0xF4,
+    Config = 0xF5,
+    PressureData = 0xF7,
+    TemperatureData = 0xFA,
+}
+
+impl Register {
+    /// Every register, in the order of their addresses.
+    pub const ALL: [Register; 21] = [
+        Register::DigT1,
+        Register::DigT2,
+        Register::DigT3,
+        Register::DigP1,
+        Register::DigP2,
+        Register::DigP3,
+        Register::DigP4,
+        Register::DigP5,
+        Register::DigP6,
+        Register::DigP7,
+        Register::DigP8,
+        Register::DigP9,
+        Register::ChipId,
+        Register::Version,
+        Register::SoftReset,
+        Register::Cal26,
+        Register::Status,
+        Register::Control,
+        Register::Config,
+        Register::PressureData,
+        Register::TemperatureData,
+    ];
+
+    /// The address of the first byte of the register.
+    pub const fn address(self) -> u8 {
+        self as u8
+    }
+
+    /// The name of the register in the memory map of the datasheet, that of its first byte for
+    /// the ones spanning several.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Register::DigT1 => "dig_T1",
+            Register::DigT2 => "dig_T2",
+            Register::DigT3 => "dig_T3",
+            Register::DigP1 => "dig_P1",
+            Register::DigP2 => "dig_P2",
+            Register::DigP3 => "dig_P3",
+            Register::DigP4 => "dig_P4",
+            Register::DigP5 => "dig_P5",
+            Register::DigP6 => "dig_P6",
+            Register::DigP7 => "dig_P7",
+            Register::DigP8 => "dig_P8",
+            Register::DigP9 => "dig_P9",
+            Register::ChipId => "id",
+            Register::Version => "version",
+            Register::SoftReset => "reset",
+            Register::Cal26 => "calib26",
+            Register::Status => "status",
+            Register::Control => "ctrl_meas",
+            Register::Config => "config",
+            Register::PressureData => "press_msb",
+            Register::TemperatureData => "temp_msb",
+        }
+    }
+}
+
+impl<'a> From<&'a Register> for u8 {
+    fn from(register: &'a Register) -> u8 {
+        register.address()
+    }
+}
+
+impl From<Register> for u8 {
+    fn from(register: Register) -> u8 {
+        register.address()
+    }
+}
+
+/// The uncompensated 20 bit ADC values of a measurement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawMeasurement {
+    pub adc_t: i32,
+    pub adc_p: i32,
+}
+
+impl RawMeasurement {
+    /// Decodes the 6 data registers from `press_msb` at 0xF7 to `temp_xlsb` at 0xFC, as read in a
+    /// single burst, straight from the read buffer.
+    ///
+    /// ```
+    /// use bmp280::RawMeasurement;
+    ///
+    /// let raw = RawMeasurement::from_data_registers(&[0x65, 0x5A, 0xC0, 0x7E, 0xED, 0x00]);
+    /// assert_eq!(raw, RawMeasurement { adc_t: 519888, adc_p: 415148 });
+    /// ```
+    pub fn from_data_registers(data: &[u8; 6]) -> Self {
+        let [press_msb, press_lsb, press_xlsb, temp_msb, temp_lsb, temp_xlsb] = *data;
+
+        RawMeasurement {
+            adc_p: adc_value([press_msb, press_lsb, press_xlsb]),
+            adc_t: adc_value([temp_msb, temp_lsb, temp_xlsb]),
+        }
+    }
+}
+
+/// The 20 bit ADC value of a data register: the `msb`, `lsb` and `xlsb` bytes, of which only the
+/// upper 4 bits of `xlsb` belong to the value.
+pub(crate) fn adc_value([msb, lsb, xlsb]: [u8; 3]) -> i32 {
+    (i32::from(msb) << 12) | (i32::from(lsb) << 4) | (i32::from(xlsb) >> 4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The memory map of section 4.2 of the datasheet: the name, address and width in bytes of
+    /// every register the driver uses.
+    const DATASHEET: [(&str, u8, usize); 21] = [
+        ("dig_T1", 0x88, 2),
+        ("dig_T2", 0x8A, 2),
+        ("dig_T3", 0x8C, 2),
+        ("dig_P1", 0x8E, 2),
+        ("dig_P2", 0x90, 2),
+        ("dig_P3", 0x92, 2),
+        ("dig_P4", 0x94, 2),
+        ("dig_P5", 0x96, 2),
+        ("dig_P6", 0x98, 2),
+        ("dig_P7", 0x9A, 2),
+        ("dig_P8", 0x9C, 2),
+        ("dig_P9", 0x9E, 2),
+        ("id", 0xD0, 1),
+        ("version", 0xD1, 1),
+        ("reset", 0xE0, 1),
+        ("calib26", 0xE1, 1),
+        ("status", 0xF3, 1),
+        ("ctrl_meas", 0xF4, 1),
+        ("config", 0xF5, 1),
+        ("press_msb", 0xF7, 3),
+        ("temp_msb", 0xFA, 3),
+    ];
+
+    #[test]
+    fn addresses_match_the_datasheet() {
+        for (register, &(name, address, _)) in Register::ALL.iter().zip(&DATASHEET) {
+            assert_eq!(register.name(), name);
+            assert_eq!(register.address(), address, "{}", name);
+            assert_eq!(u8::from(register), address, "{}", name);
+            assert_eq!(u8::from(*register), address, "{}", name);
+        }
+    }
+
+    #[test]
+    fn registers_do_not_overlap() {
+        for (first, second) in DATASHEET.iter().zip(&DATASHEET[1..]) {
+            let (name, address, width) = *first;
+            assert!(
+                usize::from(address) + width <= usize::from(second.1),
+                "{} overlaps {}",
+                name,
+                second.0
+            );
+        }
+    }
+
+    #[test]
+    fn data_registers_are_consecutive() {
+        // The burst read of both ADC values relies on this.
+        let temperature = usize::from(Register::TemperatureData.address());
+        assert_eq!(
+            usize::from(Register::PressureData.address()) + 3,
+            temperature
+        );
+        assert_eq!(temperature + 3, 0xFD);
+    }
+
+    #[test]
+    fn constants_match_the_datasheet() {
+        assert_eq!(CHIP_ID, 0x58);
+        assert_eq!(SOFT_RESET, 0xB6);
+        assert_eq!(ADC_RANGE, 0..=(1 << 20) - 1);
+    }
+
+    #[test]
+    fn adc_values_are_the_upper_20_bits() {
+        assert_eq!(adc_value([0xFF, 0xFF, 0xF0]), *ADC_RANGE.end());
+        assert_eq!(adc_value([0x80, 0x00, 0x00]), 0x80000);
+        // The lower nibble of xlsb is not part of the value
+        assert_eq!(adc_value([0x00, 0x00, 0x1F]), 1);
+        assert_eq!(adc_value([0x12, 0x34, 0x56]), 0x12345);
+    }
+}
@@ -0,0 +1,79 @@
+//! Retrying failed I2C transactions, for buses where single transactions are unreliable because
+//! of EMI or long cables.
+//!
+//! ```ignore
+//! use bmp280::retry::RetryPolicy;
+//! use bmp280::Bmp280Builder;
+//! use std::time::Duration;
+//!
+//! let mut sensor = Bmp280Builder::new()
+//!     .retry_policy(RetryPolicy {
+//!         attempts: 5,
+//!         backoff: Duration::from_millis(2),
+//!         ..RetryPolicy::default()
+//!     })
+//!     .build()
+//!     .expect("Could not build device");
+//! ```
+
+use crate::Error;
+use std::time::Duration;
+
+/// How often and how fast a failed register read or write is tried again.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// The number of times a transaction is tried, including the first try. 1 disables retries.
+    pub attempts: u32,
+    /// The time to wait before the first retry
+    pub backoff: Duration,
+    /// The wait time is multiplied by this after every retry
+    pub multiplier: u32,
+    /// Whether a failed transaction is worth retrying
+    pub retryable: fn(&Error) -> bool,
+}
+
+impl Default for RetryPolicy {
+    /// Transactions are not retried.
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 1,
+            backoff: Duration::from_millis(1),
+            multiplier: 2,
+            retryable: is_transient,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Try each transaction up to `attempts` times, retrying transient errors after 1ms, 2ms, 4ms
+    /// and so on.
+    pub fn new(attempts: u32) -> Self {
+        RetryPolicy {
+            attempts,
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// The time to wait before the given retry, starting at 1.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = self.multiplier.saturating_pow(retry.saturating_sub(1));
+
+        self.backoff.saturating_mul(factor)
+    }
+}
+
+/// Whether the error is one a marginal bus produces now and then: a missing acknowledge, lost
+/// arbitration, or a generic bus error or timeout of the adapter.
+pub fn is_transient(error: &Error) -> bool {
+    match error.raw_os_error() {
+        Some(errno) => [
+            libc::EIO,
+            libc::EAGAIN,
+            libc::ETIMEDOUT,
+            libc::EREMOTEIO,
+            libc::ENXIO,
+        ]
+        .contains(&errno),
+        None => false,
+    }
+}
@@ -0,0 +1,285 @@
+//! Periodic sampling of a sensor, or of anything else implementing `Source` such as a
+//! `SensorArray`.
+//!
+//! ```ignore
+//! use bmp280::{Bmp280Builder, sampler::Sampler};
+//! use std::time::Duration;
+//!
+//! let mut sensor = Bmp280Builder::new().build().expect("Could not build device");
+//!
+//! for measurement in Sampler::new(&mut sensor).interval(Duration::from_millis(250)).limit(10) {
+//!     println!("{:?}", measurement);
+//! }
+//! ```
+//!
+//! Every sample gets the next number, starting at 0, in `Measurement::sequence`, so consumers at
+//! the end of a lossy transport like UDP can tell when samples went missing. The readings of a
+//! `SensorArray` share the number of their sample. A failed reading uses up its number too.
+//!
+//! ```
+//! use bmp280::emulator::Emulator;
+//! use bmp280::{sampler::Sampler, Bmp280Builder};
+//! use std::time::Duration;
+//!
+//! let mut sensor = Bmp280Builder::new()
+//!     .build_with(Emulator::new())
+//!     .expect("Could not build device");
+//!
+//! let sequence: Vec<Option<u64>> = Sampler::new(&mut sensor)
+//!     .interval(Duration::from_millis(1))
+//!     .limit(3)
+//!     .map(|measurement| measurement.unwrap().sequence)
+//!     .collect();
+//! assert_eq!(sequence, [Some(0), Some(1), Some(2)]);
+//! ```
+//!
+//! Hooks can be added for side effects on every reading, wherever the samples end up:
+//!
+//! ```ignore
+//! let mut failures = 0;
+//! let mut sampler = Sampler::new(&mut array);
+//! sampler
+//!     .on_measurement(|label, measurement| blink_led(label))
+//!     .on_error(|label, error| failures += 1);
+//! ```
+
+use crate::{Bmp280, Error, Measurement, Result};
+use i2cdev::core::I2CDevice;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Something the sampler can take samples from.
+pub trait Source {
+    type Output;
+
+    fn sample(&mut self) -> Self::Output;
+
+    /// Take the sample the sampler numbers `sequence`, setting `Measurement::sequence` on its
+    /// readings. Sources that don't number their readings take a plain sample.
+    fn sample_numbered(&mut self, sequence: u64) -> Self::Output {
+        let _ = sequence;
+        self.sample()
+    }
+}
+
+impl<D> Source for Bmp280<D>
+where
+    D: I2CDevice,
+    Error: From<D::Error>,
+{
+    type Output = Result<Measurement>;
+
+    fn sample(&mut self) -> Self::Output {
+        self.measure()
+    }
+
+    fn sample_numbered(&mut self, sequence: u64) -> Self::Output {
+        let mut measurement = self.measure()?;
+        measurement.sequence = Some(sequence);
+        Ok(measurement)
+    }
+}
+
+/// Sample outputs that are made up of readings, so measurement and error hooks can look at them.
+pub trait Readings {
+    /// Call `f` with the label and result of every reading, the label being empty for a single
+    /// sensor.
+    fn for_each_reading(&self, f: &mut dyn FnMut(&str, &Result<Measurement>));
+}
+
+impl Readings for Result<Measurement> {
+    fn for_each_reading(&self, f: &mut dyn FnMut(&str, &Result<Measurement>)) {
+        f("", self)
+    }
+}
+
+impl Readings for (String, Result<Measurement>) {
+    fn for_each_reading(&self, f: &mut dyn FnMut(&str, &Result<Measurement>)) {
+        f(&self.0, &self.1)
+    }
+}
+
+impl<T: Readings> Readings for Vec<T> {
+    fn for_each_reading(&self, f: &mut dyn FnMut(&str, &Result<Measurement>)) {
+        for readings in self {
+            readings.for_each_reading(f);
+        }
+    }
+}
+
+impl<T: Readings> Readings for Option<T> {
+    fn for_each_reading(&self, f: &mut dyn FnMut(&str, &Result<Measurement>)) {
+        if let Some(readings) = self {
+            readings.for_each_reading(f);
+        }
+    }
+}
+
+type Hook<'a, T> = Box<dyn FnMut(&T) + 'a>;
+
+/// The time between samples unless set with `Sampler::interval()`.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The longest a sampler will sleep at once, so it notices a stop request in time.
+const MAX_SLEEP: Duration = Duration::from_millis(50);
+
+/// An iterator that takes a sample every interval until a count or duration limit is hit, or until
+/// it is asked to stop. Failed measurements are yielded as errors and do not end sampling.
+pub struct Sampler<'a, S: Source = Bmp280> {
+    source: &'a mut S,
+    interval: Duration,
+    count: Option<u64>,
+    duration: Option<Duration>,
+    stop: Option<&'a AtomicBool>,
+    started: Option<Instant>,
+    next: Option<Instant>,
+    taken: u64,
+    hooks: Vec<Hook<'a, S::Output>>,
+}
+
+impl<'a, S: Source> Sampler<'a, S> {
+    pub fn new(source: &'a mut S) -> Self {
+        Sampler {
+            source,
+            interval: DEFAULT_INTERVAL,
+            count: None,
+            duration: None,
+            stop: None,
+            started: None,
+            next: None,
+            taken: 0,
+            hooks: Vec::new(),
+        }
+    }
+
+    /// Set the time between the start of two samples. Defaults to 250ms.
+    pub fn interval(&mut self, interval: Duration) -> &mut Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Stop after this many samples.
+    pub fn limit(&mut self, count: u64) -> &mut Self {
+        self.count = Some(count);
+        self
+    }
+
+    /// Stop once this much time has passed since the first sample.
+    pub fn duration(&mut self, duration: Duration) -> &mut Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Stop as soon as the flag is set, e.g. from a signal handler.
+    pub fn stop_flag(&mut self, stop: &'a AtomicBool) -> &mut Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    /// Call `hook` with every sample before it is yielded.
+    pub fn on_sample(&mut self, hook: impl FnMut(&S::Output) + 'a) -> &mut Self {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    /// The number of samples taken so far.
+    pub fn taken(&self) -> u64 {
+        self.taken
+    }
+
+    fn stopped(&self) -> bool {
+        self.stop.is_some_and(|stop| stop.load(Ordering::SeqCst))
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        match (self.started, self.duration) {
+            (Some(started), Some(duration)) => Some(started + duration),
+            _ => None,
+        }
+    }
+
+    /// Sleep until `until`, returning false if sampling should end instead.
+    fn wait(&self, until: Instant) -> bool {
+        loop {
+            if self.stopped() {
+                return false;
+            }
+
+            let now = Instant::now();
+            if let Some(deadline) = self.deadline() {
+                if now >= deadline {
+                    return false;
+                }
+            }
+            if now >= until {
+                return true;
+            }
+
+            thread::sleep((until - now).min(MAX_SLEEP));
+        }
+    }
+}
+
+impl<'a, S: Source> Sampler<'a, S>
+where
+    S::Output: Readings,
+{
+    /// Call `hook` with the label and value of every successful reading.
+    pub fn on_measurement(&mut self, mut hook: impl FnMut(&str, &Measurement) + 'a) -> &mut Self {
+        self.on_sample(move |sample: &S::Output| {
+            sample.for_each_reading(&mut |label, reading| {
+                if let Ok(measurement) = reading {
+                    hook(label, measurement);
+                }
+            })
+        })
+    }
+
+    /// Call `hook` with the label and error of every failed reading.
+    pub fn on_error(&mut self, mut hook: impl FnMut(&str, &Error) + 'a) -> &mut Self {
+        self.on_sample(move |sample: &S::Output| {
+            sample.for_each_reading(&mut |label, reading| {
+                if let Err(error) = reading {
+                    hook(label, error);
+                }
+            })
+        })
+    }
+}
+
+impl<'a, S: Source> Iterator for Sampler<'a, S> {
+    type Item = S::Output;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.count.is_some_and(|count| self.taken >= count) {
+            return None;
+        }
+
+        match self.next {
+            Some(next) => {
+                if !self.wait(next) {
+                    return None;
+                }
+            }
+            None => {
+                if self.stopped() {
+                    return None;
+                }
+                self.started = Some(Instant::now());
+            }
+        }
+
+        let scheduled = self.next.unwrap_or_else(Instant::now);
+        let sample = self.source.sample_numbered(self.taken);
+        self.taken += 1;
+        for hook in &mut self.hooks {
+            hook(&sample);
+        }
+
+        // Keep a fixed rate, but don't try to catch up if a sample took too long.
+        self.next = Some((scheduled + self.interval).max(Instant::now()));
+
+        Some(sample)
+    }
+}
@@ -0,0 +1,104 @@
+//! A self-test for production line bring-up.
+//!
+//! ```ignore
+//! use bmp280::{selftest, Bmp280Builder};
+//!
+//! let mut sensor = Bmp280Builder::new().build().expect("Could not build device");
+//! let report = selftest::run(&mut sensor);
+//!
+//! print!("{}", report);
+//! assert!(report.passed());
+//! ```
+
+use crate::field;
+use crate::{Bmp280, Error, CHIP_ID, PRESSURE_RANGE_PA, TEMPERATURE_RANGE_CELSIUS};
+use i2cdev::core::I2CDevice;
+use std::fmt;
+
+/// The result of a single check.
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// The results of all checks, printed one check per line by `Display`.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// Whether all checks passed.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    /// Add a check to the report.
+    pub fn check(&mut self, name: &'static str, passed: bool, detail: impl Into<String>) {
+        self.checks.push(Check {
+            name,
+            passed,
+            detail: detail.into(),
+        });
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for check in &self.checks {
+            let result = if check.passed { "PASS" } else { "FAIL" };
+            writeln!(f, "{} {:<12} {}", result, check.name, check.detail)?;
+        }
+
+        let result = if self.passed() { "PASS" } else { "FAIL" };
+        writeln!(f, "{}", result)
+    }
+}
+
+/// Check the chip ID, the plausibility of the calibration data, and whether a forced measurement
+/// is within the operating range of the sensor. Errors are reported as failed checks.
+pub fn run<D>(sensor: &mut Bmp280<D>) -> Report
+where
+    D: I2CDevice,
+    Error: From<D::Error>,
+{
+    let mut report = Report::default();
+
+    match sensor.read(field::CHIP_ID) {
+        Ok(id) => report.check("chip id", id == CHIP_ID, format!("0x{:02X}", id)),
+        Err(e) => report.check("chip id", false, e.to_string()),
+    }
+
+    let cal = &sensor.calibration;
+    report.check(
+        "calibration",
+        cal.is_valid(),
+        format!(
+            "dig_T1={} dig_P1={} fingerprint {:08x}",
+            cal.dig_t1,
+            cal.dig_p1,
+            cal.fingerprint()
+        ),
+    );
+
+    match sensor.forced_measurement() {
+        Ok(measurement) => {
+            let pressure_pa = measurement.pressure_kpa * 1000.;
+            report.check(
+                "pressure",
+                PRESSURE_RANGE_PA.contains(&pressure_pa),
+                format!("{:.2} hPa", pressure_pa / 100.),
+            );
+            report.check(
+                "temperature",
+                TEMPERATURE_RANGE_CELSIUS.contains(&measurement.temperature_celsius),
+                format!("{:.2} C", measurement.temperature_celsius),
+            );
+        }
+        Err(e) => report.check("measurement", false, e.to_string()),
+    }
+
+    report
+}
@@ -0,0 +1,978 @@
+//! Outputs that measurements are written to, one at a time, such as a CSV file, an MQTT broker or
+//! an InfluxDB server.
+//!
+//! Every output implements `Sink`, so programs can write to any set of them, including their own:
+//!
+//! ```
+//! use bmp280::emulator::Emulator;
+//! use bmp280::sink::{CsvSink, JsonSink, Sink, Sinks};
+//! use bmp280::{Bmp280Builder, Measurement};
+//!
+//! /// Counts the readings instead of storing them.
+//! struct Counter(u32);
+//!
+//! impl Sink for Counter {
+//!     fn write(&mut self, _: &Measurement) -> bmp280::Result<()> {
+//!         self.0 += 1;
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut sensor = Bmp280Builder::new()
+//!     .label("outdoor")
+//!     .build_with(Emulator::new())
+//!     .expect("Could not build device");
+//!
+//! let mut csv = CsvSink::new(Vec::new());
+//! let measurement = sensor.measure().unwrap();
+//! csv.write(&measurement).unwrap();
+//! assert_eq!(
+//!     String::from_utf8(csv.into_inner()).unwrap(),
+//!     "label,pressure_kpa,altitude_m,temperature_celsius\noutdoor,100.65325,NaN,25.08\n"
+//! );
+//!
+//! let mut sinks = Sinks::new();
+//! sinks.push(JsonSink::new(std::io::sink())).push(Counter(0));
+//! sinks.write(&measurement).unwrap();
+//! sinks.close().unwrap();
+//! ```
+//!
+//! Every sink has a `transform` deciding the units, rounding and fields it writes, see the
+//! `transform` module.
+//!
+//! The sinks talking to the network, `InfluxSink` over UDP and `MqttSink`, are built with the
+//! `sinks` feature, as is `open()`, which makes a sink from a spec like
+//! `mqtt=broker:1883/weather/outdoor` and is how `bmp280 serve --sink` composes its outputs.
+
+#[cfg(feature = "sinks")]
+use crate::buffer::Buffering;
+use crate::csv;
+use crate::info::json_string;
+#[cfg(feature = "sinks")]
+use crate::transform::Value;
+use crate::transform::{Field, PressureUnit, Record, Transform};
+use crate::validate::Implausibility;
+#[cfg(feature = "sinks")]
+use crate::Error;
+use crate::{Measurement, Result};
+use std::fs::{self, File, OpenOptions};
+#[cfg(feature = "sinks")]
+use std::io::Read;
+use std::io::{self, Write};
+#[cfg(feature = "sinks")]
+use std::net::SocketAddr;
+#[cfg(feature = "sinks")]
+use std::net::TcpStream;
+#[cfg(any(feature = "sinks", feature = "mavlink"))]
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+#[cfg(feature = "sinks")]
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Something measurements are written to.
+pub trait Sink {
+    fn write(&mut self, measurement: &Measurement) -> Result<()>;
+
+    /// Push out what was written so far, for sinks that buffer.
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Flush and shut down the sink, e.g. disconnecting from a server. It isn't written to
+    /// afterwards.
+    fn close(&mut self) -> Result<()> {
+        self.flush()
+    }
+}
+
+impl<S: Sink + ?Sized> Sink for Box<S> {
+    fn write(&mut self, measurement: &Measurement) -> Result<()> {
+        (**self).write(measurement)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
+
+    fn close(&mut self) -> Result<()> {
+        (**self).close()
+    }
+}
+
+/// Several sinks written together. A failing sink doesn't keep the measurement from the others;
+/// the first error is returned once all of them were written.
+#[derive(Default)]
+pub struct Sinks {
+    sinks: Vec<Box<dyn Sink + Send>>,
+}
+
+impl Sinks {
+    pub fn new() -> Self {
+        Sinks::default()
+    }
+
+    pub fn push(&mut self, sink: impl Sink + Send + 'static) -> &mut Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.sinks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    fn each(&mut self, mut f: impl FnMut(&mut dyn Sink) -> Result<()>) -> Result<()> {
+        let mut result = Ok(());
+        for sink in &mut self.sinks {
+            let written = f(sink.as_mut());
+            if result.is_ok() {
+                result = written;
+            }
+        }
+        result
+    }
+}
+
+impl Sink for Sinks {
+    fn write(&mut self, measurement: &Measurement) -> Result<()> {
+        self.each(|sink| sink.write(measurement))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.each(|sink| sink.flush())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.each(|sink| sink.close())
+    }
+}
+
+/// How a `Downsampled` sink reduces the measurements it gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Downsample {
+    /// Keep the first of every N measurements
+    Decimate(u32),
+    /// Write the mean of every N measurements, which also evens out the noise
+    Average(u32),
+}
+
+/// The measurements of a sensor not written yet by a `Downsampled` sink.
+struct Window {
+    label: Option<Arc<str>>,
+    count: u32,
+    /// The sums of the pressure, altitude and temperature
+    sums: [f64; 3],
+    implausible: Option<Implausibility>,
+}
+
+/// A sink that gets only some of the measurements, or their averages, so the sensor can be sampled
+/// fast for filtering or other outputs while the sink keeps a clean low rate log:
+///
+/// ```
+/// use bmp280::emulator::Emulator;
+/// use bmp280::sampler::Sampler;
+/// use bmp280::sink::{Downsample, Downsampled, JsonSink, Sink};
+/// use bmp280::transform::Field;
+/// use bmp280::Bmp280Builder;
+/// use std::time::Duration;
+///
+/// let mut sensor = Bmp280Builder::new()
+///     .build_with(Emulator::new())
+///     .expect("Could not build device");
+///
+/// let mut json = JsonSink::new(Vec::new());
+/// json.transform.fields = vec![Field::Sequence];
+/// let mut sink = Downsampled::new(json, Downsample::Decimate(4));
+///
+/// let mut sampler = Sampler::new(&mut sensor);
+/// sampler.interval(Duration::from_millis(1)).limit(10);
+/// for measurement in sampler {
+///     sink.write(&measurement.unwrap()).unwrap();
+/// }
+/// assert_eq!(
+///     String::from_utf8(sink.into_inner().into_inner()).unwrap(),
+///     "{\"sequence\":0}\n{\"sequence\":4}\n{\"sequence\":8}\n"
+/// );
+/// ```
+///
+/// The measurements of every label are downsampled on their own, so the readings of a sensor array
+/// aren't mixed up.
+pub struct Downsampled<S: Sink> {
+    sink: S,
+    downsample: Downsample,
+    windows: Vec<Window>,
+}
+
+impl<S: Sink> Downsampled<S> {
+    pub fn new(sink: S, downsample: Downsample) -> Self {
+        Downsampled {
+            sink,
+            downsample,
+            windows: Vec::new(),
+        }
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    pub fn into_inner(self) -> S {
+        self.sink
+    }
+
+    fn window(&mut self, label: &Option<Arc<str>>) -> &mut Window {
+        let index = match self.windows.iter().position(|w| &w.label == label) {
+            Some(index) => index,
+            None => {
+                self.windows.push(Window {
+                    label: label.clone(),
+                    count: 0,
+                    sums: [0.; 3],
+                    implausible: None,
+                });
+                self.windows.len() - 1
+            }
+        };
+        &mut self.windows[index]
+    }
+}
+
+/// The mean of the measurements in `window`, with the other fields of `last`, emptying the window.
+fn average(window: &mut Window, last: &Measurement) -> Measurement {
+    let count = f64::from(window.count);
+    let mut average = last.clone();
+    average.pressure_kpa = (window.sums[0] / count) as f32;
+    average.altitude_m = (window.sums[1] / count) as f32;
+    average.temperature_celsius = (window.sums[2] / count) as f32;
+    average.implausible = window.implausible.take();
+
+    window.count = 0;
+    window.sums = [0.; 3];
+    average
+}
+
+impl<S: Sink> Sink for Downsampled<S> {
+    /// Written to the sink if it is the first of its window, or the average of a full window. The
+    /// average has the other fields, such as the sequence number and raw values, of the last
+    /// measurement of the window, and is implausible like the first implausible one.
+    fn write(&mut self, measurement: &Measurement) -> Result<()> {
+        let downsample = self.downsample;
+        let window = self.window(&measurement.label);
+        window.count += 1;
+
+        match downsample {
+            Downsample::Decimate(every) => {
+                let first = window.count == 1;
+                if window.count >= every {
+                    window.count = 0;
+                }
+                if first {
+                    return self.sink.write(measurement);
+                }
+            }
+            Downsample::Average(count) => {
+                window.sums[0] += f64::from(measurement.pressure_kpa);
+                window.sums[1] += f64::from(measurement.altitude_m);
+                window.sums[2] += f64::from(measurement.temperature_celsius);
+                window.implausible = window.implausible.or(measurement.implausible);
+                if window.count >= count {
+                    let average = average(window, measurement);
+                    return self.sink.write(&average);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.sink.flush()
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.sink.close()
+    }
+}
+
+/// Make a sink from a spec `KIND=TARGET[,OPTION...]`:
+///
+/// - `csv=PATH` and `json=PATH` append CSV rows or JSON lines to a file, or stdout for `-`
+/// - `influx=HOST:PORT` sends InfluxDB line protocol over UDP
+/// - `prometheus=PATH` keeps a file for the textfile collector of the Prometheus node exporter,
+///   whose tags must be valid label names
+/// - `mqtt=HOST:PORT/TOPIC` publishes JSON objects to an MQTT broker
+/// - `mavlink=HOST:PORT` sends MAVLink messages over UDP, with the `mavlink` feature
+///
+/// The options `every=N` and `average=N` downsample the measurements, see `Downsample`. The
+/// options `batch=N`, `delay_s=SECONDS` and `spool=PATH` put a buffer in front of the sink, which
+/// keeps the measurements while the sink fails, in the spool file if there is one, with those
+/// settings of `Buffering`. The others configure the transform of the sink, see
+/// `Transform::configure()`. MAVLink messages have fixed units, so that sink takes only the
+/// former two.
+#[cfg(feature = "sinks")]
+pub fn open(spec: &str) -> Result<Box<dyn Sink + Send>> {
+    let invalid =
+        |message: String| Error::from(io::Error::new(io::ErrorKind::InvalidInput, message));
+    let mut options: Vec<&str> = spec.split(',').collect();
+    let (kind, target) = options
+        .remove(0)
+        .split_once('=')
+        .ok_or_else(|| invalid(format!("expected KIND=TARGET in sink '{}'", spec)))?;
+
+    let mut downsample = None;
+    let mut buffering: Option<Buffering> = None;
+    let mut transform_options = Vec::new();
+    for option in options {
+        let count = |value: &str| {
+            value
+                .parse()
+                .ok()
+                .filter(|&count| count > 0)
+                .ok_or_else(|| invalid(format!("invalid count '{}'", value)))
+        };
+        match option.split_once('=') {
+            Some(("every", value)) => downsample = Some(Downsample::Decimate(count(value)?)),
+            Some(("average", value)) => downsample = Some(Downsample::Average(count(value)?)),
+            Some(("batch", value)) => {
+                buffering.get_or_insert_with(Buffering::default).batch_size =
+                    count(value)? as usize;
+            }
+            Some(("delay_s", value)) => {
+                buffering.get_or_insert_with(Buffering::default).max_delay = value
+                    .parse()
+                    .ok()
+                    .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                    .ok_or_else(|| invalid(format!("invalid delay '{}'", value)))?;
+            }
+            Some(("spool", path)) if !path.is_empty() => {
+                buffering.get_or_insert_with(Buffering::default).spool = Some(path.into());
+            }
+            _ => transform_options.push(option),
+        }
+    }
+    let options = transform_options;
+
+    fn configured<S: Sink + Send + 'static>(
+        mut sink: S,
+        transform: fn(&mut S) -> &mut Transform,
+        options: &[&str],
+    ) -> Result<Box<dyn Sink + Send>> {
+        transform(&mut sink).configure(options)?;
+        Ok(Box::new(sink))
+    }
+
+    let sink: Result<Box<dyn Sink + Send>> = match kind {
+        "csv" if target == "-" => {
+            configured(CsvSink::new(io::stdout()), |s| &mut s.transform, &options)
+        }
+        "csv" => configured(CsvSink::append(target)?, |s| &mut s.transform, &options),
+        "json" if target == "-" => {
+            configured(JsonSink::new(io::stdout()), |s| &mut s.transform, &options)
+        }
+        "json" => configured(
+            JsonSink::new(append(target)?),
+            |s| &mut s.transform,
+            &options,
+        ),
+        "influx" => configured(
+            InfluxSink::new(UdpWriter::connect(target)?),
+            |s| &mut s.transform,
+            &options,
+        ),
+        "prometheus" => {
+            let mut sink = PrometheusSink::new(target);
+            sink.transform.configure(&options)?;
+            let tags = &sink.transform.tags;
+            if let Some((tag, _)) = tags.iter().find(|(tag, _)| !prometheus_label_name(tag)) {
+                return Err(invalid(format!("invalid Prometheus label name '{}'", tag)));
+            }
+            Ok(Box::new(sink))
+        }
+        "mqtt" => {
+            let (addr, topic) = target
+                .split_once('/')
+                .ok_or_else(|| invalid(format!("expected HOST:PORT/TOPIC in sink '{}'", spec)))?;
+            // Checked before connecting, so bad options don't leave a session behind.
+            let mut transform = Transform::default();
+            transform.configure(&options)?;
+
+            let client_id = format!("bmp280-{}", std::process::id());
+            let mut sink = MqttSink::connect(addr, &client_id, topic)?;
+            sink.transform = transform;
+            Ok(Box::new(sink))
+        }
+        #[cfg(feature = "mavlink")]
+        "mavlink" if options.is_empty() => {
+            Ok(Box::new(crate::mavlink::MavlinkOutput::udp(target)?))
+        }
+        #[cfg(feature = "mavlink")]
+        "mavlink" => Err(invalid("the mavlink sink takes no options".into())),
+        _ => Err(invalid(format!("unknown sink '{}'", kind))),
+    };
+    let sink = match downsample {
+        Some(downsample) => Box::new(Downsampled::new(sink?, downsample)),
+        None => sink?,
+    };
+
+    Ok(match buffering {
+        Some(buffering) => Box::new(buffering.open(sink)?),
+        None => sink,
+    })
+}
+
+fn append(path: impl AsRef<Path>) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// The label of a record, empty if it has none.
+fn label(record: &Record) -> &str {
+    record.label.as_deref().unwrap_or("")
+}
+
+/// The fields of `bmp280 read`, without the sequence number.
+fn all_but_sequence() -> Vec<Field> {
+    vec![Field::Pressure, Field::Altitude, Field::Temperature]
+}
+
+/// Writes every datagram to a connected UDP socket.
+#[cfg(any(feature = "sinks", feature = "mavlink"))]
+pub struct UdpWriter {
+    socket: UdpSocket,
+}
+
+#[cfg(any(feature = "sinks", feature = "mavlink"))]
+impl UdpWriter {
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(addr)?;
+
+        Ok(UdpWriter { socket })
+    }
+}
+
+#[cfg(any(feature = "sinks", feature = "mavlink"))]
+impl Write for UdpWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.socket.send(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes a header row and a row per measurement. By default, the columns are those of
+/// `bmp280 read --format csv` with a label, followed by any tags.
+pub struct CsvSink<W: Write> {
+    writer: W,
+    header_written: bool,
+    pub transform: Transform,
+}
+
+impl<W: Write> CsvSink<W> {
+    pub fn new(writer: W) -> Self {
+        CsvSink {
+            writer,
+            header_written: false,
+            transform: Transform {
+                fields: all_but_sequence(),
+                ..Transform::default()
+            },
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl CsvSink<File> {
+    /// Append to the file at `path`, writing the header only if the file is new or empty.
+    pub fn append(path: impl AsRef<Path>) -> Result<Self> {
+        let file = append(path)?;
+        let header_written = file.metadata()?.len() > 0;
+
+        let mut sink = CsvSink::new(file);
+        sink.header_written = header_written;
+        Ok(sink)
+    }
+}
+
+impl<W: Write> Sink for CsvSink<W> {
+    fn write(&mut self, measurement: &Measurement) -> Result<()> {
+        let record = self.transform.apply(measurement);
+
+        if !self.header_written {
+            let mut header = vec![String::from("label")];
+            header.extend(record.tags.iter().map(|(tag, _)| csv::escape(tag)));
+            header.extend(record.fields.iter().map(|(name, _)| name.clone()));
+            writeln!(self.writer, "{}", header.join(","))?;
+            self.header_written = true;
+        }
+
+        let mut row = vec![csv::escape(label(&record))];
+        row.extend(record.tags.iter().map(|(_, value)| csv::escape(value)));
+        row.extend(record.fields.iter().map(|(_, value)| value.to_string()));
+        writeln!(self.writer, "{}", row.join(","))?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// The record as a JSON object on a single line, as written by `JsonSink`. Tags are strings, and
+/// NaN and infinities are null.
+fn json(record: &Record) -> String {
+    let mut members = Vec::new();
+    if let Some(label) = &record.label {
+        members.push(format!("\"label\":{}", json_string(label)));
+    }
+    for (tag, value) in &record.tags {
+        members.push(format!("{}:{}", json_string(tag), json_string(value)));
+    }
+    for (name, value) in &record.fields {
+        if value.is_finite() {
+            members.push(format!("\"{}\":{}", name, value));
+        } else {
+            members.push(format!("\"{}\":null", name));
+        }
+    }
+    format!("{{{}}}", members.join(","))
+}
+
+/// Writes a JSON object per measurement on a line of its own, like `bmp280 serve`.
+pub struct JsonSink<W: Write> {
+    writer: W,
+    pub transform: Transform,
+}
+
+impl<W: Write> JsonSink<W> {
+    pub fn new(writer: W) -> Self {
+        JsonSink {
+            writer,
+            transform: Transform::default(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> Sink for JsonSink<W> {
+    fn write(&mut self, measurement: &Measurement) -> Result<()> {
+        let record = self.transform.apply(measurement);
+        writeln!(self.writer, "{}", json(&record))?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// Writes InfluxDB line protocol, one line per measurement with the label as the `sensor` tag:
+/// `bmp280,sensor=outdoor pressure_pa=100653,temperature_celsius=25.08 1700000000000000000`.
+/// Pressures are in Pa by default.
+#[cfg(feature = "sinks")]
+pub struct InfluxSink<W: Write> {
+    writer: W,
+    /// The name of the measurement in InfluxDB, `bmp280` by default
+    pub measurement: String,
+    pub transform: Transform,
+}
+
+#[cfg(feature = "sinks")]
+impl<W: Write> InfluxSink<W> {
+    pub fn new(writer: W) -> Self {
+        InfluxSink {
+            writer,
+            measurement: "bmp280".into(),
+            transform: Transform {
+                pressure_unit: PressureUnit::Pa,
+                fields: all_but_sequence(),
+                ..Transform::default()
+            },
+        }
+    }
+}
+
+/// Escape a measurement name, tag or field key for the line protocol.
+#[cfg(feature = "sinks")]
+fn influx_escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace('=', "\\=")
+        .replace(' ', "\\ ")
+}
+
+#[cfg(feature = "sinks")]
+impl<W: Write> Sink for InfluxSink<W> {
+    fn write(&mut self, measurement: &Measurement) -> Result<()> {
+        let record = self.transform.apply(measurement);
+        let mut line = influx_escape(&self.measurement);
+        let label = label(&record);
+        if !label.is_empty() {
+            line.push_str(&format!(",sensor={}", influx_escape(label)));
+        }
+        for (tag, value) in &record.tags {
+            line.push_str(&format!(",{}={}", influx_escape(tag), influx_escape(value)));
+        }
+
+        // The line protocol has no NaN.
+        let fields: Vec<String> = record
+            .fields
+            .iter()
+            .filter(|(_, value)| value.is_finite())
+            .map(|(name, value)| match value {
+                Value::Float(value) => format!("{}={}", influx_escape(name), value),
+                Value::Integer(value) => format!("{}={}i", influx_escape(name), value),
+            })
+            .collect();
+        if fields.is_empty() {
+            // A line needs at least one field.
+            return Ok(());
+        }
+        line.push(' ');
+        line.push_str(&fields.join(","));
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_nanos());
+        line.push_str(&format!(" {}\n", nanos));
+
+        // A single write, so it goes out as a single datagram over UDP.
+        self.writer.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// The name and help text of the Prometheus metric of a field, in the base units Prometheus
+/// prefers where there is one.
+fn prometheus_metric(field: &str) -> (String, &'static str) {
+    match field {
+        "pressure_pa" => ("bmp280_pressure_pascals".into(), "Air pressure"),
+        "pressure_hpa" => ("bmp280_pressure_hectopascals".into(), "Air pressure"),
+        "pressure_kpa" => ("bmp280_pressure_kilopascals".into(), "Air pressure"),
+        "pressure_inhg" => ("bmp280_pressure_inches_of_mercury".into(), "Air pressure"),
+        "temperature_celsius" => ("bmp280_temperature_celsius".into(), "Die temperature"),
+        "altitude_m" => (
+            "bmp280_altitude_meters".into(),
+            "Altitude above the reference pressure",
+        ),
+        "sequence" => ("bmp280_sequence".into(), "Number of the measurement"),
+        "clock_error_us" => (
+            "bmp280_clock_error_microseconds".into(),
+            "Estimated error of the system clock",
+        ),
+        _ => (format!("bmp280_{}", field), "Measured value"),
+    }
+}
+
+/// Whether a tag can be the name of a label of a Prometheus metric, `[a-zA-Z_][a-zA-Z0-9_]*`,
+/// other than `sensor`, which holds the label of the sensor.
+#[cfg(feature = "sinks")]
+fn prometheus_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let first = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    first && chars.all(|c| c.is_ascii_alphanumeric() || c == '_') && name != "sensor"
+}
+
+/// Escape a label value of a Prometheus metric.
+fn prometheus_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Keeps the latest measurement of every sensor in a file for the textfile collector of the
+/// Prometheus node exporter, with the label as the `sensor` label and the tags as labels, so
+/// their names have to be valid label names. Pressures are in Pa by default. The file is replaced on every flush, so the exporter never reads it half written.
+pub struct PrometheusSink {
+    path: PathBuf,
+    latest: Vec<Record>,
+    pub transform: Transform,
+}
+
+impl PrometheusSink {
+    /// Write to `path`, which should end in `.prom` and be in the directory of the collector.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        PrometheusSink {
+            path: path.into(),
+            latest: Vec::new(),
+            transform: Transform {
+                pressure_unit: PressureUnit::Pa,
+                fields: all_but_sequence(),
+                ..Transform::default()
+            },
+        }
+    }
+
+    /// The contents of the file.
+    fn metrics(&self) -> String {
+        let mut names: Vec<&str> = Vec::new();
+        for record in &self.latest {
+            for (name, _) in &record.fields {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+        }
+
+        let mut metrics = String::new();
+        for name in names {
+            let (metric, help) = prometheus_metric(name);
+            metrics.push_str(&format!(
+                "# HELP {} {}\n# TYPE {} gauge\n",
+                metric, help, metric
+            ));
+            for record in &self.latest {
+                let value = record.fields.iter().find(|(n, _)| n == name);
+                let value = match value {
+                    Some((_, value)) if value.is_finite() => value,
+                    _ => continue,
+                };
+
+                let mut labels = Vec::new();
+                if !label(record).is_empty() {
+                    labels.push(format!("sensor=\"{}\"", prometheus_escape(label(record))));
+                }
+                for (tag, tag_value) in &record.tags {
+                    labels.push(format!("{}=\"{}\"", tag, prometheus_escape(tag_value)));
+                }
+
+                if labels.is_empty() {
+                    metrics.push_str(&format!("{} {}\n", metric, value));
+                } else {
+                    metrics.push_str(&format!("{}{{{}}} {}\n", metric, labels.join(","), value));
+                }
+            }
+        }
+        metrics
+    }
+}
+
+impl Sink for PrometheusSink {
+    fn write(&mut self, measurement: &Measurement) -> Result<()> {
+        let record = self.transform.apply(measurement);
+        match self.latest.iter_mut().find(|r| r.label == record.label) {
+            Some(latest) => *latest = record,
+            None => self.latest.push(record),
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+
+        fs::write(&temporary, self.metrics())?;
+        fs::rename(&temporary, &self.path)?;
+        Ok(())
+    }
+}
+
+/// How long to wait for the broker to accept the connection, and for a publish to be sent.
+#[cfg(feature = "sinks")]
+const MQTT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The shortest wait between two attempts to connect to the broker again.
+#[cfg(feature = "sinks")]
+const MQTT_RECONNECT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Publishes every measurement as a JSON object to a topic of an MQTT broker, with MQTT 3.1.1 at
+/// QoS 0, so nothing is resent if the connection drops. A publish that fails is tried once more on
+/// a new connection, and while the broker can't be reached, a new connection is tried at most
+/// every 10 seconds, failing the publishes in between. Put a `buffer::Buffered` in front of the
+/// sink to keep those measurements.
+#[cfg(feature = "sinks")]
+pub struct MqttSink {
+    addrs: Vec<SocketAddr>,
+    /// The CONNECT packet
+    connect: Vec<u8>,
+    /// `None` while disconnected
+    stream: Option<TcpStream>,
+    /// When to try connecting again after a failure
+    reconnect_at: Option<Instant>,
+    /// The topic measurements are published to
+    pub topic: String,
+    /// Whether the broker keeps the last measurement for clients that subscribe later
+    pub retain: bool,
+    pub transform: Transform,
+}
+
+/// Append the variable length "remaining length" of an MQTT packet.
+#[cfg(feature = "sinks")]
+fn mqtt_length(packet: &mut Vec<u8>, mut length: usize) {
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            return;
+        }
+    }
+}
+
+/// Append a string with its 16 bit length, failing for strings too long for that.
+#[cfg(feature = "sinks")]
+fn mqtt_string(packet: &mut Vec<u8>, s: &str) -> io::Result<()> {
+    let length = u16::try_from(s.len()).map_err(|_| {
+        let message = format!("{} bytes are too long for an MQTT string", s.len());
+        io::Error::new(io::ErrorKind::InvalidInput, message)
+    })?;
+    packet.extend_from_slice(&length.to_be_bytes());
+    packet.extend_from_slice(s.as_bytes());
+    Ok(())
+}
+
+/// The largest remaining length of an MQTT packet, which takes 4 bytes.
+#[cfg(feature = "sinks")]
+const MQTT_MAX_LENGTH: usize = 268_435_455;
+
+/// A packet of the given type and flags, e.g. `0x30` for PUBLISH, failing for bodies too long for
+/// one.
+#[cfg(feature = "sinks")]
+fn mqtt_packet(header: u8, body: &[u8]) -> io::Result<Vec<u8>> {
+    if body.len() > MQTT_MAX_LENGTH {
+        let message = format!("{} bytes are too long for an MQTT packet", body.len());
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+    }
+
+    let mut packet = vec![header];
+    mqtt_length(&mut packet, body.len());
+    packet.extend_from_slice(body);
+    Ok(packet)
+}
+
+/// Open a connection to the first of `addrs` that accepts one within `MQTT_TIMEOUT`, and send
+/// the CONNECT packet.
+#[cfg(feature = "sinks")]
+fn mqtt_connect(addrs: &[SocketAddr], connect: &[u8]) -> io::Result<TcpStream> {
+    let mut error = io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "no address for the MQTT broker",
+    );
+    for addr in addrs {
+        let mut stream = match TcpStream::connect_timeout(addr, MQTT_TIMEOUT) {
+            Ok(stream) => stream,
+            Err(e) => {
+                error = e;
+                continue;
+            }
+        };
+        stream.set_read_timeout(Some(MQTT_TIMEOUT))?;
+        stream.set_write_timeout(Some(MQTT_TIMEOUT))?;
+        stream.write_all(connect)?;
+
+        let mut connack = [0; 4];
+        stream.read_exact(&mut connack)?;
+        if connack[0] != 0x20 || connack[3] != 0 {
+            let message = format!("MQTT broker refused the connection (code {})", connack[3]);
+            return Err(io::Error::new(io::ErrorKind::ConnectionRefused, message));
+        }
+        return Ok(stream);
+    }
+    Err(error)
+}
+
+#[cfg(feature = "sinks")]
+impl MqttSink {
+    /// Connect to the broker at `addr` with a clean session.
+    pub fn connect(addr: impl ToSocketAddrs, client_id: &str, topic: &str) -> Result<Self> {
+        let mut connect = Vec::new();
+        mqtt_string(&mut connect, "MQTT")?;
+        // Protocol level 4 (3.1.1), a clean session and no keep alive, so the broker doesn't
+        // expect pings between measurements.
+        connect.extend_from_slice(&[4, 0x02, 0, 0]);
+        mqtt_string(&mut connect, client_id)?;
+        let connect = mqtt_packet(0x10, &connect)?;
+        // Checked before connecting, rather than failing every publish.
+        mqtt_string(&mut Vec::new(), topic)?;
+
+        let addrs: Vec<SocketAddr> = addr.to_socket_addrs()?.collect();
+        let stream = mqtt_connect(&addrs, &connect)?;
+
+        Ok(MqttSink {
+            addrs,
+            connect,
+            stream: Some(stream),
+            reconnect_at: None,
+            topic: topic.into(),
+            retain: false,
+            transform: Transform::default(),
+        })
+    }
+
+    /// The connection to the broker, connecting again if it was lost.
+    fn stream(&mut self) -> io::Result<&mut TcpStream> {
+        if self.stream.is_none() {
+            if self.reconnect_at.is_some_and(|at| Instant::now() < at) {
+                let message = "not connected to the MQTT broker";
+                return Err(io::Error::new(io::ErrorKind::NotConnected, message));
+            }
+            match mqtt_connect(&self.addrs, &self.connect) {
+                Ok(stream) => {
+                    self.stream = Some(stream);
+                    self.reconnect_at = None;
+                }
+                Err(e) => {
+                    self.reconnect_at = Some(Instant::now() + MQTT_RECONNECT_INTERVAL);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(self.stream.as_mut().expect("connected above"))
+    }
+
+    /// Send a packet, on a new connection if sending it on the current one fails.
+    fn send(&mut self, packet: &[u8]) -> io::Result<()> {
+        // A connection the broker dropped is only noticed when writing to it.
+        let connected = self.stream.is_some();
+        match self.send_once(packet) {
+            Err(_) if connected => self.send_once(packet),
+            sent => sent,
+        }
+    }
+
+    fn send_once(&mut self, packet: &[u8]) -> io::Result<()> {
+        let sent = self.stream()?.write_all(packet);
+        if sent.is_err() {
+            self.stream = None;
+        }
+        sent
+    }
+}
+
+#[cfg(feature = "sinks")]
+impl Sink for MqttSink {
+    fn write(&mut self, measurement: &Measurement) -> Result<()> {
+        let mut publish = Vec::new();
+        mqtt_string(&mut publish, &self.topic)?;
+        let record = self.transform.apply(measurement);
+        publish.extend_from_slice(json(&record).as_bytes());
+
+        let header = if self.retain { 0x31 } else { 0x30 };
+        self.send(&mqtt_packet(header, &publish)?)?;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        if let Some(mut stream) = self.stream.take() {
+            stream.write_all(&mqtt_packet(0xE0, &[])?)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,272 @@
+//! Recording and replaying the I2C traffic of a sensor, so data processing changes can be checked
+//! offline against data captured in the field.
+//!
+//! A `Recorder` wraps a device and writes every read and write that succeeded to a trace. A
+//! `Replayer` is a device that plays a trace back: writes are checked against the recorded ones,
+//! and reads return the recorded data. Since the driver issues the same transactions for the same
+//! calls, a replayed sensor returns the same readings as the recorded one, computed by the current
+//! code.
+//!
+//! ```ignore
+//! use bmp280::trace::{Recorder, Replayer};
+//! use bmp280::Bmp280Builder;
+//! use i2cdev::linux::LinuxI2CDevice;
+//!
+//! let device = LinuxI2CDevice::new("/dev/i2c-1", 0x77)?;
+//! let mut sensor = Bmp280Builder::new().build_with(Recorder::create(device, "trace.bin")?)?;
+//! sensor.measure()?;
+//! drop(sensor);
+//!
+//! let mut replayed = Bmp280Builder::new().build_with(Replayer::open("trace.bin")?)?;
+//! println!("{:?}", replayed.measure()?);
+//! ```
+//!
+//! The trace starts with the magic bytes `BMP280TR`, followed by one record per transaction: `R`
+//! or `W`, the length of the data as a little endian u16, and the data.
+
+use crate::Error;
+use i2cdev::core::I2CDevice;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"BMP280TR";
+
+const READ: u8 = b'R';
+const WRITE: u8 = b'W';
+
+/// Whether the error means that a replayed trace has run out of transactions.
+pub fn is_end_of_trace(error: &Error) -> bool {
+    match error.innermost() {
+        Error::IoError(e) => e.kind() == io::ErrorKind::UnexpectedEof,
+        _ => false,
+    }
+}
+
+/// Whether the error means that the driver issued a transaction that doesn't match the trace.
+pub fn is_divergence(error: &Error) -> bool {
+    match error.innermost() {
+        Error::IoError(e) => e.kind() == io::ErrorKind::InvalidData,
+        _ => false,
+    }
+}
+
+/// A device that records the transactions of another device.
+///
+/// SMBus block reads are recorded as the write of the register address and the read they stand
+/// for, so a trace replays the same with and without them. Other SMBus transactions are passed
+/// through without being recorded, since the driver doesn't use them.
+pub struct Recorder<D, W: Write = BufWriter<File>> {
+    device: D,
+    trace: W,
+}
+
+impl<D> Recorder<D> {
+    /// Record to a new file at `path`, replacing an existing one.
+    pub fn create(device: D, path: impl AsRef<Path>) -> io::Result<Self> {
+        Recorder::new(device, BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<D, W: Write> Recorder<D, W> {
+    pub fn new(device: D, mut trace: W) -> io::Result<Self> {
+        trace.write_all(MAGIC)?;
+
+        Ok(Recorder { device, trace })
+    }
+
+    /// Flush the trace and recover the device and the trace writer.
+    pub fn into_inner(mut self) -> io::Result<(D, W)> {
+        self.trace.flush()?;
+
+        Ok((self.device, self.trace))
+    }
+
+    fn record(&mut self, kind: u8, data: &[u8]) -> io::Result<()> {
+        if data.len() > usize::from(u16::MAX) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "transaction too long to record",
+            ));
+        }
+
+        self.trace.write_all(&[kind])?;
+        self.trace.write_all(&(data.len() as u16).to_le_bytes())?;
+        self.trace.write_all(data)
+    }
+}
+
+impl<D, W> I2CDevice for Recorder<D, W>
+where
+    D: I2CDevice,
+    D::Error: From<io::Error>,
+    W: Write,
+{
+    type Error = D::Error;
+
+    fn read(&mut self, data: &mut [u8]) -> Result<(), Self::Error> {
+        self.device.read(data)?;
+        self.record(READ, data)?;
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.device.write(data)?;
+        self.record(WRITE, data)?;
+        Ok(())
+    }
+
+    fn smbus_write_quick(&mut self, bit: bool) -> Result<(), Self::Error> {
+        self.device.smbus_write_quick(bit)
+    }
+
+    fn smbus_read_block_data(&mut self, register: u8) -> Result<Vec<u8>, Self::Error> {
+        self.device.smbus_read_block_data(register)
+    }
+
+    fn smbus_read_i2c_block_data(&mut self, register: u8, len: u8) -> Result<Vec<u8>, Self::Error> {
+        let data = self.device.smbus_read_i2c_block_data(register, len)?;
+        self.record(WRITE, &[register])?;
+        self.record(READ, &data)?;
+        Ok(data)
+    }
+
+    fn smbus_write_block_data(&mut self, register: u8, values: &[u8]) -> Result<(), Self::Error> {
+        self.device.smbus_write_block_data(register, values)
+    }
+
+    fn smbus_write_i2c_block_data(
+        &mut self,
+        register: u8,
+        values: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.device.smbus_write_i2c_block_data(register, values)
+    }
+
+    fn smbus_process_block(&mut self, register: u8, values: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        self.device.smbus_process_block(register, values)
+    }
+}
+
+/// A device that plays back a recorded trace.
+///
+/// Once the trace is exhausted, every transaction fails with an `UnexpectedEof` error, see
+/// `is_end_of_trace()`. A transaction that doesn't match the next record fails with an
+/// `InvalidData` error.
+pub struct Replayer<R: Read = BufReader<File>> {
+    trace: R,
+    transactions: u64,
+}
+
+impl Replayer {
+    /// Play back the trace file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Replayer::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> Replayer<R> {
+    pub fn new(mut trace: R) -> io::Result<Self> {
+        let mut magic = [0; 8];
+        trace.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a BMP280 trace",
+            ));
+        }
+
+        Ok(Replayer {
+            trace,
+            transactions: 0,
+        })
+    }
+
+    /// The number of transactions played back so far.
+    pub fn transactions(&self) -> u64 {
+        self.transactions
+    }
+
+    fn next_record(&mut self, kind: u8, len: usize) -> io::Result<Vec<u8>> {
+        let mut header = [0; 3];
+        self.trace.read_exact(&mut header)?;
+
+        let found_len = usize::from(u16::from_le_bytes([header[1], header[2]]));
+        let mut data = vec![0; found_len];
+        self.trace.read_exact(&mut data)?;
+
+        if header[0] != kind || found_len != len {
+            return Err(self.diverged(format!(
+                "expected {} of {} bytes, recorded {} of {} bytes",
+                kind as char, len, header[0] as char, found_len
+            )));
+        }
+
+        Ok(data)
+    }
+
+    fn diverged(&self, detail: String) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "trace diverged at transaction {}: {}",
+                self.transactions + 1,
+                detail
+            ),
+        )
+    }
+
+    fn unsupported() -> io::Error {
+        io::Error::other("SMBus transactions are not recorded in traces")
+    }
+}
+
+impl<R: Read> I2CDevice for Replayer<R> {
+    type Error = io::Error;
+
+    fn read(&mut self, data: &mut [u8]) -> io::Result<()> {
+        let recorded = self.next_record(READ, data.len())?;
+        data.copy_from_slice(&recorded);
+        self.transactions += 1;
+        Ok(())
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        let recorded = self.next_record(WRITE, data.len())?;
+        if recorded != data {
+            return Err(self.diverged(format!(
+                "expected a write of {:02X?}, recorded {:02X?}",
+                data, recorded
+            )));
+        }
+        self.transactions += 1;
+        Ok(())
+    }
+
+    fn smbus_write_quick(&mut self, _bit: bool) -> io::Result<()> {
+        Err(Self::unsupported())
+    }
+
+    fn smbus_read_block_data(&mut self, _register: u8) -> io::Result<Vec<u8>> {
+        Err(Self::unsupported())
+    }
+
+    fn smbus_read_i2c_block_data(&mut self, register: u8, len: u8) -> io::Result<Vec<u8>> {
+        let mut data = vec![0; usize::from(len)];
+        self.write(&[register])?;
+        self.read(&mut data)?;
+        Ok(data)
+    }
+
+    fn smbus_write_block_data(&mut self, _register: u8, _values: &[u8]) -> io::Result<()> {
+        Err(Self::unsupported())
+    }
+
+    fn smbus_write_i2c_block_data(&mut self, _register: u8, _values: &[u8]) -> io::Result<()> {
+        Err(Self::unsupported())
+    }
+
+    fn smbus_process_block(&mut self, _register: u8, _values: &[u8]) -> io::Result<Vec<u8>> {
+        Err(Self::unsupported())
+    }
+}
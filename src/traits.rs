@@ -0,0 +1,63 @@
+//! Device independent traits for pressure and temperature sensors, in the shape of those of the
+//! generic sensor crates, so logging and sensor fusion code can be written against them instead of
+//! against `Bmp280`. Built with the `sensor-traits` feature.
+//!
+//! ```
+//! use bmp280::emulator::Emulator;
+//! use bmp280::traits::{PressureSensor, TemperatureSensor};
+//! use bmp280::Bmp280Builder;
+//!
+//! fn log<S: PressureSensor + TemperatureSensor>(sensor: &mut S) -> Option<String> {
+//!     let pressure_pa = sensor.read_pressure_pa().ok()?;
+//!     let temperature_celsius = sensor.read_temperature_celsius().ok()?;
+//!     Some(format!("{:.0} Pa {:.2} C", pressure_pa, temperature_celsius))
+//! }
+//!
+//! let mut sensor = Bmp280Builder::new()
+//!     .build_with(Emulator::new())
+//!     .expect("Could not build device");
+//! assert_eq!(log(&mut sensor).unwrap(), "100653 Pa 25.08 C");
+//! ```
+
+use crate::{Bmp280, Error};
+use i2cdev::core::I2CDevice;
+
+/// A sensor of the air pressure.
+pub trait PressureSensor {
+    type Error;
+
+    /// The pressure in Pa.
+    fn read_pressure_pa(&mut self) -> Result<f32, Self::Error>;
+}
+
+/// A sensor of the temperature.
+pub trait TemperatureSensor {
+    type Error;
+
+    /// The temperature in degrees celsius.
+    fn read_temperature_celsius(&mut self) -> Result<f32, Self::Error>;
+}
+
+impl<D> PressureSensor for Bmp280<D>
+where
+    D: I2CDevice,
+    Error: From<D::Error>,
+{
+    type Error = Error;
+
+    fn read_pressure_pa(&mut self) -> Result<f32, Error> {
+        Ok(self.pressure_kpa()? * 1000.)
+    }
+}
+
+impl<D> TemperatureSensor for Bmp280<D>
+where
+    D: I2CDevice,
+    Error: From<D::Error>,
+{
+    type Error = Error;
+
+    fn read_temperature_celsius(&mut self) -> Result<f32, Error> {
+        self.temperature_celsius()
+    }
+}
@@ -0,0 +1,290 @@
+//! Changes made to measurements on their way to a sink: the pressure unit, rounding, which fields
+//! are kept and extra tags. Every sink has a transform of its own, so e.g. an MQTT dashboard can get
+//! hPa with 1 decimal while a CSV log keeps full precision:
+//!
+//! ```
+//! use bmp280::emulator::Emulator;
+//! use bmp280::sink::{JsonSink, Sink};
+//! use bmp280::transform::{Field, PressureUnit};
+//! use bmp280::Bmp280Builder;
+//!
+//! let mut sensor = Bmp280Builder::new()
+//!     .build_with(Emulator::new())
+//!     .expect("Could not build device");
+//!
+//! let mut json = JsonSink::new(Vec::new());
+//! json.transform.pressure_unit = PressureUnit::Hpa;
+//! json.transform.decimals = Some(1);
+//! json.transform.fields = vec![Field::Pressure, Field::Temperature];
+//! json.transform.tags.push(("site".into(), "roof".into()));
+//!
+//! json.write(&sensor.measure().unwrap()).unwrap();
+//! assert_eq!(
+//!     String::from_utf8(json.into_inner()).unwrap(),
+//!     "{\"site\":\"roof\",\"pressure_hpa\":1006.5,\"temperature_celsius\":25.1}\n"
+//! );
+//! ```
+//!
+//! `bmp280 serve --sink` takes the same settings as options after the sink, like
+//! `mqtt=broker:1883/weather,unit=hpa,decimals=1,field=pressure,field=temperature,tag.site=roof`.
+
+use crate::clock::ClockSync;
+use crate::{Error, Measurement, ReferencePressure, Result};
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+/// The unit pressures are written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PressureUnit {
+    Pa,
+    Hpa,
+    Kpa,
+    Inhg,
+}
+
+impl PressureUnit {
+    /// The unit as it is written in field names and options, e.g. `hpa`.
+    pub fn suffix(self) -> &'static str {
+        match self {
+            PressureUnit::Pa => "pa",
+            PressureUnit::Hpa => "hpa",
+            PressureUnit::Kpa => "kpa",
+            PressureUnit::Inhg => "inhg",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "pa" => Some(PressureUnit::Pa),
+            "hpa" => Some(PressureUnit::Hpa),
+            "kpa" => Some(PressureUnit::Kpa),
+            "inhg" => Some(PressureUnit::Inhg),
+            _ => None,
+        }
+    }
+
+    fn convert(self, kpa: f32) -> f32 {
+        match self {
+            PressureUnit::Pa => kpa * 1000.,
+            PressureUnit::Hpa => kpa * 10.,
+            PressureUnit::Kpa => kpa,
+            PressureUnit::Inhg => ReferencePressure::from_kpa(kpa).inhg(),
+        }
+    }
+}
+
+/// A field of a measurement that sinks write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Pressure,
+    Altitude,
+    Temperature,
+    /// The number of the measurement from a sampler, see `Measurement::sequence`
+    Sequence,
+}
+
+impl Field {
+    /// Every field, in the order sinks write them by default.
+    pub const ALL: [Field; 4] = [
+        Field::Pressure,
+        Field::Altitude,
+        Field::Temperature,
+        Field::Sequence,
+    ];
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "pressure" => Some(Field::Pressure),
+            "altitude" => Some(Field::Altitude),
+            "temperature" => Some(Field::Temperature),
+            "sequence" => Some(Field::Sequence),
+            _ => None,
+        }
+    }
+}
+
+/// The value of a field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Float(f32),
+    Integer(u64),
+}
+
+impl Value {
+    /// Whether the value is a number, as NaN and infinities can't be written to every sink.
+    pub fn is_finite(self) -> bool {
+        match self {
+            Value::Float(value) => value.is_finite(),
+            Value::Integer(_) => true,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Float(value) => write!(f, "{}", value),
+            Value::Integer(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// A measurement as a sink writes it, made by `Transform::apply()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub label: Option<Arc<str>>,
+    pub tags: Vec<(String, String)>,
+    /// The fields with their names, e.g. `pressure_hpa`, in the order they are written
+    pub fields: Vec<(String, Value)>,
+}
+
+/// The changes made to measurements written to a sink.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transform {
+    pub pressure_unit: PressureUnit,
+    /// The decimal places values are rounded to, none for full precision. More than `MAX_DECIMALS`
+    /// are no rounding at all, as an f32 doesn't have them.
+    pub decimals: Option<u32>,
+    /// The fields written, in this order. A measurement without a sequence number has no
+    /// `Sequence` field.
+    pub fields: Vec<Field>,
+    /// Tags added to every measurement, such as `site=roof`
+    pub tags: Vec<(String, String)>,
+    /// Whether to tag measurements with the synchronization state of the system clock, as
+    /// `clock=synced`, `clock=unsynced` or `clock=unknown`, and add its estimated error in
+    /// microseconds as the `clock_error_us` field
+    pub clock: bool,
+}
+
+impl Default for Transform {
+    /// All fields in full precision with the pressure in kPa, like the measurement itself.
+    fn default() -> Self {
+        Transform {
+            pressure_unit: PressureUnit::Kpa,
+            decimals: None,
+            fields: Field::ALL.to_vec(),
+            tags: Vec::new(),
+            clock: false,
+        }
+    }
+}
+
+impl Transform {
+    /// The most decimals `configure()` takes.
+    pub const MAX_DECIMALS: u32 = 9;
+
+    pub fn apply(&self, measurement: &Measurement) -> Record {
+        let scale = self
+            .decimals
+            .filter(|&decimals| decimals <= Transform::MAX_DECIMALS)
+            .and_then(|decimals| i32::try_from(decimals).ok())
+            .map(|decimals| 10f32.powi(decimals));
+        let round = |value: f32| match scale {
+            Some(scale) => (value * scale).round() / scale,
+            None => value,
+        };
+
+        let mut fields = Vec::with_capacity(self.fields.len());
+        for field in &self.fields {
+            let (name, value) = match field {
+                Field::Pressure => (
+                    format!("pressure_{}", self.pressure_unit.suffix()),
+                    self.pressure_unit.convert(measurement.pressure_kpa),
+                ),
+                Field::Altitude => ("altitude_m".into(), measurement.altitude_m),
+                Field::Temperature => (
+                    "temperature_celsius".into(),
+                    measurement.temperature_celsius,
+                ),
+                Field::Sequence => {
+                    if let Some(sequence) = measurement.sequence {
+                        fields.push(("sequence".into(), Value::Integer(sequence)));
+                    }
+                    continue;
+                }
+            };
+            fields.push((name, Value::Float(round(value))));
+        }
+
+        let mut tags = self.tags.clone();
+        if self.clock {
+            // The field is there even if the state can't be read, so every record has the same
+            // fields.
+            let (state, error) = match ClockSync::read() {
+                Ok(clock) => {
+                    let state = if clock.synchronized {
+                        "synced"
+                    } else {
+                        "unsynced"
+                    };
+                    let error = Value::Integer(clock.estimated_error.as_micros() as u64);
+                    (state, error)
+                }
+                Err(_) => ("unknown", Value::Float(f32::NAN)),
+            };
+            tags.push(("clock".into(), state.into()));
+            fields.push(("clock_error_us".into(), error));
+        }
+
+        Record {
+            label: measurement.label.clone(),
+            tags,
+            fields,
+        }
+    }
+
+    /// Apply options like `unit=hpa`, `decimals=1`, `field=pressure`, `tag.site=roof` or
+    /// `clock=true`. The first `field` option replaces the fields, and the others add to them.
+    pub fn configure(&mut self, options: &[&str]) -> Result<()> {
+        let invalid =
+            |message: String| Error::from(io::Error::new(io::ErrorKind::InvalidInput, message));
+        let mut fields = None;
+
+        for option in options {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("expected KEY=VALUE in option '{}'", option)))?;
+
+            match key {
+                "unit" => {
+                    self.pressure_unit = PressureUnit::parse(value)
+                        .ok_or_else(|| invalid(format!("unknown pressure unit '{}'", value)))?;
+                }
+                "decimals" => {
+                    let decimals = value
+                        .parse()
+                        .ok()
+                        .filter(|&decimals| decimals <= Transform::MAX_DECIMALS)
+                        .ok_or_else(|| {
+                            invalid(format!(
+                                "invalid decimals '{}', expected 0 to {}",
+                                value,
+                                Transform::MAX_DECIMALS
+                            ))
+                        })?;
+                    self.decimals = Some(decimals);
+                }
+                "clock" => {
+                    self.clock = value
+                        .parse()
+                        .map_err(|_| invalid(format!("expected true or false in '{}'", option)))?;
+                }
+                "field" => {
+                    let field = Field::parse(value)
+                        .ok_or_else(|| invalid(format!("unknown field '{}'", value)))?;
+                    fields.get_or_insert_with(Vec::new).push(field);
+                }
+                _ => match key.strip_prefix("tag.") {
+                    Some(tag) if !tag.is_empty() => self.tags.push((tag.into(), value.into())),
+                    _ => return Err(invalid(format!("unknown option '{}'", key))),
+                },
+            }
+        }
+
+        if let Some(fields) = fields {
+            self.fields = fields;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+//! Rejecting or flagging readings that can't be physically right, e.g. after a partial bus read.
+//!
+//! ```ignore
+//! use bmp280::validate::{Action, Validation};
+//! use bmp280::Bmp280Builder;
+//!
+//! let mut sensor = Bmp280Builder::new()
+//!     .validation(Validation {
+//!         max_pressure_rate_pa_per_s: Some(1000.),
+//!         action: Action::Reject,
+//!         ..Validation::default()
+//!     })
+//!     .build()
+//!     .expect("Could not build device");
+//!
+//! match sensor.measure() {
+//!     Err(bmp280::Error::ImplausibleReading { reason }) => eprintln!("dropped: {}", reason),
+//!     result => println!("{:?}", result),
+//! }
+//! ```
+
+use crate::{Measurement, PRESSURE_RANGE_PA, TEMPERATURE_RANGE_CELSIUS};
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::time::Instant;
+
+/// What to do with an implausible reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Return `Error::ImplausibleReading` instead of the reading
+    Reject,
+    /// Return the reading with `Measurement::implausible` set
+    Flag,
+}
+
+/// Why a reading is implausible.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Implausibility {
+    /// The pressure in Pa is outside the allowed range
+    Pressure(f32),
+    /// The temperature in degrees celsius is outside the allowed range
+    Temperature(f32),
+    /// The pressure changed faster than allowed since the last plausible reading, in Pa/s
+    PressureRate(f32),
+    /// The temperature changed faster than allowed since the last plausible reading, in degrees
+    /// celsius per second
+    TemperatureRate(f32),
+}
+
+impl fmt::Display for Implausibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Implausibility::Pressure(pa) => write!(f, "pressure of {:.2} hPa", pa / 100.),
+            Implausibility::Temperature(celsius) => write!(f, "temperature of {:.2} C", celsius),
+            Implausibility::PressureRate(rate) => {
+                write!(f, "pressure changing by {:.1} Pa/s", rate)
+            }
+            Implausibility::TemperatureRate(rate) => {
+                write!(f, "temperature changing by {:.2} C/s", rate)
+            }
+        }
+    }
+}
+
+/// Which side of the operating range of the sensor a reading is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Below,
+    Above,
+}
+
+/// Where a reading is outside the operating range of the sensor, `PRESSURE_RANGE_PA` and
+/// `TEMPERATURE_RANGE_CELSIUS`, in which the datasheet specifies its accuracy. Outside of it the
+/// readings still follow the pressure and temperature, with unknown accuracy, until the pressure
+/// compensation runs out near 0 Pa.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub pressure: Option<Limit>,
+    pub temperature: Option<Limit>,
+    /// The pressure compensated to 0 Pa or less, so it can't tell how far below 0 Pa it is
+    pub saturated: bool,
+}
+
+impl Limits {
+    /// The limits of a pressure in Pa and a temperature in degrees celsius, as compensated.
+    pub fn of(pressure_pa: f32, temperature_celsius: f32) -> Self {
+        Limits {
+            pressure: limit(&PRESSURE_RANGE_PA, pressure_pa),
+            temperature: limit(&TEMPERATURE_RANGE_CELSIUS, temperature_celsius),
+            saturated: pressure_pa <= 0.,
+        }
+    }
+
+    /// Whether the reading is within the operating range.
+    pub fn is_within(&self) -> bool {
+        *self == Limits::default()
+    }
+}
+
+/// NaN is on neither side.
+fn limit(range: &RangeInclusive<f32>, value: f32) -> Option<Limit> {
+    if value < *range.start() {
+        Some(Limit::Below)
+    } else if value > *range.end() {
+        Some(Limit::Above)
+    } else {
+        None
+    }
+}
+
+/// The limits a reading has to be within. The ranges default to the operating range of the sensor,
+/// and the rates are not checked unless set.
+#[derive(Debug, Clone)]
+pub struct Validation {
+    pub pressure_range_pa: RangeInclusive<f32>,
+    pub temperature_range_celsius: RangeInclusive<f32>,
+    pub max_pressure_rate_pa_per_s: Option<f32>,
+    pub max_temperature_rate_celsius_per_s: Option<f32>,
+    pub action: Action,
+    /// The time, pressure in Pa and temperature of the last plausible reading
+    last: Option<(Instant, f32, f32)>,
+}
+
+impl Default for Validation {
+    fn default() -> Self {
+        Validation {
+            pressure_range_pa: PRESSURE_RANGE_PA,
+            temperature_range_celsius: TEMPERATURE_RANGE_CELSIUS,
+            max_pressure_rate_pa_per_s: None,
+            max_temperature_rate_celsius_per_s: None,
+            action: Action::Reject,
+            last: None,
+        }
+    }
+}
+
+impl Validation {
+    /// Check a reading taken at `at`. The rates are computed against the last reading that passed.
+    pub fn check(&mut self, measurement: &Measurement, at: Instant) -> Option<Implausibility> {
+        let pressure_pa = measurement.pressure_kpa * 1000.;
+        let temperature = measurement.temperature_celsius;
+
+        // NaN is never in range.
+        if !self.pressure_range_pa.contains(&pressure_pa) {
+            return Some(Implausibility::Pressure(pressure_pa));
+        }
+        if !self.temperature_range_celsius.contains(&temperature) {
+            return Some(Implausibility::Temperature(temperature));
+        }
+
+        if let Some((last_at, last_pressure_pa, last_temperature)) = self.last {
+            let seconds = at.saturating_duration_since(last_at).as_secs_f32();
+
+            if seconds > 0. {
+                let pressure_rate = (pressure_pa - last_pressure_pa).abs() / seconds;
+                if self
+                    .max_pressure_rate_pa_per_s
+                    .is_some_and(|max| pressure_rate > max)
+                {
+                    return Some(Implausibility::PressureRate(pressure_rate));
+                }
+
+                let temperature_rate = (temperature - last_temperature).abs() / seconds;
+                if self
+                    .max_temperature_rate_celsius_per_s
+                    .is_some_and(|max| temperature_rate > max)
+                {
+                    return Some(Implausibility::TemperatureRate(temperature_rate));
+                }
+            }
+        }
+
+        self.last = Some((at, pressure_pa, temperature));
+        None
+    }
+
+    /// Forget the last plausible reading, e.g. after a pause in sampling.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
@@ -0,0 +1,94 @@
+//! Climb rates from a stream of altitudes, as shown by the variometer of a glider computer.
+//!
+//! Pulling up trades airspeed for height, which a plain variometer shows as climbing although the
+//! glider gained no energy. Given the airspeed, the climb rate is total energy (TE) compensated:
+//! it is that of the altitude plus the height the kinetic energy would be worth, `v² / 2g`, so a
+//! pull-up reads as no climb at all:
+//!
+//! ```
+//! use bmp280::variometer::Variometer;
+//! use std::time::{Duration, Instant};
+//!
+//! let mut plain = Variometer::default();
+//! let mut te = Variometer::default();
+//!
+//! // A pull-up from 30 m/s, 10 readings a second: the airspeed goes down as the altitude goes up,
+//! // keeping the energy constant.
+//! let start = Instant::now();
+//! for i in 0..50 {
+//!     let airspeed = 30. - i as f32 * 0.2;
+//!     let altitude = 1000. + (30f32.powi(2) - airspeed * airspeed) / (2. * 9.80665);
+//!     let at = start + Duration::from_millis(i * 100);
+//!     plain.update(altitude, None, at);
+//!     te.update(altitude, Some(airspeed), at);
+//! }
+//!
+//! assert!(plain.climb_rate_m_per_s().unwrap() > 4.);
+//! assert!(te.climb_rate_m_per_s().unwrap().abs() < 0.01);
+//! ```
+
+use std::time::{Duration, Instant};
+
+/// The standard acceleration of gravity in m/s²
+const GRAVITY: f32 = 9.80665;
+
+/// Turns altitudes, with airspeeds for TE compensation, into climb rates.
+#[derive(Debug, Clone)]
+pub struct Variometer {
+    /// The time constant of the smoothing of the climb rate. Glider computers use 1 to 3 seconds.
+    pub smoothing: Duration,
+    /// When the last altitude was given, and the energy height it was worth
+    last: Option<(Instant, f32)>,
+    climb_rate: Option<f32>,
+}
+
+impl Default for Variometer {
+    /// Smoothed over 1 second.
+    fn default() -> Self {
+        Variometer {
+            smoothing: Duration::from_secs(1),
+            last: None,
+            climb_rate: None,
+        }
+    }
+}
+
+impl Variometer {
+    /// Take the altitude read at `at`, and the true airspeed in m/s at that time for TE
+    /// compensation, returning the climb rate in m/s. The airspeed should be given for all
+    /// altitudes or none, as the change from one to the other reads as a climb. NaN altitudes
+    /// are skipped.
+    pub fn update(
+        &mut self,
+        altitude_m: f32,
+        airspeed_m_s: Option<f32>,
+        at: Instant,
+    ) -> Option<f32> {
+        if altitude_m.is_nan() {
+            return self.climb_rate;
+        }
+        let energy_m = altitude_m + airspeed_m_s.map_or(0., |v| v * v / (2. * GRAVITY));
+
+        if let Some((last_at, last_energy_m)) = self.last {
+            let seconds = at.saturating_duration_since(last_at).as_secs_f32();
+            if seconds <= 0. {
+                return self.climb_rate;
+            }
+
+            let rate = (energy_m - last_energy_m) / seconds;
+            let alpha = seconds / (self.smoothing.as_secs_f32() + seconds);
+            self.climb_rate = Some(match self.climb_rate {
+                Some(climb_rate) => climb_rate + alpha * (rate - climb_rate),
+                None => rate,
+            });
+        }
+        self.last = Some((at, energy_m));
+
+        self.climb_rate
+    }
+
+    /// The smoothed climb rate in m/s, `None` until two altitudes were given.
+    pub fn climb_rate_m_per_s(&self) -> Option<f32> {
+        self.climb_rate
+    }
+}
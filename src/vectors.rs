@@ -0,0 +1,102 @@
+//! Known good outputs of the compensation formulas, to check changes to them against.
+//!
+//! The first vector is the example of the datasheet, which lists its results as floating point
+//! values (25.08 C and 100653.27 Pa). The integer results of all vectors are those of the 64 bit
+//! reference code of the datasheet for its example calibration.
+//!
+//! ```
+//! use bmp280::vectors::{DATASHEET_CALIBRATION, VECTORS};
+//!
+//! for vector in VECTORS.iter() {
+//!     let cal = DATASHEET_CALIBRATION;
+//!
+//!     let fine = cal.fine_temperature(vector.adc_t).unwrap();
+//!     assert_eq!(fine, vector.t_fine);
+//!
+//!     let (temperature, _) = cal.compensate_temperature(vector.adc_t);
+//!     assert_eq!(temperature, vector.temperature_centi_celsius as f32 / 100.);
+//!
+//!     let pressure = cal.pressure_q24_8(vector.adc_p, fine).unwrap();
+//!     assert_eq!(pressure, vector.pressure_q24_8);
+//! }
+//! ```
+
+use crate::Calibration;
+
+/// The example calibration of the datasheet.
+pub const DATASHEET_CALIBRATION: Calibration = Calibration {
+    dig_t1: 27504,
+    dig_t2: 26435,
+    dig_t3: -1000,
+
+    dig_p1: 36477,
+    dig_p2: -10685,
+    dig_p3: 3024,
+    dig_p4: 2855,
+    dig_p5: 140,
+    dig_p6: -7,
+    dig_p7: 15500,
+    dig_p8: -14600,
+    dig_p9: 6000,
+
+    _dig_h1: 0,
+    _dig_h2: 0,
+    _dig_h3: 0,
+    _dig_h4: 0,
+    _dig_h5: 0,
+    _dig_h6: 0,
+};
+
+/// Raw ADC values and what `DATASHEET_CALIBRATION` compensates them to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Vector {
+    pub adc_t: i32,
+    pub adc_p: i32,
+    pub t_fine: i32,
+    /// The temperature in hundredths of a degree celsius
+    pub temperature_centi_celsius: i32,
+    /// The pressure in Pa as a Q24.8 fixed point value
+    pub pressure_q24_8: i64,
+}
+
+pub const VECTORS: [Vector; 5] = [
+    // The datasheet example
+    Vector {
+        adc_t: 519888,
+        adc_p: 415148,
+        t_fine: 128422,
+        temperature_centi_celsius: 2508,
+        pressure_q24_8: 25767233,
+    },
+    // Freezing temperatures
+    Vector {
+        adc_t: 430000,
+        adc_p: 300000,
+        t_fine: -16244,
+        temperature_centi_celsius: -317,
+        pressure_q24_8: 29533248,
+    },
+    Vector {
+        adc_t: 400000,
+        adc_p: 350000,
+        t_fine: -64736,
+        temperature_centi_celsius: -1264,
+        pressure_q24_8: 27005957,
+    },
+    // Low pressure, as at altitude
+    Vector {
+        adc_t: 560000,
+        adc_p: 480000,
+        t_fine: 192674,
+        temperature_centi_celsius: 3763,
+        pressure_q24_8: 23343943,
+    },
+    // High pressure
+    Vector {
+        adc_t: 540000,
+        adc_p: 250000,
+        t_fine: 160661,
+        temperature_centi_celsius: 3138,
+        pressure_q24_8: 33426032,
+    },
+];
@@ -0,0 +1,237 @@
+//! Hourly and daily summaries of the readings of a weather station, and uploading them as the APRS
+//! weather packets of the Citizen Weather Observer Program (CWOP). Built with the `history` feature,
+//! since the summaries are of `history::Sample`s.
+//!
+//! ```
+//! use bmp280::history::Sample;
+//! use bmp280::weather::{self, Station, HOUR};
+//! use std::time::{Duration, UNIX_EPOCH};
+//!
+//! // A reading every 10 minutes from 2024-07-04 06:00 UTC, with the pressure falling by 1 Pa each.
+//! let start = UNIX_EPOCH + Duration::from_secs(1720072800);
+//! let samples: Vec<Sample> = (0..36)
+//!     .map(|i| Sample {
+//!         at: start + Duration::from_secs(i * 600),
+//!         pressure_pa: 101300. - i as f32,
+//!         temperature_celsius: 20. + i as f32 / 10.,
+//!     })
+//!     .collect();
+//!
+//! let hourly = weather::summaries(&samples, HOUR);
+//! assert_eq!(hourly.len(), 6);
+//! assert_eq!(hourly[5].pressure_pa.min, 101265.);
+//! assert_eq!(hourly[5].pressure_pa.max, 101270.);
+//! assert_eq!(hourly[5].tendency_pa, Some(-18.));
+//!
+//! let station = Station {
+//!     callsign: "CW0001".into(),
+//!     latitude: 37.6188,
+//!     longitude: -122.375,
+//! };
+//! let latest = samples.last().unwrap();
+//! assert_eq!(
+//!     weather::cwop_packet(&station, latest.at, latest.pressure_pa, latest.temperature_celsius),
+//!     "CW0001>APRS,TCPIP*:@041150z3737.13N/12222.50W_.../...g...t074b10127"
+//! );
+//! ```
+//!
+//! The pressure of a CWOP packet is the sea level pressure, see `sea_level_pressure()`. The `cwop`
+//! module, built with the feature of that name, submits the packets to APRS-IS.
+
+use crate::history::{Sample, TENDENCY_PERIOD};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const HOUR: Duration = Duration::from_secs(3600);
+pub const DAY: Duration = Duration::from_secs(24 * 3600);
+
+/// The smallest, largest and mean value of a period.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+}
+
+/// The readings of one period.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    /// The start of the period, a multiple of its length since the Unix epoch, which makes days
+    /// those of UTC
+    pub start: SystemTime,
+    pub samples: usize,
+    pub pressure_pa: Range,
+    pub temperature_celsius: Range,
+    /// The change of the pressure in Pa over the 3 hours up to the last sample of the period,
+    /// `None` if the samples don't go back that far
+    pub tendency_pa: Option<f32>,
+}
+
+/// Accumulates the values of a period.
+#[derive(Debug, Clone, Copy)]
+struct Accumulator {
+    min: f32,
+    max: f32,
+    sum: f64,
+}
+
+impl Accumulator {
+    fn new(value: f32) -> Self {
+        Accumulator {
+            min: value,
+            max: value,
+            sum: value as f64,
+        }
+    }
+
+    fn add(&mut self, value: f32) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value as f64;
+    }
+
+    fn range(&self, samples: usize) -> Range {
+        Range {
+            min: self.min,
+            max: self.max,
+            mean: (self.sum / samples as f64) as f32,
+        }
+    }
+}
+
+/// The start of the period of `period` that `at` is in.
+fn period_start(at: SystemTime, period: Duration) -> SystemTime {
+    let since = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let period = period.as_secs().max(1);
+
+    UNIX_EPOCH + Duration::from_secs(since - since % period)
+}
+
+/// The summaries of the periods of length `period` that have samples, oldest first, from samples
+/// sorted oldest first, as those of `History::samples()`.
+pub fn summaries<'a>(
+    samples: impl IntoIterator<Item = &'a Sample>,
+    period: Duration,
+) -> Vec<Summary> {
+    let samples: Vec<&Sample> = samples.into_iter().collect();
+    let mut summaries = Vec::new();
+
+    let mut i = 0;
+    while i < samples.len() {
+        let start = period_start(samples[i].at, period);
+        let mut pressure = Accumulator::new(samples[i].pressure_pa);
+        let mut temperature = Accumulator::new(samples[i].temperature_celsius);
+
+        let mut end = i + 1;
+        while end < samples.len() && period_start(samples[end].at, period) == start {
+            pressure.add(samples[end].pressure_pa);
+            temperature.add(samples[end].temperature_celsius);
+            end += 1;
+        }
+
+        let count = end - i;
+        summaries.push(Summary {
+            start,
+            samples: count,
+            pressure_pa: pressure.range(count),
+            temperature_celsius: temperature.range(count),
+            tendency_pa: tendency_pa(&samples[..end]),
+        });
+        i = end;
+    }
+
+    summaries
+}
+
+/// The change of the pressure from the latest sample at least 3 hours before the last one, like
+/// `History::tendency_pa()`.
+fn tendency_pa(samples: &[&Sample]) -> Option<f32> {
+    let latest = samples.last()?;
+    let since = latest.at.checked_sub(TENDENCY_PERIOD)?;
+    let earlier = samples.iter().rev().find(|sample| sample.at <= since)?;
+
+    Some(latest.pressure_pa - earlier.pressure_pa)
+}
+
+/// A weather station registered with CWOP.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Station {
+    /// The CWOP ID, like `CW1234`, or the callsign of a radio amateur
+    pub callsign: String,
+    /// In degrees, north positive
+    pub latitude: f64,
+    /// In degrees, east positive
+    pub longitude: f64,
+}
+
+/// The APRS weather report of the station at `at`, with the sea level pressure in Pa and the
+/// temperature. The wind is reported as unknown, since the station doesn't measure it.
+pub fn cwop_packet(
+    station: &Station,
+    at: SystemTime,
+    sea_level_pa: f32,
+    temperature_celsius: f32,
+) -> String {
+    let fahrenheit = (temperature_celsius * 9. / 5. + 32.).round() as i32;
+    // Tenths of hPa, in 5 digits
+    let pressure = ((sea_level_pa / 10.).round() as i32).clamp(0, 99999);
+
+    format!(
+        "{}>APRS,TCPIP*:@{}z{}/{}_.../...g...t{}b{:05}",
+        station.callsign,
+        day_hour_minute(at),
+        coordinate(station.latitude, 2, 'N', 'S'),
+        coordinate(station.longitude, 3, 'E', 'W'),
+        temperature_field(fahrenheit),
+        pressure
+    )
+}
+
+/// A temperature in degrees fahrenheit in the 3 characters of an APRS weather report.
+fn temperature_field(fahrenheit: i32) -> String {
+    if fahrenheit < 0 {
+        format!("-{:02}", (-fahrenheit).min(99))
+    } else {
+        format!("{:03}", fahrenheit.min(999))
+    }
+}
+
+/// A latitude or longitude in the degrees and decimal minutes of APRS, like `3737.13N`.
+fn coordinate(degrees: f64, degree_digits: usize, positive: char, negative: char) -> String {
+    let hemisphere = if degrees < 0. { negative } else { positive };
+    let hundredths = (degrees.abs() * 6000.).round() as u64;
+
+    format!(
+        "{:0width$}{:05.2}{}",
+        hundredths / 6000,
+        (hundredths % 6000) as f64 / 100.,
+        hemisphere,
+        width = degree_digits
+    )
+}
+
+/// The day of the month, hour and minute of a time in UTC, as `DDHHMM`.
+fn day_hour_minute(at: SystemTime) -> String {
+    let seconds = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+
+    format!(
+        "{:02}{:02}{:02}",
+        day_of_month(days as i64),
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
+}
+
+/// The day of the month of a number of days since the Unix epoch, by the algorithm of Howard
+/// Hinnant's `civil_from_days`.
+fn day_of_month(days: i64) -> u32 {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+
+    (day_of_year - (153 * month + 2) / 5 + 1) as u32
+}
@@ -0,0 +1,305 @@
+//! The driver against the emulator: setting up the sensor, forced measurements, and what happens
+//! when the bus or the sensor misbehaves.
+
+use bmp280::config::Mode;
+use bmp280::emulator::Emulator;
+use bmp280::fault::{Faults, Faulty};
+use bmp280::retry::RetryPolicy;
+use bmp280::{Bmp280, Bmp280Builder, Error, RawMeasurement};
+use std::time::Duration;
+
+const CONTROL: u8 = 0xF4;
+const CONFIG: u8 = 0xF5;
+
+fn faulty() -> (Bmp280<Faulty<Emulator>>, Emulator) {
+    let emulator = Emulator::new();
+    let sensor = Bmp280Builder::new()
+        .retry_policy(RetryPolicy {
+            attempts: 3,
+            backoff: Duration::ZERO,
+            ..RetryPolicy::default()
+        })
+        .build_with(Faulty::new(emulator.clone(), Faults::default()))
+        .expect("Could not build device");
+
+    (sensor, emulator)
+}
+
+#[test]
+fn begin_writes_the_settings() {
+    let emulator = Emulator::new();
+    let sensor = Bmp280Builder::new()
+        .build_with(emulator.clone())
+        .expect("Could not build device");
+
+    let settings = sensor.settings();
+    assert_eq!(emulator.register(CONTROL), settings.control_bits());
+    assert_eq!(emulator.register(CONFIG), settings.config_bits());
+    assert_eq!(settings.mode, Mode::Normal);
+}
+
+#[test]
+fn begin_reads_the_calibration() {
+    let mut sensor = Bmp280Builder::new()
+        .build_with(Emulator::new())
+        .expect("Could not build device");
+
+    // The datasheet example
+    let measurement = sensor.measure().unwrap();
+    assert_eq!(measurement.temperature_celsius, 25.08);
+    assert!((measurement.pressure_kpa - 100.6535).abs() < 0.001);
+}
+
+#[test]
+fn begin_rejects_another_chip() {
+    let emulator = Emulator::new();
+    emulator.set_chip_id(0x59);
+
+    match Bmp280Builder::new().build_with(emulator) {
+        Err(Error::ChipIdMismatch { found }) => assert_eq!(found, 0x59),
+        result => panic!("unexpected result {:?}", result.map(|_| ())),
+    }
+}
+
+#[test]
+fn begin_rejects_blank_calibration() {
+    let emulator = Emulator::new();
+    emulator.set_registers(0x8E, &[0, 0]);
+
+    match Bmp280Builder::new().build_with(emulator) {
+        Err(Error::InvalidCalibration { register, value }) => {
+            assert_eq!((register, value), (0x8E, 0))
+        }
+        result => panic!("unexpected result {:?}", result.map(|_| ())),
+    }
+}
+
+#[test]
+fn begin_fails_without_a_sensor() {
+    let emulator = Emulator::new();
+    emulator.disconnect();
+
+    match Bmp280Builder::new().build_with(emulator) {
+        Err(error) => {
+            assert!(matches!(error, Error::Transfer { write: false, .. }));
+            assert_eq!(error.raw_os_error(), Some(libc::ENXIO));
+        }
+        result => panic!("unexpected result {:?}", result.map(|_| ())),
+    }
+}
+
+#[test]
+fn build_rejects_other_addresses() {
+    match Bmp280Builder::new().build_at("/dev/i2c-1", 0x42) {
+        Err(Error::UnexpectedAddress { address }) => assert_eq!(address, 0x42),
+        result => panic!("unexpected result {:?}", result.map(|_| ())),
+    }
+}
+
+#[test]
+fn build_rejects_missing_devices() {
+    match Bmp280Builder::new().build_at("/dev/i2c-does-not-exist", 0x76) {
+        Err(Error::InvalidDevicePath { path, .. }) => {
+            assert_eq!(path.to_str(), Some("/dev/i2c-does-not-exist"))
+        }
+        result => panic!("unexpected result {:?}", result.map(|_| ())),
+    }
+}
+
+#[test]
+fn forced_measurement_waits_for_the_conversion() {
+    let emulator = Emulator::new();
+    let mut sensor = Bmp280Builder::new()
+        .build_with(emulator.clone())
+        .expect("Could not build device");
+    let control = emulator.register(CONTROL);
+
+    emulator.set_raw(RawMeasurement {
+        adc_t: 560000,
+        adc_p: 480000,
+    });
+    emulator.set_conversion_reads(3);
+    let measurement = sensor.forced_measurement().unwrap();
+
+    assert!(measurement.fresh);
+    assert_eq!(measurement.temperature_celsius, 37.63);
+    assert_eq!(sensor.bus_stats().conversions.count(), 1);
+    // Back in normal mode
+    assert_eq!(emulator.register(CONTROL), control);
+}
+
+#[test]
+fn forced_measurement_from_sleep_stays_asleep() {
+    let emulator = Emulator::new();
+    let mut sensor = Bmp280Builder::new()
+        .mode(Mode::Sleep)
+        .build_with(emulator.clone())
+        .expect("Could not build device");
+
+    emulator.set_conversion_reads(2);
+    sensor.forced_measurement().unwrap();
+
+    assert_eq!(Mode::from_bits(emulator.register(CONTROL)), Mode::Sleep);
+}
+
+#[test]
+fn forced_measurement_times_out() {
+    let emulator = Emulator::new();
+    let mut sensor = Bmp280Builder::new()
+        .measurement_timeout(Duration::from_millis(5))
+        .build_with(emulator.clone())
+        .expect("Could not build device");
+
+    emulator.set_conversion_reads(u32::MAX);
+    match sensor.forced_measurement() {
+        Err(Error::MeasurementTimeout { register, waited }) => {
+            assert_eq!(register, 0xF3);
+            assert!(waited >= Duration::from_millis(5));
+        }
+        result => panic!("unexpected result {:?}", result.map(|_| ())),
+    }
+}
+
+#[test]
+fn wait_until_ready_times_out() {
+    let emulator = Emulator::new();
+    let mut sensor = Bmp280Builder::new()
+        .mode(Mode::Sleep)
+        .build_with(emulator.clone())
+        .expect("Could not build device");
+
+    // Start a conversion that never finishes behind the back of the driver.
+    emulator.set_conversion_reads(u32::MAX);
+    let mut device = emulator.clone();
+    i2cdev::core::I2CDevice::smbus_write_byte_data(&mut device, CONTROL, 0b01).unwrap();
+
+    match sensor.wait_until_ready(Duration::from_millis(5)) {
+        Err(Error::MeasurementTimeout { register, .. }) => assert_eq!(register, 0xF3),
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn retries_get_past_transient_naks() {
+    let (mut sensor, _) = faulty();
+
+    // One transfer in 10 fails, which 3 attempts nearly always get past.
+    sensor.inner_mut().set_faults(Faults {
+        nak: 0.1,
+        ..Faults::default()
+    });
+    for _ in 0..20 {
+        sensor.measure().unwrap();
+    }
+
+    let stats = sensor.stats();
+    assert!(stats.retries > 0);
+    assert_eq!(stats.naks, stats.retries);
+    assert_eq!(stats.failures, 0);
+}
+
+#[test]
+fn retries_run_out() {
+    let (mut sensor, _) = faulty();
+
+    sensor.inner_mut().set_faults(Faults {
+        nak: 1.,
+        ..Faults::default()
+    });
+    match sensor.measure() {
+        Err(error @ Error::Transfer { .. }) => {
+            assert_eq!(error.raw_os_error(), Some(libc::ENXIO))
+        }
+        result => panic!("unexpected result {:?}", result.map(|_| ())),
+    }
+
+    let stats = sensor.stats();
+    assert_eq!(stats.failures, 1);
+    assert_eq!(stats.retries, 2);
+    assert_eq!(stats.naks, 3);
+    assert_eq!(sensor.inner().injected().naks, 3);
+}
+
+#[test]
+fn adapter_timeouts_are_timeouts() {
+    let (mut sensor, _) = faulty();
+
+    sensor.inner_mut().set_faults(Faults {
+        timeout: 1.,
+        ..Faults::default()
+    });
+    match sensor.measure() {
+        Err(Error::Timeout { write, .. }) => assert!(!write),
+        result => panic!("unexpected result {:?}", result.map(|_| ())),
+    }
+
+    let stats = sensor.stats();
+    assert_eq!(stats.timeouts, 1);
+    assert_eq!(stats.retries, 2);
+}
+
+#[test]
+fn retrying_stops_at_the_timeout() {
+    let emulator = Emulator::new();
+    let mut sensor = Bmp280Builder::new()
+        .retry_policy(RetryPolicy {
+            attempts: 1000,
+            backoff: Duration::from_millis(2),
+            multiplier: 1,
+            ..RetryPolicy::default()
+        })
+        .timeout(Duration::from_millis(10))
+        .build_with(Faulty::new(emulator, Faults::default()))
+        .expect("Could not build device");
+
+    sensor.inner_mut().set_faults(Faults {
+        nak: 1.,
+        ..Faults::default()
+    });
+    match sensor.measure() {
+        Err(Error::Timeout {
+            elapsed, source, ..
+        }) => {
+            assert!(elapsed < Duration::from_millis(100));
+            // The NAK of the last attempt is kept as the source of the timeout.
+            assert_eq!(source.raw_os_error(), Some(libc::ENXIO));
+        }
+        result => panic!("unexpected result {:?}", result.map(|_| ())),
+    }
+    assert!(sensor.stats().retries < 10);
+}
+
+#[test]
+fn unplugged_sensors_recover() {
+    let (mut sensor, emulator) = faulty();
+
+    emulator.disconnect();
+    assert!(sensor.measure().is_err());
+
+    emulator.connect();
+    assert_eq!(sensor.measure().unwrap().temperature_celsius, 25.08);
+}
+
+#[test]
+fn corrupt_reads_fail_verification() {
+    let emulator = Emulator::new();
+    let mut sensor = Bmp280Builder::new()
+        .verify_writes(true)
+        .build_with(Faulty::new(emulator, Faults::default()))
+        .expect("Could not build device");
+
+    sensor.inner_mut().set_faults(Faults {
+        partial_read: 1.,
+        ..Faults::default()
+    });
+    // The control register reads back as 0xFF after the write.
+    match sensor.apply(|settings| {
+        settings.mode(Mode::Sleep);
+    }) {
+        Err(Error::WriteVerificationFailed { register, read, .. }) => {
+            assert_eq!(register, CONTROL);
+            assert_eq!(read, 0xFF);
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+}